@@ -0,0 +1,362 @@
+//! `render --gif=<file>`: rendered frames written to an animated GIF that
+//! loops forever. `--webp` and `--avif` make far smaller files. Built with
+//! the `webp` and `avif` features they're encoded in process by libwebp and
+//! by rav1e through libavif, and otherwise piped to ffmpeg, if it was built
+//! with those. `--archival` pipes them to ffmpeg's lossless FFV1 codec, for
+//! master copies to grade and edit later.
+//!
+//! GIFs have at most 256 colours per frame, which bands smooth gradients.
+//! Dithering trades the bands for noise: ordered dithering adds a fixed Bayer
+//! pattern, which stays put from frame to frame, while Floyd–Steinberg
+//! spreads each pixel's error onto its neighbours, which looks finer but
+//! crawls in animation. A global palette keeps colours from shifting between
+//! frames, but holds every frame in memory until the end.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use color_quant::NeuQuant;
+use gif::{Encoder, Frame, Repeat, SetParameter};
+use image::RgbaImage;
+
+#[cfg(feature = "avif")]
+use avif::AvifWriter;
+use error::ShadeyError;
+#[cfg(feature = "webp")]
+use webp::WebPWriter;
+
+/// Share of pixels NeuQuant learns from, from 1 for all of them to 30.
+const SAMPLE_FACTOR: i32 = 10;
+
+/// 8×8 Bayer matrix, for ordered dithering.
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21]
+];
+
+#[derive(Copy, Clone)]
+pub enum Dither {
+    None,
+    Ordered,
+    FloydSteinberg
+}
+
+/// How frames are reduced to a palette, from `--gif-colors`, `--gif-palette`
+/// and `--gif-dither`.
+#[derive(Copy, Clone)]
+pub struct GifOptions {
+    colors: usize,
+    global: bool,
+    dither: Dither
+}
+
+impl GifOptions {
+    pub fn parse(colors: &str, palette: &str, dither: &str) -> Result<GifOptions, ShadeyError> {
+        let colors = colors.parse().ok().filter(|colors| (2..=256).contains(colors)).ok_or_else(||
+            ShadeyError::invalid(format!("Invalid GIF colour count `{}`: give 2 to 256.", colors))
+        )?;
+        let global = match palette {
+            "frame" => false,
+            "global" => true,
+            _ => return Err(ShadeyError::invalid(format!(
+                "Invalid GIF palette `{}`. Expected frame or global.", palette
+            )))
+        };
+        let dither = match dither {
+            "none" => Dither::None,
+            "ordered" => Dither::Ordered,
+            "floyd-steinberg" => Dither::FloydSteinberg,
+            _ => return Err(ShadeyError::invalid(format!(
+                "Invalid GIF dithering `{}`. Expected none, ordered or floyd-steinberg.", dither
+            )))
+        };
+
+        Ok(GifOptions { colors, global, dither })
+    }
+}
+
+enum Output {
+    /// Each frame is written as it comes, with its own palette.
+    Frames(Encoder<BufWriter<File>>),
+    /// Frames are kept until the end, when the palette is made from them all.
+    Global(BufWriter<File>, Vec<Vec<u8>>)
+}
+
+pub struct GifWriter {
+    output: Output,
+    path: PathBuf,
+    size: (u16, u16),
+    options: GifOptions,
+    /// Time each frame is shown for, in hundredths of a second.
+    delay: u16
+}
+
+fn encoder(file: BufWriter<File>, size: (u16, u16), palette: &[u8], path: &Path) ->
+    Result<Encoder<BufWriter<File>>, ShadeyError>
+{
+    let mut encoder = Encoder::new(file, size.0, size.1, palette).
+        map_err(|e| ShadeyError::io("Could not write", path, e))?;
+    encoder.set(Repeat::Infinite).map_err(|e| ShadeyError::io("Could not write", path, e))?;
+    Ok(encoder)
+}
+
+/// Palette indices for the opaque RGBA `pixels`, `width` pixels across.
+fn quantize(pixels: &[u8], width: usize, quant: &NeuQuant, palette: &[u8], dither: Dither) -> Vec<u8> {
+    let index = |rgb: [f32; 3]| {
+        let c = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        quant.index_of(&[c(rgb[0]), c(rgb[1]), c(rgb[2]), 255])
+    };
+    let pixel = |i: usize| [f32::from(pixels[i * 4]), f32::from(pixels[i * 4 + 1]), f32::from(pixels[i * 4 + 2])];
+    let count = pixels.len() / 4;
+
+    match dither {
+        Dither::None => (0..count).map(|i| index(pixel(i)) as u8).collect(),
+        Dither::Ordered => {
+            // Roughly the gap between neighbouring palette levels per channel.
+            let spread = 255.0 / (palette.len() as f32 / 3.0).cbrt();
+            (0..count).map(|i| {
+                let offset = (f32::from(BAYER[i / width % 8][i % width % 8]) + 0.5) / 64.0 - 0.5;
+                let rgb = pixel(i);
+                index([rgb[0] + offset * spread, rgb[1] + offset * spread, rgb[2] + offset * spread]) as u8
+            }).collect()
+        },
+        Dither::FloydSteinberg => {
+            let mut error = vec![[0.0f32; 3]; count];
+            let mut indices = Vec::with_capacity(count);
+            for i in 0..count {
+                let (x, rgb) = (i % width, pixel(i));
+                let wanted = [rgb[0] + error[i][0], rgb[1] + error[i][1], rgb[2] + error[i][2]];
+                let chosen = index(wanted);
+                indices.push(chosen as u8);
+
+                let mut spread = |j: usize, weight: f32| if j < count {
+                    for c in 0..3 {
+                        let got = f32::from(palette[chosen * 3 + c]);
+                        error[j][c] += (wanted[c].clamp(0.0, 255.0) - got) * weight;
+                    }
+                };
+                if x + 1 < width {
+                    spread(i + 1, 7.0 / 16.0);
+                    spread(i + width + 1, 1.0 / 16.0);
+                }
+                if x > 0 {
+                    spread(i + width - 1, 3.0 / 16.0);
+                }
+                spread(i + width, 5.0 / 16.0);
+            }
+            indices
+        }
+    }
+}
+
+impl GifWriter {
+    pub fn new(path: &Path, size: (u32, u32), fps: f32, options: GifOptions) -> Result<GifWriter, ShadeyError> {
+        if size.0 > u32::from(u16::MAX) || size.1 > u32::from(u16::MAX) {
+            return Err(ShadeyError::invalid("GIFs can be at most 65535 pixels across."));
+        }
+        let size = (size.0 as u16, size.1 as u16);
+        let file = BufWriter::new(File::create(path).map_err(|e| ShadeyError::io("Could not create", path, e))?);
+        let output = if options.global {
+            Output::Global(file, Vec::new())
+        } else {
+            Output::Frames(encoder(file, size, &[], path)?)
+        };
+
+        // GIF delays are whole hundredths, so frame rates that don't divide
+        // 100 play back slightly off.
+        let delay = (100.0 / fps).round().clamp(1.0, f32::from(u16::MAX)) as u16;
+        if (f32::from(delay) - 100.0 / fps).abs() > 0.01 {
+            eprintln!(
+                "Warning: GIFs can't play at {} fps exactly, so frames will be shown for {} ms each.",
+                fps,
+                u32::from(delay) * 10
+            );
+        }
+
+        Ok(GifWriter { output, path: path.to_path_buf(), size, options, delay })
+    }
+
+    pub fn add(&mut self, img: &RgbaImage) -> Result<(), ShadeyError> {
+        let mut pixels = img.clone().into_raw();
+        // The output is opaque whatever the shader left in alpha.
+        for pixel in pixels.chunks_mut(4) {
+            pixel[3] = 255;
+        }
+
+        match self.output {
+            Output::Frames(ref mut encoder) => {
+                let quant = NeuQuant::new(SAMPLE_FACTOR, self.options.colors, &pixels);
+                let palette = quant.color_map_rgb();
+                let indices = quantize(&pixels, img.width() as usize, &quant, &palette, self.options.dither);
+                let frame = Frame {
+                    delay: self.delay,
+                    width: self.size.0,
+                    height: self.size.1,
+                    palette: Some(palette),
+                    buffer: Cow::Owned(indices),
+                    ..Frame::default()
+                };
+                let path = &self.path;
+                encoder.write_frame(&frame).map_err(|e| ShadeyError::io("Could not write", path, e))
+            },
+            Output::Global(_, ref mut frames) => {
+                frames.push(pixels);
+                Ok(())
+            }
+        }
+    }
+
+    /// Write out the frames held for a global palette.
+    pub fn finish(self) -> Result<(), ShadeyError> {
+        let (file, frames) = match self.output {
+            Output::Frames(_) => return Ok(()),
+            Output::Global(file, frames) => (file, frames)
+        };
+        // About a frame's worth of pixels, taken evenly from all of them.
+        let stride = frames.len().max(1);
+        let mut sample = Vec::new();
+        for (i, frame) in frames.iter().enumerate() {
+            for pixel in frame.chunks(4).skip(i % stride).step_by(stride) {
+                sample.extend_from_slice(pixel);
+            }
+        }
+        let quant = NeuQuant::new(SAMPLE_FACTOR, self.options.colors, &sample);
+        let palette = quant.color_map_rgb();
+
+        let path = &self.path;
+        let mut encoder = encoder(file, self.size, &palette, path)?;
+        for pixels in &frames {
+            let frame = Frame {
+                delay: self.delay,
+                width: self.size.0,
+                height: self.size.1,
+                buffer: Cow::Owned(quantize(pixels, usize::from(self.size.0), &quant, &palette, self.options.dither)),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(|e| ShadeyError::io("Could not write", path, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Format {
+    WebP,
+    Avif,
+    Ffv1
+}
+
+/// Where `--webp`, `--avif` and `--archival` frames go.
+pub enum VideoWriter {
+    Ffmpeg(FfmpegWriter),
+    #[cfg(feature = "webp")]
+    WebP(WebPWriter),
+    #[cfg(feature = "avif")]
+    Avif(AvifWriter)
+}
+
+impl VideoWriter {
+    /// Encode in process where shadey was built to, and through ffmpeg
+    /// otherwise.
+    pub fn new(path: &Path, size: (u32, u32), fps: f32, format: Format) -> Result<VideoWriter, ShadeyError> {
+        Ok(match format {
+            #[cfg(feature = "webp")]
+            Format::WebP => VideoWriter::WebP(WebPWriter::new(path, size, fps)?),
+            #[cfg(feature = "avif")]
+            Format::Avif => VideoWriter::Avif(AvifWriter::new(path, size, fps)?),
+            _ => VideoWriter::Ffmpeg(FfmpegWriter::new(path, size, fps, format)?)
+        })
+    }
+
+    pub fn add(&mut self, img: &RgbaImage) -> Result<(), ShadeyError> {
+        match *self {
+            VideoWriter::Ffmpeg(ref mut writer) => writer.add(img),
+            #[cfg(feature = "webp")]
+            VideoWriter::WebP(ref mut writer) => writer.add(img),
+            #[cfg(feature = "avif")]
+            VideoWriter::Avif(ref mut writer) => writer.add(img)
+        }
+    }
+
+    pub fn finish(self) -> Result<(), ShadeyError> {
+        match self {
+            VideoWriter::Ffmpeg(writer) => writer.finish(),
+            #[cfg(feature = "webp")]
+            VideoWriter::WebP(writer) => writer.finish(),
+            #[cfg(feature = "avif")]
+            VideoWriter::Avif(writer) => writer.finish()
+        }
+    }
+}
+
+/// Frames piped to ffmpeg as raw RGBA.
+pub struct FfmpegWriter {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    path: PathBuf
+}
+
+impl FfmpegWriter {
+    pub fn new(path: &Path, size: (u32, u32), fps: f32, format: Format) -> Result<FfmpegWriter, ShadeyError> {
+        let mut command = Command::new("ffmpeg");
+        command.
+            args(["-hide_banner", "-loglevel", "error", "-y"]).
+            args(["-f", "rawvideo", "-pix_fmt", "rgba"]).
+            arg("-s").arg(format!("{}x{}", size.0, size.1)).
+            arg("-framerate").arg(fps.to_string()).
+            args(["-i", "-"]);
+        // All without alpha, as with GIFs.
+        match format {
+            Format::WebP => command.args([
+                "-pix_fmt", "yuv420p", "-c:v", "libwebp_anim", "-quality", "80", "-loop", "0", "-f", "webp"
+            ]),
+            Format::Avif => command.args(["-pix_fmt", "yuv420p", "-f", "avif"]),
+            // Kept in RGB so nothing is lost to chroma subsampling. Every
+            // frame stands alone and each slice is checksummed, so damage to
+            // the file stays local and can be found.
+            Format::Ffv1 => command.args([
+                "-pix_fmt", "bgr0", "-c:v", "ffv1", "-level", "3", "-g", "1", "-slicecrc", "1", "-f", "matroska"
+            ])
+        };
+        let mut child = command.arg(path).stdin(Stdio::piped()).spawn().map_err(|error| match error.kind() {
+            ErrorKind::NotFound =>
+                ShadeyError::invalid("--webp, --avif and --archival need ffmpeg, which isn't on the PATH."),
+            _ => ShadeyError::Io { action: "Could not run ffmpeg", path: None, error }
+        })?;
+
+        Ok(FfmpegWriter { stdin: child.stdin.take(), child, path: path.to_path_buf() })
+    }
+
+    pub fn add(&mut self, img: &RgbaImage) -> Result<(), ShadeyError> {
+        let stdin = match self.stdin {
+            Some(ref mut stdin) => stdin,
+            None => return Ok(())
+        };
+        if stdin.write_all(img).is_err() {
+            // ffmpeg gave up, and says why when waited on.
+            self.stdin = None;
+        }
+        Ok(())
+    }
+
+    /// Wait for ffmpeg to finish writing the file.
+    pub fn finish(mut self) -> Result<(), ShadeyError> {
+        drop(self.stdin.take());
+        let status = self.child.wait().
+            map_err(|error| ShadeyError::Io { action: "Could not run ffmpeg", path: None, error })?;
+        if !status.success() {
+            return Err(ShadeyError::invalid(format!("ffmpeg could not write {}.", self.path.display())));
+        }
+        Ok(())
+    }
+}
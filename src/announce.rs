@@ -0,0 +1,31 @@
+//! `--announce`: describes what changes in the window as plain lines on
+//! stdout, for screen readers and for driving shadey over SSH, where the
+//! overlay can't be seen. Actions are announced as they happen. Things that
+//! stay on screen, like the palette's selection, are announced whenever what
+//! they show changes, but not as they start out.
+
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub struct Announcer {
+    /// What each watched part of the window showed last.
+    showing: BTreeMap<&'static str, String>
+}
+
+impl Announcer {
+    pub fn new() -> Announcer {
+        Default::default()
+    }
+
+    pub fn say(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    /// Announce `text` as what `part` shows, if it's changed since last time.
+    pub fn show(&mut self, part: &'static str, text: String) {
+        match self.showing.insert(part, text.clone()) {
+            Some(ref last) if *last != text => println!("{}: {}", part, text),
+            _ => ()
+        }
+    }
+}
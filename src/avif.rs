@@ -0,0 +1,244 @@
+//! `render --avif=<file>` encoded in process with rav1e through libavif, when
+//! shadey is built with the `avif` feature.
+//!
+//! libavif has no setters for its encoder's settings, and their order in
+//! `avifEncoder` changes between releases, so the version found at run time
+//! decides where they're written. 0.11 and 1.x are supported; anything else
+//! is refused rather than guessed at. `avifImage` starts the same in all of
+//! them.
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::prelude::*;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+use std::{ptr, slice, thread};
+
+use image::RgbaImage;
+
+use error::ShadeyError;
+
+const RESULT_OK: c_int = 0;
+const CODEC_CHOICE_RAV1E: c_int = 4;
+const PIXEL_FORMAT_YUV420: c_int = 3;
+const PLANES_YUV: u32 = 1;
+/// From 0 for lossless to 63, about as good as WebP at quality 80.
+const QUANTIZERS: (c_int, c_int) = (20, 32);
+/// The same for 1.x, which takes a quality from 0 to 100 instead.
+const QUALITY: c_int = 60;
+/// Frames are counted in thousandths, so rates like 29.97 play exactly.
+const FRAME_DURATION: u64 = 1000;
+
+/// The leading fields of `avifEncoder` in 0.11.
+#[repr(C)]
+struct Encoder0 {
+    codec_choice: c_int,
+    max_threads: c_int,
+    speed: c_int,
+    keyframe_interval: c_int,
+    timescale: u64,
+    min_quantizer: c_int,
+    max_quantizer: c_int
+}
+
+/// The leading fields of `avifEncoder` in 1.x.
+#[repr(C)]
+struct Encoder1 {
+    codec_choice: c_int,
+    max_threads: c_int,
+    speed: c_int,
+    keyframe_interval: c_int,
+    timescale: u64,
+    repetition_count: c_int,
+    extra_layer_count: u32,
+    quality: c_int
+}
+
+/// Only ever used through a pointer, as one of the layouts above.
+enum Encoder {}
+
+/// Which `avifEncoder` layout the loaded libavif has.
+#[derive(Copy, Clone, PartialEq)]
+enum Abi {
+    V0_11,
+    V1
+}
+
+impl Abi {
+    fn detect() -> Result<Abi, ShadeyError> {
+        let version = unsafe { CStr::from_ptr(avifVersion()) }.to_string_lossy().into_owned();
+        let mut parts = version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some("0"), Some("11")) => Ok(Abi::V0_11),
+            (Some("1"), Some(_)) => Ok(Abi::V1),
+            _ => Err(ShadeyError::invalid(format!(
+                "--avif supports libavif 0.11 and 1.x, but found {}. Build without the avif feature to use ffmpeg.",
+                version
+            )))
+        }
+    }
+}
+
+/// The leading fields of `avifImage`.
+#[repr(C)]
+struct Image {
+    width: u32,
+    height: u32,
+    depth: u32,
+    yuv_format: c_int,
+    yuv_range: c_int,
+    yuv_chroma_sample_position: c_int,
+    yuv_planes: [*mut u8; 3],
+    yuv_row_bytes: [u32; 3]
+}
+
+#[repr(C)]
+struct RwData {
+    data: *mut u8,
+    size: usize
+}
+
+#[link(name = "avif")]
+extern "C" {
+    fn avifEncoderCreate() -> *mut Encoder;
+    fn avifEncoderAddImage(encoder: *mut Encoder, image: *const Image, duration: u64, flags: u32) -> c_int;
+    fn avifEncoderFinish(encoder: *mut Encoder, output: *mut RwData) -> c_int;
+    fn avifEncoderDestroy(encoder: *mut Encoder);
+    fn avifImageCreate(width: c_int, height: c_int, depth: c_int, format: c_int) -> *mut Image;
+    /// Returns nothing in 0.11 and an `avifResult` in 1.x.
+    fn avifImageAllocatePlanes(image: *mut Image, planes: u32) -> c_int;
+    fn avifImageDestroy(image: *mut Image);
+    fn avifRWDataFree(data: *mut RwData);
+    fn avifResultToString(result: c_int) -> *const c_char;
+    fn avifVersion() -> *const c_char;
+}
+
+fn check(result: c_int, path: &Path) -> Result<(), ShadeyError> {
+    if result == RESULT_OK {
+        return Ok(());
+    }
+    let reason = unsafe { CStr::from_ptr(avifResultToString(result)) }.to_string_lossy();
+    Err(ShadeyError::invalid(format!("libavif could not encode {}: {}.", path.display(), reason)))
+}
+
+/// Convert `img` to full range BT.601 4:2:0, which is what libavif assumes
+/// for an image it hasn't been told more about. Alpha is dropped.
+fn fill_yuv(img: &RgbaImage, planes: [&mut [u8]; 3], row_bytes: [usize; 3]) {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let rgb = |x: usize, y: usize| {
+        let pixel = &img.get_pixel(x as u32, y as u32).data;
+        (f32::from(pixel[0]), f32::from(pixel[1]), f32::from(pixel[2]))
+    };
+    let byte = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    let [luma, blue, red] = planes;
+
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = rgb(x, y);
+            luma[y * row_bytes[0] + x] = byte(0.299 * r + 0.587 * g + 0.114 * b);
+        }
+    }
+    for y in 0..height.div_ceil(2) {
+        for x in 0..width.div_ceil(2) {
+            // The average of the (up to) four pixels each chroma sample covers.
+            let (mut r, mut g, mut b, mut count) = (0.0, 0.0, 0.0, 0.0);
+            for (px, py) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (px, py) = (x * 2 + px, y * 2 + py);
+                if px < width && py < height {
+                    let pixel = rgb(px, py);
+                    r += pixel.0;
+                    g += pixel.1;
+                    b += pixel.2;
+                    count += 1.0;
+                }
+            }
+            let (r, g, b) = (r / count, g / count, b / count);
+            blue[y * row_bytes[1] + x] = byte(-0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0);
+            red[y * row_bytes[2] + x] = byte(0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0);
+        }
+    }
+}
+
+pub struct AvifWriter {
+    encoder: *mut Encoder,
+    abi: Abi,
+    file: File,
+    path: PathBuf,
+    size: (u32, u32)
+}
+
+impl AvifWriter {
+    pub fn new(path: &Path, size: (u32, u32), fps: f32) -> Result<AvifWriter, ShadeyError> {
+        let abi = Abi::detect()?;
+        let file = File::create(path).map_err(|e| ShadeyError::io("Could not create", path, e))?;
+        let encoder = unsafe { avifEncoderCreate() };
+        if encoder.is_null() {
+            return Err(ShadeyError::invalid("Could not start libavif's encoder."));
+        }
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get()).min(c_int::MAX as usize);
+        let timescale = ((f64::from(fps) * FRAME_DURATION as f64).round() as u64).max(1);
+        unsafe {
+            match abi {
+                Abi::V0_11 => {
+                    let settings = &mut *(encoder as *mut Encoder0);
+                    settings.codec_choice = CODEC_CHOICE_RAV1E;
+                    settings.max_threads = threads as c_int;
+                    settings.timescale = timescale;
+                    settings.min_quantizer = QUANTIZERS.0;
+                    settings.max_quantizer = QUANTIZERS.1;
+                },
+                Abi::V1 => {
+                    let settings = &mut *(encoder as *mut Encoder1);
+                    settings.codec_choice = CODEC_CHOICE_RAV1E;
+                    settings.max_threads = threads as c_int;
+                    settings.timescale = timescale;
+                    settings.quality = QUALITY;
+                }
+            }
+        }
+
+        Ok(AvifWriter { encoder, abi, file, path: path.to_path_buf(), size })
+    }
+
+    pub fn add(&mut self, img: &RgbaImage) -> Result<(), ShadeyError> {
+        let (width, height) = self.size;
+        let image = unsafe { avifImageCreate(width as c_int, height as c_int, 8, PIXEL_FORMAT_YUV420) };
+        if image.is_null() {
+            return Err(ShadeyError::invalid("Could not create an image for libavif."));
+        }
+        let result = unsafe {
+            let allocated = avifImageAllocatePlanes(image, PLANES_YUV);
+            let (planes, rows) = ((*image).yuv_planes, (*image).yuv_row_bytes);
+            if (self.abi == Abi::V1 && allocated != RESULT_OK) || planes.contains(&ptr::null_mut()) {
+                avifImageDestroy(image);
+                return Err(ShadeyError::invalid("libavif could not allocate a frame."));
+            }
+            let rows = [rows[0] as usize, rows[1] as usize, rows[2] as usize];
+            let chroma_height = height.div_ceil(2) as usize;
+            fill_yuv(img, [
+                slice::from_raw_parts_mut(planes[0], rows[0] * height as usize),
+                slice::from_raw_parts_mut(planes[1], rows[1] * chroma_height),
+                slice::from_raw_parts_mut(planes[2], rows[2] * chroma_height)
+            ], rows);
+            let result = avifEncoderAddImage(self.encoder, image, FRAME_DURATION, 0);
+            avifImageDestroy(image);
+            result
+        };
+        check(result, &self.path)
+    }
+
+    pub fn finish(mut self) -> Result<(), ShadeyError> {
+        let mut output = RwData { data: ptr::null_mut(), size: 0 };
+        check(unsafe { avifEncoderFinish(self.encoder, &mut output) }, &self.path)?;
+        let written = self.file.write_all(unsafe { slice::from_raw_parts(output.data, output.size) });
+        unsafe { avifRWDataFree(&mut output) };
+        let path = &self.path;
+        written.map_err(|e| ShadeyError::io("Could not write", path, e))
+    }
+}
+
+impl Drop for AvifWriter {
+    fn drop(&mut self) {
+        unsafe { avifEncoderDestroy(self.encoder) };
+    }
+}
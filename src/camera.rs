@@ -0,0 +1,206 @@
+//! Built-in mouse driven camera for raymarching shaders.
+//!
+//! Dragging with the left mouse button looks around and the scroll wheel
+//! zooms (orbit) or moves along the view direction (fly). Ctrl+arrow keys
+//! and page up and down do the same from the keyboard. The result is
+//! exposed as `uCameraPos`, `uCameraDir` and `uViewMatrix`, the latter being
+//! the usual world-to-camera transform; `transpose(mat3(uViewMatrix))` turns
+//! camera-space rays into world-space ones.
+
+use std::f32::consts::FRAC_PI_2;
+
+use glium::glutin::VirtualKeyCode;
+
+use error::ShadeyError;
+
+/// Radians of rotation per pixel dragged.
+const LOOK_SPEED: f32 = 0.005;
+/// Pixel scroll deltas are converted to lines at this rate.
+const PIXELS_PER_LINE: f32 = 20.0;
+/// How many pixels of dragging a press of an arrow key is worth.
+const KEY_PIXELS: f32 = 20.0;
+/// Keep the pitch just short of straight up/down so the view basis is valid.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraMode {
+    /// Circle around a fixed target.
+    Orbit,
+    /// Free-flying first person camera.
+    Fly
+}
+
+impl CameraMode {
+    pub fn from_name(name: &str) -> Result<CameraMode, ShadeyError> {
+        match name {
+            "orbit" => Ok(CameraMode::Orbit),
+            "fly" => Ok(CameraMode::Fly),
+            _ => Err(ShadeyError::invalid(format!("Unknown camera mode `{}`. Expected orbit or fly.", name)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    mode: CameraMode,
+    yaw: f32,
+    pitch: f32,
+    /// Orbit: the point looked at. Fly: the eye position.
+    anchor: [f32; 3],
+    /// Distance from the target in orbit mode.
+    distance: f32
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CameraUniforms {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub view: [[f32; 4]; 4]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0]
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = dot(a, a).sqrt();
+    [a[0] / len, a[1] / len, a[2] / len]
+}
+
+impl Camera {
+    pub fn new(mode: CameraMode) -> Camera {
+        match mode {
+            CameraMode::Orbit => Camera {
+                mode,
+                yaw: 0.0,
+                pitch: 0.3,
+                anchor: [0.0, 0.0, 0.0],
+                distance: 5.0
+            },
+            CameraMode::Fly => Camera {
+                mode,
+                yaw: 0.0,
+                pitch: 0.0,
+                anchor: [0.0, 1.0, -5.0],
+                distance: 0.0
+            }
+        }
+    }
+
+    fn direction(&self) -> [f32; 3] {
+        [
+            self.pitch.cos() * self.yaw.sin(),
+            -self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos()
+        ]
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        match self.mode {
+            CameraMode::Orbit => {
+                let dir = self.direction();
+                [
+                    self.anchor[0] - dir[0] * self.distance,
+                    self.anchor[1] - dir[1] * self.distance,
+                    self.anchor[2] - dir[2] * self.distance
+                ]
+            },
+            CameraMode::Fly => self.anchor
+        }
+    }
+
+    /// Rotate the camera by a mouse drag of `dx`, `dy` pixels.
+    pub fn drag(&mut self, dx: f32, dy: f32) {
+        self.yaw += dx * LOOK_SPEED;
+        self.pitch = (self.pitch + dy * LOOK_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Look around with Ctrl+arrow keys and zoom or move with page up and
+    /// down. Returns whether `key` did anything.
+    pub fn key(&mut self, key: VirtualKeyCode, ctrl: bool) -> bool {
+        match key {
+            VirtualKeyCode::Left if ctrl => self.drag(-KEY_PIXELS, 0.0),
+            VirtualKeyCode::Right if ctrl => self.drag(KEY_PIXELS, 0.0),
+            VirtualKeyCode::Up if ctrl => self.drag(0.0, -KEY_PIXELS),
+            VirtualKeyCode::Down if ctrl => self.drag(0.0, KEY_PIXELS),
+            VirtualKeyCode::PageUp => self.scroll(1.0),
+            VirtualKeyCode::PageDown => self.scroll(-1.0),
+            _ => return false
+        }
+        true
+    }
+
+    /// Zoom or move in response to the scroll wheel.
+    pub fn scroll(&mut self, lines: f32) {
+        match self.mode {
+            CameraMode::Orbit => {
+                self.distance = (self.distance * 0.9f32.powf(lines)).max(0.01);
+            },
+            CameraMode::Fly => {
+                let dir = self.direction();
+                for (anchor, d) in self.anchor.iter_mut().zip(&dir) {
+                    *anchor += d * lines * 0.5;
+                }
+            }
+        }
+    }
+
+    pub fn scroll_pixels(&mut self, pixels: f32) {
+        self.scroll(pixels / PIXELS_PER_LINE);
+    }
+
+    /// The camera `t` of the way from `self` to `other`. Cameras of different
+    /// modes can't be blended, so those jump half way through.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+        if self.mode != other.mode {
+            return if t < 0.5 { self.clone() } else { other.clone() };
+        }
+
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Camera {
+            mode: self.mode,
+            yaw: mix(self.yaw, other.yaw),
+            pitch: mix(self.pitch, other.pitch),
+            anchor: [
+                mix(self.anchor[0], other.anchor[0]),
+                mix(self.anchor[1], other.anchor[1]),
+                mix(self.anchor[2], other.anchor[2])
+            ],
+            distance: mix(self.distance, other.distance)
+        }
+    }
+
+    pub fn uniforms(&self) -> CameraUniforms {
+        let eye = self.position();
+        let forward = self.direction();
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let up = cross(right, forward);
+
+        // Column-major look-at matrix; the camera looks down -Z.
+        let view = [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [-dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0]
+        ];
+
+        CameraUniforms {
+            position: eye,
+            direction: forward,
+            view
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec3 key;
+// Chroma distance from the key below which pixels are fully transparent.
+uniform float tolerance;
+
+// Just the chroma of BT.601 YCbCr, so shadows on the screen still key out.
+vec2 chroma(vec3 c) {
+    return vec2(
+        -0.168736 * c.r - 0.331264 * c.g + 0.5 * c.b,
+        0.5 * c.r - 0.418688 * c.g - 0.081312 * c.b
+    );
+}
+
+void main() {
+    vec4 c = texture(tex, pos);
+    float d = distance(chroma(c.rgb), chroma(key));
+    // Fade out over a band as wide as the tolerance to soften the edges.
+    float alpha = smoothstep(tolerance, tolerance * 2.0 + 0.001, d);
+    color = vec4(c.rgb, c.a * alpha);
+}
@@ -0,0 +1,63 @@
+//! Green-screen keying of the input image, so compositing shaders can be
+//! tried on footage with a real background without writing a keyer first.
+//!
+//! The keyed image replaces `tex`, with the background made transparent.
+
+use glium::{Display, Program, Surface};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::Texture2d;
+use glium::uniforms::Sampler;
+
+use error::ShadeyError;
+use numbers::Decimal;
+use pipeline;
+
+const CHROMAKEY_SHADER: &str = include_str!("chromakey.frag");
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChromaKey {
+    color: [f32; 3],
+    tolerance: f32
+}
+
+impl ChromaKey {
+    /// Parse `#rrggbb[,tolerance]`, e.g. `#00ff00,0.1`, or `#00ff00;0,1` when
+    /// `decimal` is a comma.
+    pub fn parse(spec: &str, decimal: Decimal) -> Result<ChromaKey, ShadeyError> {
+        let invalid = || ShadeyError::invalid(format!(
+            "Invalid chroma key `{}`. Expected a colour and tolerance, e.g. #00ff00,0.1.",
+            spec
+        ));
+        let (color, tolerance) = match decimal.split_once(spec) {
+            Some((color, tolerance)) => (color, decimal.parse(tolerance).map_err(|_| invalid())?),
+            None => (spec, 0.1)
+        };
+        let hex = color.trim().trim_start_matches('#');
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).
+            map(|c| f32::from(c) / 255.0).
+            map_err(|_| invalid());
+
+        Ok(ChromaKey { color: [channel(0)?, channel(2)?, channel(4)?], tolerance })
+    }
+
+    /// A copy of `image` with the key colour made transparent.
+    pub fn apply(&self, display: &Display, image: &Texture2d) -> Result<Texture2d, ShadeyError> {
+        let program = Program::from_source(display, pipeline::VERTEX_SHADER, CHROMAKEY_SHADER, None).
+            map_err(|e| ShadeyError::compile("the chroma key shader", e))?;
+        let keyed = Texture2d::empty(display, image.width(), image.height()).
+            map_err(|_| ShadeyError::Gl("Could not create the keyed image."))?;
+
+        keyed.as_surface().draw(
+            &pipeline::quad(display)?,
+            NoIndices(PrimitiveType::TrianglesList),
+            &program,
+            &uniform! {tex: Sampler::new(image), key: self.color, tolerance: self.tolerance},
+            &Default::default()
+        ).map_err(|_| ShadeyError::Gl("Could not key the input image."))?;
+
+        Ok(keyed)
+    }
+}
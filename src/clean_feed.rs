@@ -0,0 +1,61 @@
+//! A second, borderless window showing only the shader output, for screen
+//! capture software to grab without picking up any overlays drawn on the
+//! main window.
+
+use glium::{glutin, Display, Surface};
+use glium::texture::{RawImage2d, Texture2d};
+use glium::texture::pixel_buffer::PixelBuffer;
+
+use error::ShadeyError;
+use pipeline::Blitter;
+
+pub struct CleanFeed {
+    display: Display,
+    blitter: Blitter,
+    texture: Option<Texture2d>,
+    /// Readback of the previous frame. Frames are shown one frame late so
+    /// the copy never stalls the main window.
+    pending: Option<PixelBuffer<(u8, u8, u8, u8)>>
+}
+
+impl CleanFeed {
+    pub fn new(events_loop: &glutin::EventsLoop, context: glutin::ContextBuilder) ->
+        Result<CleanFeed, ShadeyError>
+    {
+        let window = glutin::WindowBuilder::new().
+            with_title("Shadey (clean feed)").
+            with_decorations(false);
+        let display = Display::new(window, context, events_loop).
+            map_err(|e| ShadeyError::Context(format!("clean feed window: {}", e)))?;
+        let blitter = Blitter::new(&display)?;
+
+        Ok(CleanFeed {
+            display,
+            blitter,
+            texture: None,
+            pending: None
+        })
+    }
+
+    /// Show the frame queued by the previous call and queue `source`, the
+    /// frame the main window presented.
+    pub fn present(&mut self, source: &Texture2d) -> Result<(), ShadeyError> {
+        if let Some(pending) = self.pending.take() {
+            let image: RawImage2d<u8> = pending.read_as_texture_2d().
+                map_err(|_| ShadeyError::Gl("Could not read back frame for the clean feed."))?;
+            self.texture = Some(Texture2d::new(&self.display, image).
+                map_err(|_| ShadeyError::Gl("Could not upload frame to the clean feed."))?);
+        }
+        self.pending = Some(source.read_to_pixel_buffer());
+
+        let mut target = self.display.draw();
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+        let result = match self.texture {
+            Some(ref texture) => self.blitter.draw(&mut target, texture),
+            None => Ok(())
+        };
+        target.finish().map_err(|_| ShadeyError::Gl("Could not present clean feed frame."))?;
+
+        result
+    }
+}
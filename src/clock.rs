@@ -0,0 +1,170 @@
+//! Frame timing and the wall clock, as exposed to shaders.
+//!
+//! Time is kept in double precision. `iTime` is only a float, which can't
+//! resolve a frame after a day or so, so it's also split into `iTimeHigh`, a
+//! whole number of `--time-wrap` periods (an hour by default), and
+//! `iTimeLow`, the seconds into the current period.
+
+use std::mem;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use error::ShadeyError;
+use pipeline::FrameInputs;
+
+/// What `iTime` counts from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeEpoch {
+    /// The monotonic clock, from when the shader was loaded.
+    Start,
+    /// The system clock, from when the shader was loaded. Follows NTP
+    /// corrections, so it stays in step with other machines over long runs.
+    Wallclock
+}
+
+impl TimeEpoch {
+    pub fn from_name(name: &str) -> Result<TimeEpoch, ShadeyError> {
+        match name {
+            "start" => Ok(TimeEpoch::Start),
+            "wallclock" => Ok(TimeEpoch::Wallclock),
+            _ => Err(ShadeyError::invalid(format!("Unknown time epoch `{}`. Expected start or wallclock.", name)))
+        }
+    }
+}
+
+pub struct Clock {
+    epoch: TimeEpoch,
+    /// Length of the period `iTimeLow` wraps around after.
+    wrap: f64,
+    start: Instant,
+    start_wall: SystemTime,
+    last: f64,
+    frame: i32,
+    frame_rate: f32
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+/// `time` as `(iTimeHigh, iTimeLow)`. Both stay precise in single precision
+/// however long shadey has been running, as long as `wrap` is a whole number
+/// of seconds.
+pub fn split(time: f64, wrap: f64) -> (f32, f32) {
+    let high = (time / wrap).floor() * wrap;
+    (high as f32, (time - high) as f32)
+}
+
+/// Whether GLSL `source` reads `iTime` itself, rather than only the split
+/// uniforms.
+pub fn uses_plain_time(source: &str) -> bool {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    source.match_indices("iTime").any(|(start, name)| {
+        let before = source[..start].chars().next_back();
+        let after = source[start + name.len()..].chars().next();
+        !before.is_some_and(is_identifier) && !after.is_some_and(is_identifier)
+    })
+}
+
+/// Shadertoy's `iDate`: year, month (from 0), day of the month (from 1) and
+/// seconds since midnight, all in local time.
+pub fn date() -> [f32; 4] {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let time = now.as_secs() as libc::time_t;
+    let mut local: libc::tm = unsafe { mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut local) }.is_null() {
+        return [0.0; 4];
+    }
+
+    let midnight = local.tm_hour * 3600 + local.tm_min * 60 + local.tm_sec;
+    [
+        (local.tm_year + 1900) as f32,
+        local.tm_mon as f32,
+        local.tm_mday as f32,
+        midnight as f32 + now.subsec_nanos() as f32 * 1e-9
+    ]
+}
+
+impl Clock {
+    pub fn new(epoch: TimeEpoch, wrap: f64) -> Clock {
+        Clock {
+            epoch,
+            wrap,
+            start: Instant::now(),
+            start_wall: SystemTime::now(),
+            last: 0.0,
+            frame: 0,
+            frame_rate: 0.0
+        }
+    }
+
+    /// Carry on from `time`, e.g. the time a tab was left at.
+    pub fn set_time(&mut self, time: f64) {
+        let elapsed = Duration::from_secs_f64(time.max(0.0));
+        self.start = Instant::now().checked_sub(elapsed).unwrap_or(self.start);
+        self.start_wall = SystemTime::now().checked_sub(elapsed).unwrap_or(self.start_wall);
+    }
+
+    /// The time of the last frame.
+    pub fn time(&self) -> f64 {
+        self.last
+    }
+
+    /// The number of the next frame.
+    pub fn frame(&self) -> i32 {
+        self.frame
+    }
+
+    /// Carry on from the frame after `frame` at `time`, as if never stopped.
+    pub fn resume(&mut self, time: f64, frame: i32) {
+        self.set_time(time);
+        self.last = time;
+        self.frame = frame;
+    }
+
+    fn now(&self) -> f64 {
+        match self.epoch {
+            TimeEpoch::Start => seconds(self.start.elapsed()),
+            // The system clock can be set backwards; hold still until it
+            // catches up rather than running time in reverse.
+            TimeEpoch::Wallclock => SystemTime::now().duration_since(self.start_wall).
+                map(seconds).
+                unwrap_or(0.0).
+                max(self.last)
+        }
+    }
+
+    /// Start the next frame. Mouse and camera are left for the caller.
+    pub fn tick(&mut self) -> FrameInputs {
+        let time = self.now();
+        let delta = if self.frame == 0 { 0.0 } else { (time - self.last) as f32 };
+        if delta > 0.0 {
+            // Smoothed so it's readable when shown on screen.
+            self.frame_rate = if self.frame_rate == 0.0 {
+                1.0 / delta
+            } else {
+                self.frame_rate * 0.9 + 0.1 / delta
+            };
+        }
+
+        let (time_high, time_low) = split(time, self.wrap);
+        let inputs = FrameInputs {
+            time: time as f32,
+            time_high,
+            time_low,
+            time_delta: delta,
+            frame: self.frame,
+            frame_rate: self.frame_rate,
+            date: date(),
+            mouse: [0.0; 4],
+            cursor: [0.0; 2],
+            idle_time: 0.0,
+            camera: None
+        };
+        self.last = time;
+        self.frame += 1;
+
+        inputs
+    }
+}
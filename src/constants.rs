@@ -0,0 +1,97 @@
+//! Constant uniforms given on the command line, e.g.
+//! `--uniform=float:threshold=0.3` or `--uniform=vec3:tint=1,0.5,0.2`.
+
+use std::collections::BTreeMap;
+
+use glium::uniforms::UniformValue;
+
+use error::ShadeyError;
+use numbers::Decimal;
+use pipeline::BUILT_IN_UNIFORMS;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    Float(f32),
+    Int(i32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4])
+}
+
+impl Constant {
+    pub fn uniform(&self) -> UniformValue<'static> {
+        match *self {
+            Constant::Float(v) => UniformValue::Float(v),
+            Constant::Int(v) => UniformValue::SignedInt(v),
+            Constant::Vec2(v) => UniformValue::Vec2(v),
+            Constant::Vec3(v) => UniformValue::Vec3(v),
+            Constant::Vec4(v) => UniformValue::Vec4(v)
+        }
+    }
+}
+
+/// Parse `type:name=value`, where vector components are separated by commas,
+/// or by semicolons when `decimal` is a comma.
+fn parse(spec: &str, decimal: Decimal) -> Result<(String, Constant), ShadeyError> {
+    let example = match decimal {
+        Decimal::Point => "float:threshold=0.3 or vec3:tint=1,0.5,0.2",
+        Decimal::Comma => "float:threshold=0,3 or vec3:tint=1;0,5;0,2"
+    };
+    let invalid = |why: &str| ShadeyError::invalid(format!(
+        "Invalid uniform `{}`: {}. Expected e.g. {}.",
+        spec, why, example
+    ));
+    let (kind, assignment) = spec.split_once(':').ok_or_else(|| invalid("missing type"))?;
+    let (name, value) = assignment.split_once('=').ok_or_else(|| invalid("missing value"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(invalid("missing name"));
+    }
+
+    let floats = |count: usize| -> Result<Vec<f32>, ShadeyError> {
+        let components = decimal.split(value).into_iter().
+            map(|c| decimal.parse::<f32>(c)).
+            collect::<Result<Vec<_>, _>>().
+            map_err(|_| invalid("not a number"))?;
+        if components.len() != count {
+            return Err(invalid(&format!("{} takes {} components", kind, count)));
+        }
+        Ok(components)
+    };
+
+    let constant = match kind.trim() {
+        "float" => Constant::Float(floats(1)?[0]),
+        "int" => Constant::Int(value.trim().parse().map_err(|_| invalid("not an integer"))?),
+        "vec2" => {
+            let v = floats(2)?;
+            Constant::Vec2([v[0], v[1]])
+        },
+        "vec3" => {
+            let v = floats(3)?;
+            Constant::Vec3([v[0], v[1], v[2]])
+        },
+        "vec4" => {
+            let v = floats(4)?;
+            Constant::Vec4([v[0], v[1], v[2], v[3]])
+        },
+        _ => return Err(invalid("the type must be float, int, vec2, vec3 or vec4"))
+    };
+
+    Ok((name.to_string(), constant))
+}
+
+/// Parse every `--uniform` option. Built-in uniforms can't be overridden.
+pub fn parse_all(specs: &[String], decimal: Decimal) -> Result<BTreeMap<String, Constant>, ShadeyError> {
+    let mut constants = BTreeMap::new();
+    for spec in specs {
+        let (name, constant) = parse(spec, decimal)?;
+        if BUILT_IN_UNIFORMS.contains(&name.as_str()) {
+            return Err(ShadeyError::invalid(format!("`{}` is provided by shadey and can't be set.", name)));
+        }
+        if constants.insert(name.clone(), constant).is_some() {
+            return Err(ShadeyError::invalid(format!("Uniform `{}` is given more than once.", name)));
+        }
+    }
+
+    Ok(constants)
+}
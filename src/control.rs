@@ -0,0 +1,228 @@
+//! A control socket every running shadey listens on, so scripts can query it
+//! without scraping logs. `shadey status` sends `status` and prints the reply:
+//!
+//! ```text
+//! {"shader":"blur.frag","uptime":3600.5,"fps":59.94,"error":null,"uniforms":{"radius":2}}
+//! ```
+//!
+//! `shadey quit` sends `quit`, which exits the way closing the window does,
+//! finishing the recording and saving the session and settings first. A
+//! `shadey render` given `--socket` stops after the frame it's on and
+//! finishes its GIF, WebP, AVIF and archival files. The reply only comes
+//! once all that's done, so supervisors can wait on it.
+//!
+//! The socket is `$XDG_RUNTIME_DIR/shadey.sock`, or another path given with
+//! `--socket`. Each connection sends one command on a line and gets one
+//! reply. Connections are answered one at a time, so one that sends nothing
+//! is dropped after `TIMEOUT` rather than holding up the rest.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use error::ShadeyError;
+use events::{push_number, push_string};
+use pipeline::{FrameInputs, Pipeline};
+
+/// How long a connection gets to send its command or take its reply.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Status {
+    shader: String,
+    frame_rate: f32,
+    error: Option<String>,
+    uniforms: BTreeMap<String, f32>,
+    /// Connections waiting for shadey to quit.
+    quitting: Vec<UnixStream>
+}
+
+pub struct ControlServer {
+    path: PathBuf,
+    status: Arc<Mutex<Status>>
+}
+
+/// Where the socket is unless `--socket` says otherwise.
+fn default_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(ref dir) if !dir.is_empty() => Path::new(dir).join("shadey.sock"),
+        _ => PathBuf::from(format!("/tmp/shadey-{}.sock", unsafe { libc::getuid() }))
+    }
+}
+
+fn socket_path(socket: Option<&str>) -> PathBuf {
+    socket.map_or_else(default_path, PathBuf::from)
+}
+
+fn render(status: &Status, uptime: f64) -> String {
+    let mut reply = String::from("{\"shader\":");
+    push_string(&mut reply, &status.shader);
+    reply.push_str(",\"uptime\":");
+    push_number(&mut reply, (uptime * 1000.0).round() / 1000.0);
+    reply.push_str(",\"fps\":");
+    push_number(&mut reply, (f64::from(status.frame_rate) * 100.0).round() / 100.0);
+    reply.push_str(",\"error\":");
+    match status.error {
+        Some(ref error) => push_string(&mut reply, error),
+        None => reply.push_str("null")
+    }
+    reply.push_str(",\"uniforms\":{");
+    for (i, (name, &value)) in status.uniforms.iter().enumerate() {
+        if i > 0 {
+            reply.push(',');
+        }
+        push_string(&mut reply, name);
+        reply.push(':');
+        push_number(&mut reply, f64::from(value));
+    }
+    reply.push_str("}}\n");
+
+    reply
+}
+
+fn respond(mut stream: UnixStream, status: &Mutex<Status>, start: Instant) {
+    if stream.set_read_timeout(Some(TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(TIMEOUT))).is_err() {
+        return;
+    }
+    let mut command = String::new();
+    if BufReader::new(&stream).read_line(&mut command).is_err() {
+        return;
+    }
+
+    let reply = match command.trim() {
+        "status" => match status.lock() {
+            Ok(status) => render(&status, start.elapsed().as_secs_f64()),
+            Err(_) => return
+        },
+        "quit" => {
+            // Answered on exit.
+            if let Ok(mut status) = status.lock() {
+                status.quitting.push(stream);
+            }
+            return;
+        },
+        other => {
+            let mut reply = String::from("{\"error\":");
+            push_string(&mut reply, &format!("unknown command `{}`", other));
+            reply.push_str("}\n");
+            reply
+        }
+    };
+    let _ = stream.write_all(reply.as_bytes());
+}
+
+impl ControlServer {
+    /// Listen on `socket`, or the default path. Other instances may already
+    /// be listening there, so failing to is only a warning.
+    pub fn start(socket: Option<&str>) -> Option<ControlServer> {
+        let path = socket_path(socket);
+        if UnixStream::connect(&path).is_ok() {
+            eprintln!(
+                "Warning: Another shadey is listening on {}, so `shadey status` will reach that one. \
+                 Give this one a --socket of its own to query it.",
+                path.display()
+            );
+            return None;
+        }
+        // Left behind by an instance that didn't exit cleanly.
+        let _ = fs::remove_file(&path);
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Warning: {}", ShadeyError::io("Could not listen for commands on", &path, e));
+                return None;
+            }
+        };
+
+        let status = Arc::new(Mutex::new(Status::default()));
+        let shared = status.clone();
+        let start = Instant::now();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &shared, start);
+            }
+        });
+
+        Some(ControlServer { path, status })
+    }
+
+    fn update<F: FnOnce(&mut Status)>(&self, f: F) {
+        if let Ok(mut status) = self.status.lock() {
+            f(&mut status);
+        }
+    }
+
+    /// Call whenever a shader is loaded.
+    pub fn shader(&self, shader: &Path) {
+        self.update(|status| status.shader = shader.display().to_string());
+    }
+
+    /// Call after every frame.
+    pub fn frame(&self, inputs: &FrameInputs, pipeline: &Pipeline) {
+        self.update(|status| {
+            status.frame_rate = inputs.frame_rate;
+            status.uniforms.clone_from(pipeline.uniforms());
+        });
+    }
+
+    pub fn error(&self, message: &str) {
+        self.update(|status| status.error = Some(message.to_string()));
+    }
+
+    /// Whether `quit` has been sent.
+    pub fn quit_requested(&self) -> bool {
+        self.status.lock().is_ok_and(|status| !status.quitting.is_empty())
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        // Dropped last thing, after the recording and session are saved.
+        if let Ok(mut status) = self.status.lock() {
+            for mut stream in status.quitting.drain(..) {
+                let _ = stream.write_all(b"{\"quit\":true}\n");
+            }
+        }
+    }
+}
+
+/// Send `command` to the shadey listening on `socket`, or the default path,
+/// and return its reply.
+pub fn send(socket: Option<&str>, command: &str) -> Result<String, ShadeyError> {
+    let path = socket_path(socket);
+    let mut stream = UnixStream::connect(&path).
+        map_err(|e| ShadeyError::io("Could not reach a running shadey at", &path, e))?;
+
+    let mut reply = String::new();
+    stream.write_all(format!("{}\n", command).as_bytes()).
+        and_then(|_| stream.read_to_string(&mut reply)).
+        map_err(|e| ShadeyError::io("Could not talk to shadey at", &path, e))?;
+
+    Ok(reply)
+}
+
+/// `shadey status`: print the status of a running instance.
+pub fn status(socket: Option<&str>) -> Result<(), ShadeyError> {
+    print!("{}", send(socket, "status")?);
+    Ok(())
+}
+
+/// `shadey quit`: quit a running instance, returning once it has.
+pub fn quit(socket: Option<&str>) -> Result<(), ShadeyError> {
+    let reply = send(socket, "quit")?;
+    // Nothing comes back if shadey crashed on the way out.
+    if reply.trim().is_empty() {
+        return Err(ShadeyError::invalid("shadey didn't confirm it had quit cleanly."));
+    }
+    Ok(())
+}
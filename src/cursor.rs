@@ -0,0 +1,130 @@
+//! The mouse cursor over the output: hidden with `--hide-cursor`, hidden
+//! after `--cursor-timeout` seconds without moving, or replaced by a shader
+//! with `--cursor-shader`.
+//!
+//! A cursor shader draws a `--cursor-size` square centred on the mouse,
+//! blended over the output. It gets `pos` from 0 to 1 across the square,
+//! `iResolution` as the square's size, `iTime` and `uPressed`, which is 1
+//! while the button is held.
+
+use std::time::{Duration, Instant};
+
+use glium::{glutin, Blend, Display, DrawParameters, Program, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+
+use error::ShadeyError;
+use header;
+use pipeline::{self, Vertex};
+use {read_shader, Args};
+
+struct CursorShader {
+    program: Program,
+    vertex_buffer: VertexBuffer<Vertex>,
+    size: u32
+}
+
+pub struct Cursor {
+    /// Never show the system cursor.
+    hide: bool,
+    timeout: Option<Duration>,
+    last_moved: Instant,
+    /// Whether the system cursor is hidden right now.
+    hidden: bool,
+    shader: Option<CursorShader>
+}
+
+impl Cursor {
+    pub fn new(display: &Display, args: &Args) -> Result<Cursor, ShadeyError> {
+        let timeout = match args.cursor_timeout {
+            Some(seconds) if seconds > 0.0 => Some(Duration::from_secs_f32(seconds)),
+            Some(_) => return Err(ShadeyError::invalid("--cursor-timeout must be a positive number of seconds.")),
+            None => None
+        };
+        let shader = match args.flag_cursor_shader {
+            Some(_) if args.flag_hide_cursor => {
+                return Err(ShadeyError::invalid("--cursor-shader can't be combined with --hide-cursor."));
+            },
+            Some(ref path) => {
+                let source = header::inject(&read_shader(path)?, path)?;
+                Some(CursorShader {
+                    program: Program::from_source(display, pipeline::VERTEX_SHADER, &source, None).
+                        map_err(|e| ShadeyError::compile(path, e))?,
+                    vertex_buffer: VertexBuffer::dynamic(display, &pipeline::rect_vertices([-1.0, -1.0, 1.0, 1.0])).
+                        map_err(|_| ShadeyError::Gl("Could not create the cursor vertex buffer."))?,
+                    size: args.flag_cursor_size
+                })
+            },
+            None => None
+        };
+
+        let mut cursor = Cursor {
+            hide: args.flag_hide_cursor || args.flag_kiosk,
+            timeout,
+            last_moved: Instant::now(),
+            hidden: false,
+            shader
+        };
+        cursor.update(display);
+
+        Ok(cursor)
+    }
+
+    /// Call whenever the mouse moves.
+    pub fn moved(&mut self) {
+        self.last_moved = Instant::now();
+    }
+
+    fn timed_out(&self) -> bool {
+        self.timeout.is_some_and(|timeout| self.last_moved.elapsed() >= timeout)
+    }
+
+    /// Show or hide the system cursor. Call every frame.
+    pub fn update(&mut self, display: &Display) {
+        let hidden = self.hide || self.shader.is_some() || self.timed_out();
+        if hidden == self.hidden {
+            return;
+        }
+
+        let state = if hidden { glutin::CursorState::Hide } else { glutin::CursorState::Normal };
+        if let Err(e) = display.gl_window().set_cursor_state(state) {
+            eprintln!("Warning: Could not change the cursor: {}", e);
+        }
+        // Even on failure, so it isn't retried every frame.
+        self.hidden = hidden;
+    }
+
+    /// Draw the cursor shader, if there is one, around `position` in pixels
+    /// from the bottom left.
+    pub fn draw<S: Surface>(&self, target: &mut S, position: (f32, f32), time: f32, pressed: bool) ->
+        Result<(), ShadeyError>
+    {
+        let shader = match self.shader {
+            Some(ref shader) if !self.timed_out() => shader,
+            _ => return Ok(())
+        };
+
+        // Clip space, so the square can hang off the edges of the window.
+        let (width, height) = target.get_dimensions();
+        let (x, y) = (position.0 / width as f32 * 2.0 - 1.0, position.1 / height as f32 * 2.0 - 1.0);
+        let (half_width, half_height) = (shader.size as f32 / width as f32, shader.size as f32 / height as f32);
+        shader.vertex_buffer.write(&pipeline::rect_vertices([
+            x - half_width,
+            y - half_height,
+            x + half_width,
+            y + half_height
+        ]));
+
+        let uniforms = uniform! {
+            iResolution: [shader.size as f32, shader.size as f32, 1.0],
+            iTime: time,
+            uPressed: if pressed { 1.0f32 } else { 0.0 }
+        };
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        target.draw(&shader.vertex_buffer, NoIndices(PrimitiveType::TrianglesList), &shader.program, &uniforms, &params).
+            map_err(|_| ShadeyError::Gl("Could not draw the cursor."))
+    }
+}
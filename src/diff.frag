@@ -0,0 +1,28 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D a;
+uniform sampler2D b;
+// Horizontal position of the divider, from 0 (left edge) to 1 (right edge).
+uniform float split;
+uniform bool heatmap;
+
+// Black for no difference, then red through yellow to white.
+vec3 heat(float t) {
+    return clamp(vec3(3.0 * t, 3.0 * t - 1.0, 3.0 * t - 2.0), 0.0, 1.0);
+}
+
+void main() {
+    vec4 ca = texture(a, pos);
+    vec4 cb = texture(b, pos);
+
+    if (heatmap) {
+        vec4 d = abs(ca - cb);
+        // The square root makes an off-by-one in 8-bit output visible.
+        color = vec4(heat(sqrt(max(max(d.r, d.g), max(d.b, d.a)))), 1.0);
+    } else {
+        color = pos.x < split ? ca : cb;
+    }
+}
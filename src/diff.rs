@@ -0,0 +1,222 @@
+//! `shadey diff`: renders two shaders over the same image and shows them
+//! side by side, to check that a rewrite still produces the same picture.
+//!
+//! Drag with the left mouse button or press Left and Right to move the
+//! divider, and press H to toggle a heatmap of the absolute difference
+//! between the two.
+//!
+//! Given two images instead, it prints how far apart they are and exits.
+
+use std::path::{Path, PathBuf};
+
+use glium::{glutin, Display, Program, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::Texture2d;
+use glium::uniforms::MinifySamplerFilter;
+
+use announce::Announcer;
+use clock::{Clock, TimeEpoch};
+use constants;
+use error::ShadeyError;
+use metrics::Measure;
+use monitor;
+use offline;
+use overlay::{Overlay, CHAR_SIZE};
+use pipeline::{self, Pipeline, Vertex};
+use project::Project;
+use recorder::texture_image;
+use watcher::{Change, Watcher};
+use {init_display, input_image, texture_from_path, Args, ProgramStatus};
+
+const DIFF_SHADER: &str = include_str!("diff.frag");
+/// How far Left and Right move the divider, as a fraction of the window.
+const SPLIT_STEP: f32 = 0.05;
+
+/// How the comparison is shown. Kept across reloads.
+struct View {
+    split: f32,
+    heatmap: bool,
+    dragging: bool
+}
+
+struct Comparison {
+    vertex_buffer: VertexBuffer<Vertex>,
+    program: Program
+}
+
+impl Comparison {
+    fn new(display: &Display) -> Result<Comparison, ShadeyError> {
+        Ok(Comparison {
+            vertex_buffer: pipeline::quad(display)?,
+            program: Program::from_source(display, pipeline::VERTEX_SHADER, DIFF_SHADER, None).
+                map_err(|e| ShadeyError::compile("the comparison shader", e))?
+        })
+    }
+
+    fn draw<S: Surface>(&self, target: &mut S, a: &Texture2d, b: &Texture2d, view: &View) ->
+        Result<(), ShadeyError>
+    {
+        let uniforms = uniform! {
+            a: a.sampled().minify_filter(MinifySamplerFilter::Linear),
+            b: b.sampled().minify_filter(MinifySamplerFilter::Linear),
+            split: view.split,
+            heatmap: view.heatmap
+        };
+
+        target.draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.program,
+            &uniforms,
+            &Default::default()
+        ).map_err(|_| ShadeyError::Gl("Could not draw comparison."))
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), ShadeyError> {
+    if !args.arg_image_b.is_empty() {
+        return compare_images(args);
+    }
+
+    let mut view = View { split: 0.5, heatmap: false, dragging: false };
+    while compare(args, &mut view)? == ProgramStatus::Reload {}
+
+    Ok(())
+}
+
+/// Print the RMSE and SSIM of two images, and save their heatmap if asked.
+fn compare_images(args: &Args) -> Result<(), ShadeyError> {
+    let events_loop = glutin::EventsLoop::new();
+    let display = offline::hidden_display(&events_loop, args)?;
+    let a = texture_from_path(&display, Path::new(&args.arg_image_a))?;
+    let b = texture_from_path(&display, Path::new(&args.arg_image_b))?;
+
+    let metrics = Measure::new(&display)?.compare(&display, &a, &b)?;
+    println!("RMSE: {:.6}", metrics.rmse);
+    println!("SSIM: {:.6}", metrics.ssim);
+
+    if let Some(ref path) = args.flag_heatmap {
+        let heatmap = Texture2d::empty(&display, a.width(), a.height()).
+            map_err(|_| ShadeyError::Gl("Could not create heatmap texture."))?;
+        let view = View { split: 0.0, heatmap: true, dragging: false };
+        Comparison::new(&display)?.draw(&mut heatmap.as_surface(), &a, &b, &view)?;
+        let path = Path::new(path);
+        texture_image(&heatmap)?.save(path).map_err(|e| ShadeyError::io("Could not write", path, e))?;
+    }
+
+    Ok(())
+}
+
+fn draw_labels(overlay: &mut Overlay, view: &View, width: f32, height: f32) {
+    let white = [1.0, 1.0, 1.0, 1.0];
+    if view.heatmap {
+        overlay.text(CHAR_SIZE, CHAR_SIZE, "|A - B|", white);
+        return;
+    }
+
+    let x = view.split * width;
+    overlay.rect(x - 1.0, 0.0, 2.0, height, white);
+    overlay.text(CHAR_SIZE, CHAR_SIZE, "A", white);
+    overlay.text(width - CHAR_SIZE * 2.0, CHAR_SIZE, "B", white);
+}
+
+fn compare(args: &Args, view: &mut View) -> Result<ProgramStatus, ShadeyError> {
+    let project_a = Project::single(&args.arg_image, &args.arg_shader_a);
+    let project_b = Project::single(&args.arg_image, &args.arg_shader_b);
+    let mut watched = Project::single(&args.arg_image, &args.arg_shader_a);
+    watched.watched.push(PathBuf::from(&args.arg_shader_b));
+    let constants = constants::parse_all(&args.flag_uniform, args.decimal())?;
+    let epoch = TimeEpoch::from_name(&args.flag_time_epoch)?;
+    let mut watcher = Watcher::new(&watched)?;
+
+    let mut events_loop = glutin::EventsLoop::new();
+    let display = init_display(&events_loop, args, monitor::from_args(args)?.as_deref())?;
+    let mut texture = input_image(&display, &project_a.image, args)?;
+    let mut a = Pipeline::new(&display, &project_a)?;
+    let mut b = Pipeline::new(&display, &project_b)?;
+    a.set_constants(constants.clone())?;
+    b.set_constants(constants)?;
+    let comparison = Comparison::new(&display)?;
+    let mut overlay = Overlay::new(&display)?;
+    let announcer = if args.flag_announce { Some(Announcer::new()) } else { None };
+    let announce = |text: &str| if let Some(ref announcer) = announcer {
+        announcer.say(text);
+    };
+
+    let mut clock = Clock::new(epoch, args.time_wrap);
+    let mut cursor = 0.0;
+    loop {
+        // Both shaders see exactly the same inputs.
+        let inputs = clock.tick();
+        a.render(&display, &texture, &inputs)?;
+        b.render(&display, &texture, &inputs)?;
+
+        let mut target = display.draw();
+        let (width, height) = target.get_dimensions();
+        draw_labels(&mut overlay, view, width as f32, height as f32);
+        let drawn = comparison.draw(&mut target, a.output(), b.output(), view).
+            and_then(|_| overlay.draw(&display, &mut target));
+        target.finish().map_err(|_| ShadeyError::Gl("Could not present frame."))?;
+        drawn?;
+
+        let mut status = None;
+        events_loop.poll_events(|event| {
+            let event = match event {
+                glutin::Event::WindowEvent { event, .. } => event,
+                _ => return
+            };
+
+            match event {
+                glutin::WindowEvent::Closed => status = Some(ProgramStatus::Done),
+                glutin::WindowEvent::MouseMoved { position: (x, _), .. } => cursor = x as f32,
+                glutin::WindowEvent::MouseInput { state, button: glutin::MouseButton::Left, .. } => {
+                    view.dragging = state == glutin::ElementState::Pressed;
+                    if !view.dragging {
+                        announce(&format!("Divider at {:.0}%", view.split * 100.0));
+                    }
+                },
+                glutin::WindowEvent::KeyboardInput {
+                    input: glutin::KeyboardInput {
+                        state: glutin::ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } => match key {
+                    glutin::VirtualKeyCode::H => {
+                        view.heatmap = !view.heatmap;
+                        announce(if view.heatmap { "Heatmap shown" } else { "Heatmap hidden" });
+                    },
+                    glutin::VirtualKeyCode::Left | glutin::VirtualKeyCode::Right => {
+                        let step = if key == glutin::VirtualKeyCode::Left { -SPLIT_STEP } else { SPLIT_STEP };
+                        view.split = (view.split + step).clamp(0.0, 1.0);
+                        announce(&format!("Divider at {:.0}%", view.split * 100.0));
+                    },
+                    _ => ()
+                },
+                _ => ()
+            }
+            if view.dragging {
+                view.split = (cursor / width as f32).clamp(0.0, 1.0);
+            }
+        });
+        if let Some(status) = status {
+            return Ok(status);
+        }
+
+        match watcher.poll()? {
+            Some(Change::Project) => return Ok(ProgramStatus::Reload),
+            Some(Change::Image) => {
+                match input_image(&display, &project_a.image, args) {
+                    Ok(new_texture) => {
+                        texture = new_texture;
+                        a.invalidate();
+                        b.invalidate();
+                    },
+                    Err(e) => eprintln!("Warning: {}", e)
+                }
+            },
+            None => ()
+        }
+    }
+}
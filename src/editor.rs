@@ -0,0 +1,237 @@
+//! F2 opens a small editor over the left of the window for quick fixes to
+//! the running shader without switching to another program. Arrow keys,
+//! Home, End, Page Up and Page Down move around, Ctrl+S saves, which reloads
+//! the shader as usual, and F2 or Escape close it again.
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use glium::glutin::{ModifiersState, VirtualKeyCode};
+
+use error::ShadeyError;
+use overlay::{Overlay, CHAR_SIZE};
+
+/// Spaces inserted for the tab key.
+const TAB: &str = "    ";
+/// Widest the pane gets, in characters including the line numbers.
+const MAX_COLUMNS: usize = 100;
+
+#[derive(Default)]
+pub struct Editor {
+    open: bool,
+    path: Option<PathBuf>,
+    lines: Vec<String>,
+    /// Line and character the cursor is at.
+    cursor: (usize, usize),
+    /// First line and column shown.
+    scroll: (usize, usize),
+    /// Lines moved by Page Up and Page Down, as of the last draw.
+    page: usize,
+    /// Whether there are unsaved changes.
+    dirty: bool
+}
+
+fn byte_offset(line: &str, column: usize) -> usize {
+    line.char_indices().nth(column).map_or(line.len(), |(i, _)| i)
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor { lines: vec![String::new()], page: 1, ..Default::default() }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), ShadeyError> {
+        let mut contents = String::new();
+        File::open(path).
+            and_then(|mut file| file.read_to_string(&mut contents)).
+            map_err(|e| ShadeyError::io("Could not open shader", path, e))?;
+
+        self.lines = contents.lines().map(str::to_string).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        if self.path.as_deref() != Some(path) {
+            self.cursor = (0, 0);
+            self.scroll = (0, 0);
+        }
+        self.path = Some(path.to_path_buf());
+        self.dirty = false;
+        self.clamp();
+
+        Ok(())
+    }
+
+    /// Open or close the editor on `shader`.
+    pub fn toggle(&mut self, shader: &Path) {
+        if self.open {
+            self.open = false;
+            return;
+        }
+        if self.path.as_deref() != Some(shader) || !self.dirty {
+            if let Err(e) = self.load(shader) {
+                eprintln!("Warning: {}", e);
+                return;
+            }
+        }
+        self.open = true;
+    }
+
+    /// Call when the shader is (re)loaded. Picks up changes from disk unless
+    /// there are unsaved edits, and switches over if it's another file.
+    pub fn follow(&mut self, shader: &Path) {
+        if !self.open || (self.dirty && self.path.as_deref() == Some(shader)) {
+            return;
+        }
+        if let Err(e) = self.load(shader) {
+            eprintln!("Warning: {}", e);
+            self.open = false;
+        }
+    }
+
+    fn save(&mut self) {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return
+        };
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+
+        match File::create(&path).and_then(|mut file| file.write_all(contents.as_bytes())) {
+            Ok(()) => {
+                self.dirty = false;
+                println!("Saved {}", path.display());
+            },
+            Err(e) => eprintln!("Warning: {}", ShadeyError::io("Could not write shader", &path, e))
+        }
+    }
+
+    fn line_length(&self, line: usize) -> usize {
+        self.lines[line].chars().count()
+    }
+
+    /// Keep the cursor within the text.
+    fn clamp(&mut self) {
+        let line = self.cursor.0.min(self.lines.len() - 1);
+        self.cursor = (line, self.cursor.1.min(self.line_length(line)));
+    }
+
+    fn insert(&mut self, text: &str) {
+        let (line, column) = self.cursor;
+        let at = byte_offset(&self.lines[line], column);
+        self.lines[line].insert_str(at, text);
+        self.cursor.1 += text.chars().count();
+        self.dirty = true;
+    }
+
+    pub fn character(&mut self, c: char) {
+        if !c.is_control() {
+            self.insert(&c.to_string());
+        }
+    }
+
+    pub fn key(&mut self, key: VirtualKeyCode, modifiers: ModifiersState) {
+        let (line, column) = self.cursor;
+        match key {
+            VirtualKeyCode::S if modifiers.ctrl => self.save(),
+            VirtualKeyCode::Escape => self.open = false,
+            VirtualKeyCode::Left if column > 0 => self.cursor.1 -= 1,
+            VirtualKeyCode::Left if line > 0 => self.cursor = (line - 1, self.line_length(line - 1)),
+            VirtualKeyCode::Right if column < self.line_length(line) => self.cursor.1 += 1,
+            VirtualKeyCode::Right if line + 1 < self.lines.len() => self.cursor = (line + 1, 0),
+            VirtualKeyCode::Up => self.cursor.0 = line.saturating_sub(1),
+            VirtualKeyCode::Down => self.cursor.0 = line + 1,
+            VirtualKeyCode::PageUp => self.cursor.0 = line.saturating_sub(self.page),
+            VirtualKeyCode::PageDown => self.cursor.0 = line + self.page,
+            VirtualKeyCode::Home => self.cursor.1 = 0,
+            VirtualKeyCode::End => self.cursor.1 = self.line_length(line),
+            VirtualKeyCode::Tab => self.insert(TAB),
+            VirtualKeyCode::Return => {
+                let at = byte_offset(&self.lines[line], column);
+                let rest = self.lines[line].split_off(at);
+                self.lines.insert(line + 1, rest);
+                self.cursor = (line + 1, 0);
+                self.dirty = true;
+            },
+            VirtualKeyCode::Back if column > 0 => {
+                let at = byte_offset(&self.lines[line], column - 1);
+                self.lines[line].remove(at);
+                self.cursor.1 -= 1;
+                self.dirty = true;
+            },
+            VirtualKeyCode::Back if line > 0 => {
+                let removed = self.lines.remove(line);
+                self.cursor = (line - 1, self.line_length(line - 1));
+                self.lines[line - 1].push_str(&removed);
+                self.dirty = true;
+            },
+            VirtualKeyCode::Delete if column < self.line_length(line) => {
+                let at = byte_offset(&self.lines[line], column);
+                self.lines[line].remove(at);
+                self.dirty = true;
+            },
+            VirtualKeyCode::Delete if line + 1 < self.lines.len() => {
+                let next = self.lines.remove(line + 1);
+                self.lines[line].push_str(&next);
+                self.dirty = true;
+            },
+            _ => ()
+        }
+        self.clamp();
+    }
+
+    pub fn draw(&mut self, overlay: &mut Overlay, width: f32, height: f32) {
+        if !self.open {
+            return;
+        }
+
+        let margin = CHAR_SIZE;
+        let line_height = CHAR_SIZE * 1.25;
+        let number_width = self.lines.len().to_string().len() + 1;
+        let columns = (((width * 0.5 - margin) / CHAR_SIZE) as usize).clamp(number_width + 10, MAX_COLUMNS);
+        let text_columns = columns - number_width;
+        // One row for the title.
+        let rows = (((height - margin * 2.0) / line_height) as usize).max(2) - 1;
+        self.page = rows.max(1);
+
+        let (line, column) = self.cursor;
+        if line < self.scroll.0 {
+            self.scroll.0 = line;
+        } else if line >= self.scroll.0 + rows {
+            self.scroll.0 = line + 1 - rows;
+        }
+        if column < self.scroll.1 {
+            self.scroll.1 = column;
+        } else if column >= self.scroll.1 + text_columns {
+            self.scroll.1 = column + 1 - text_columns;
+        }
+
+        let panel_width = (columns as f32 + 1.0) * CHAR_SIZE;
+        let panel_height = line_height * (rows + 1) as f32 + CHAR_SIZE * 0.5;
+        overlay.rect(margin, margin, panel_width, panel_height, [0.05, 0.05, 0.05, 0.9]);
+
+        let name = self.path.as_ref().map_or_else(String::new, |path| path.display().to_string());
+        let title = format!("{}{}  Ctrl+S save, F2 close", name, if self.dirty { " *" } else { "" });
+        let left = margin + CHAR_SIZE * 0.5;
+        let top = margin + CHAR_SIZE * 0.25;
+        overlay.text(left, top, &title.chars().take(columns).collect::<String>(), [0.6, 0.8, 1.0, 1.0]);
+
+        for (row, text) in self.lines.iter().enumerate().skip(self.scroll.0).take(rows) {
+            let y = top + line_height * (row - self.scroll.0 + 1) as f32;
+            let number = format!("{:>1$}", row + 1, number_width - 1);
+            overlay.text(left, y, &number, [0.45, 0.45, 0.45, 1.0]);
+
+            let visible: String = text.chars().skip(self.scroll.1).take(text_columns).collect();
+            let text_left = left + number_width as f32 * CHAR_SIZE;
+            overlay.text(text_left, y, &visible, [1.0, 1.0, 1.0, 1.0]);
+            if row == line {
+                let x = text_left + (column - self.scroll.1) as f32 * CHAR_SIZE;
+                overlay.rect(x, y, CHAR_SIZE * 0.15, CHAR_SIZE, [1.0, 0.8, 0.2, 1.0]);
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glium::program::ProgramCreationError;
+use image::ImageError;
+
+#[derive(Debug)]
+pub enum ShadeyError {
+    /// A file or device couldn't be read or written. `action` reads like
+    /// "Could not open shader".
+    Io { action: &'static str, path: Option<PathBuf>, error: io::Error },
+    /// The input image couldn't be decoded.
+    Image { path: PathBuf, error: ImageError },
+    /// A project, settings or snapshot file is malformed.
+    Parse { path: PathBuf, message: String },
+    /// A shader failed to compile or link.
+    Compile { shader: String, log: String, diagnostics: Vec<Diagnostic> },
+    /// The window or OpenGL context couldn't be created.
+    Context(String),
+    /// An OpenGL resource couldn't be created or used.
+    Gl(&'static str),
+    /// A background thread (e.g. the recording encoder) went away.
+    Worker(&'static str),
+    /// Bad arguments or project contents.
+    Invalid(String)
+}
+
+/// One message from a shader compile log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: Option<u32>,
+    pub message: String
+}
+
+/// Find the line number in a line of a compile log. Drivers disagree on the
+/// format: Mesa writes `0:12(5): error: ...`, NVIDIA `0(12) : error C0000: ...`
+/// and AMD `ERROR: 0:12: ...`.
+fn locate(text: &str) -> Option<(u32, &str)> {
+    let body = ["ERROR: ", "WARNING: "].iter().
+        find(|prefix| text.starts_with(*prefix)).
+        map_or(text, |prefix| &text[prefix.len()..]);
+
+    let source_end = body.find(|c: char| !c.is_ascii_digit())?;
+    if source_end == 0 {
+        return None;
+    }
+    if !body[source_end..].starts_with([':', '(']) {
+        return None;
+    }
+    let rest = &body[source_end + 1..];
+
+    let line_end = rest.find(|c: char| !c.is_ascii_digit())?;
+    let line = rest[..line_end].parse().ok()?;
+    let message = rest[line_end..].split_once(": ")?.1;
+
+    Some((line, message.trim()))
+}
+
+fn parse_log(log: &str) -> Vec<Diagnostic> {
+    log.lines().
+        map(str::trim).
+        filter(|text| !text.is_empty()).
+        map(|text| match locate(text) {
+            Some((line, message)) => Diagnostic { line: Some(line), message: message.to_string() },
+            None => Diagnostic { line: None, message: text.to_string() }
+        }).
+        collect()
+}
+
+impl ShadeyError {
+    pub fn io(action: &'static str, path: &Path, error: io::Error) -> ShadeyError {
+        ShadeyError::Io { action, path: Some(path.to_path_buf()), error }
+    }
+
+    /// `shader` names the shader in messages, usually its path.
+    pub fn compile(shader: &str, error: ProgramCreationError) -> ShadeyError {
+        let log = match error {
+            ProgramCreationError::CompilationError(log) |
+            ProgramCreationError::LinkingError(log) => log,
+            other => other.to_string()
+        };
+
+        ShadeyError::Compile {
+            shader: shader.to_string(),
+            diagnostics: parse_log(&log),
+            log
+        }
+    }
+
+    pub fn invalid<S: Into<String>>(message: S) -> ShadeyError {
+        ShadeyError::Invalid(message.into())
+    }
+}
+
+impl fmt::Display for ShadeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShadeyError::Io { action, path: Some(ref path), ref error } =>
+                write!(f, "{} {}: {}", action, path.display(), error),
+            ShadeyError::Io { action, path: None, ref error } =>
+                write!(f, "{}: {}", action, error),
+            ShadeyError::Image { ref path, ref error } =>
+                write!(f, "Could not decode image {}: {}", path.display(), error),
+            ShadeyError::Parse { ref path, ref message } =>
+                write!(f, "Could not parse {}: {}", path.display(), message),
+            ShadeyError::Compile { ref shader, ref log, ref diagnostics } => {
+                write!(f, "Could not compile {}:", shader)?;
+                if diagnostics.is_empty() {
+                    return write!(f, " {}", log.trim());
+                }
+                // `file:line: message` lets editors jump straight to the error.
+                for diagnostic in diagnostics {
+                    match diagnostic.line {
+                        Some(line) => write!(f, "\n{}:{}: {}", shader, line, diagnostic.message)?,
+                        None => write!(f, "\n{}", diagnostic.message)?
+                    }
+                }
+                Ok(())
+            },
+            ShadeyError::Context(ref message) =>
+                write!(f, "Could not create the OpenGL context: {}", message),
+            ShadeyError::Gl(message) | ShadeyError::Worker(message) => f.write_str(message),
+            ShadeyError::Invalid(ref message) => f.write_str(message)
+        }
+    }
+}
+
+impl Error for ShadeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ShadeyError::Io { ref error, .. } => Some(error),
+            ShadeyError::Image { ref error, .. } => Some(error),
+            _ => None
+        }
+    }
+}
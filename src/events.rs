@@ -0,0 +1,90 @@
+//! `--event-log`: one JSON object per line for every notable event, so long
+//! unattended runs can be audited afterwards. Every line has the Unix `time`,
+//! the `uptime` in seconds and the `event`, plus fields of its own:
+//!
+//! ```text
+//! {"time":1760000000.123,"uptime":3600.5,"event":"resize","width":1920,"height":1080}
+//! ```
+
+use std::fmt::Write as FmtWrite;
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use error::ShadeyError;
+
+pub enum Field<'a> {
+    Str(&'a str),
+    Num(f64)
+}
+
+pub struct EventLog {
+    file: LineWriter<File>,
+    start: Instant,
+    /// Stop writing after the first failure rather than warning every frame.
+    failed: bool
+}
+
+pub fn push_string(line: &mut String, value: &str) {
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(line, "\\u{:04x}", c as u32); },
+            c => line.push(c)
+        }
+    }
+    line.push('"');
+}
+
+pub fn push_number(line: &mut String, value: f64) {
+    if value.is_finite() {
+        let _ = write!(line, "{}", value);
+    } else {
+        line.push_str("null");
+    }
+}
+
+impl EventLog {
+    /// Append to the log at `path`, creating it if needed.
+    pub fn open(path: &str) -> Result<EventLog, ShadeyError> {
+        let file = OpenOptions::new().create(true).append(true).open(path).
+            map_err(|e| ShadeyError::io("Could not open event log", Path::new(path), e))?;
+
+        Ok(EventLog { file: LineWriter::new(file), start: Instant::now(), failed: false })
+    }
+
+    pub fn log(&mut self, event: &str, fields: &[(&str, Field)]) {
+        if self.failed {
+            return;
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+        let mut line = String::from("{\"time\":");
+        push_number(&mut line, (time * 1000.0).round() / 1000.0);
+        line.push_str(",\"uptime\":");
+        push_number(&mut line, (self.start.elapsed().as_secs_f64() * 1000.0).round() / 1000.0);
+        line.push_str(",\"event\":");
+        push_string(&mut line, event);
+        for (name, value) in fields {
+            line.push(',');
+            push_string(&mut line, name);
+            line.push(':');
+            match *value {
+                Field::Str(value) => push_string(&mut line, value),
+                Field::Num(value) => push_number(&mut line, value)
+            }
+        }
+        line.push_str("}\n");
+
+        if let Err(e) = self.file.write_all(line.as_bytes()) {
+            eprintln!("Warning: Could not write to the event log, giving up on it: {}", e);
+            self.failed = true;
+        }
+    }
+}
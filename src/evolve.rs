@@ -0,0 +1,257 @@
+//! `shadey evolve`: shows a grid of variants of a project's uniforms and
+//! breeds new ones from those you like.
+//!
+//! Click variants to mark them as favourites and press Enter to replace the
+//! rest with their children. From the keyboard, the arrow keys move between
+//! variants and Space marks one. S prints the favourites as TOML, ready to
+//! paste into the project file. Only uniforms declared with a range evolve.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use glium::{glutin, Surface};
+
+use announce::Announcer;
+use clock::{Clock, TimeEpoch};
+use constants;
+use error::ShadeyError;
+use explore::{self, Rng};
+use grid::Grid;
+use monitor;
+use overlay::{Overlay, CHAR_SIZE};
+use pipeline::Pipeline;
+use watcher::{Change, Watcher};
+use {init_display, input_image, load_project, Args, ProgramStatus};
+
+/// The variants on screen. Kept across reloads.
+struct Population {
+    generation: u32,
+    variants: Vec<BTreeMap<String, f32>>,
+    favourites: BTreeSet<usize>,
+    /// The variant picked with the arrow keys, once they've been used.
+    focus: Option<usize>,
+    rng: Rng
+}
+
+impl Population {
+    /// The project's own values alongside random ones.
+    fn new(uniforms: &BTreeMap<String, f32>, ranges: &BTreeMap<String, (f32, f32)>, count: usize) -> Population {
+        let mut rng = Rng::new();
+        let original = ranges.keys().
+            filter_map(|name| uniforms.get(name).map(|&value| (name.clone(), value))).
+            collect();
+        let mut variants = vec![original];
+        while variants.len() < count {
+            variants.push(explore::random_variant(ranges, &mut rng));
+        }
+
+        Population { generation: 1, variants, favourites: BTreeSet::new(), focus: None, rng }
+    }
+
+    fn toggle(&mut self, index: usize) {
+        if !self.favourites.remove(&index) {
+            self.favourites.insert(index);
+        }
+    }
+
+    /// A variant, in words, for `--announce`.
+    fn describe(&self, index: usize) -> String {
+        let favourite = if self.favourites.contains(&index) { ", favourite" } else { "" };
+        format!("Variant {} of {}{}", index + 1, self.variants.len(), favourite)
+    }
+
+    fn next_generation(&mut self, ranges: &BTreeMap<String, (f32, f32)>) {
+        if self.favourites.is_empty() {
+            eprintln!("Warning: Mark at least one variant to breed from first.");
+            return;
+        }
+
+        let parents: Vec<_> = self.favourites.iter().map(|&i| self.variants[i].clone()).collect();
+        self.variants = explore::breed(&parents, ranges, self.variants.len(), &mut self.rng);
+        self.favourites.clear();
+        self.generation += 1;
+    }
+
+    fn print_favourites(&self) {
+        for &index in &self.favourites {
+            println!("# Generation {}, variant {}", self.generation, index + 1);
+            println!("[uniforms]");
+            for (name, value) in &self.variants[index] {
+                println!("{} = {}", name, value);
+            }
+            println!();
+        }
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), ShadeyError> {
+    let mut population = None;
+    while evolve(args, &mut population)? == ProgramStatus::Reload {}
+
+    Ok(())
+}
+
+fn draw_labels(overlay: &mut Overlay, population: &Population, grid: Grid, size: (u32, u32)) {
+    let white = [1.0, 1.0, 1.0, 1.0];
+    let highlight = [1.0, 0.8, 0.2, 1.0];
+    let (width, height) = grid.cell_size(size);
+    let (width, height) = (width as f32, height as f32);
+
+    for &index in &population.favourites {
+        let (x, y) = grid.origin(index, size);
+        let (x, y) = (x as f32, y as f32);
+        overlay.rect(x, y, width, 3.0, highlight);
+        overlay.rect(x, y + height - 3.0, width, 3.0, highlight);
+        overlay.rect(x, y, 3.0, height, highlight);
+        overlay.rect(x + width - 3.0, y, 3.0, height, highlight);
+    }
+    if let Some(index) = population.focus {
+        let (x, y) = grid.origin(index, size);
+        let (x, y) = (x as f32 + 6.0, y as f32 + 6.0);
+        let (width, height) = (width - 12.0, height - 12.0);
+        overlay.rect(x, y, width, 1.0, white);
+        overlay.rect(x, y + height - 1.0, width, 1.0, white);
+        overlay.rect(x, y, 1.0, height, white);
+        overlay.rect(x + width - 1.0, y, 1.0, height, white);
+    }
+
+    let status = format!(
+        "Generation {} - click or Space for favourites, Enter to breed, S to print",
+        population.generation
+    );
+    overlay.rect(0.0, 0.0, (status.len() as f32 + 2.0) * CHAR_SIZE, CHAR_SIZE * 2.0, [0.0, 0.0, 0.0, 0.7]);
+    overlay.text(CHAR_SIZE, CHAR_SIZE * 0.5, &status, white);
+}
+
+fn evolve(args: &Args, population: &mut Option<Population>) -> Result<ProgramStatus, ShadeyError> {
+    let project = load_project(args)?;
+    if project.ranges.is_empty() {
+        return Err(ShadeyError::invalid(
+            "Nothing to evolve: declare uniforms with a range, e.g. warp = { value = 0.2, min = 0.0, max = 1.0 }."
+        ));
+    }
+    let grid = Grid::parse(args.flag_grid.as_deref().unwrap_or("3x3"))?;
+    let constants = constants::parse_all(&args.flag_uniform, args.decimal())?;
+    let epoch = TimeEpoch::from_name(&args.flag_time_epoch)?;
+    let mut watcher = Watcher::new(&project)?;
+
+    let mut events_loop = glutin::EventsLoop::new();
+    let display = init_display(&events_loop, args, monitor::from_args(args)?.as_deref())?;
+    let mut texture = input_image(&display, &project.image, args)?;
+    let mut pipeline = Pipeline::new(&display, &project)?;
+    pipeline.set_constants(constants)?;
+    let mut overlay = Overlay::new(&display)?;
+    let announcer = if args.flag_announce { Some(Announcer::new()) } else { None };
+    let announce = |text: &str| if let Some(ref announcer) = announcer {
+        announcer.say(text);
+    };
+
+    let population = population.get_or_insert_with(|| Population::new(&project.uniforms, &project.ranges, grid.cells()));
+    let mut clock = Clock::new(epoch, args.time_wrap);
+    let mut cursor = (0.0, 0.0);
+    loop {
+        let inputs = clock.tick();
+        let mut target = display.draw();
+        let size = target.get_dimensions();
+        pipeline.fix_size(grid.cell_size(size));
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+        // Every variant sees the same inputs; only their uniforms differ.
+        let mut drawn = Ok(());
+        for (index, variant) in population.variants.iter().enumerate() {
+            for (name, &value) in variant {
+                pipeline.set_uniform(name, value);
+            }
+            pipeline.invalidate();
+            drawn = pipeline.render(&display, &texture, &inputs).
+                and_then(|_| pipeline.present_in(&mut target, grid.viewport(index, size)));
+            if drawn.is_err() {
+                break;
+            }
+        }
+        draw_labels(&mut overlay, population, grid, size);
+        let drawn = drawn.and_then(|_| overlay.draw(&display, &mut target));
+        target.finish().map_err(|_| ShadeyError::Gl("Could not present frame."))?;
+        drawn?;
+
+        let mut status = None;
+        events_loop.poll_events(|event| {
+            let event = match event {
+                glutin::Event::WindowEvent { event, .. } => event,
+                _ => return
+            };
+            match event {
+                glutin::WindowEvent::Closed => status = Some(ProgramStatus::Done),
+                glutin::WindowEvent::MouseMoved { position: (x, y), .. } => cursor = (x as f32, y as f32),
+                glutin::WindowEvent::MouseInput {
+                    state: glutin::ElementState::Pressed,
+                    button: glutin::MouseButton::Left,
+                    ..
+                } => {
+                    if let Some(index) = grid.cell_at(cursor.0, cursor.1, size) {
+                        if index < population.variants.len() {
+                            population.toggle(index);
+                            announce(&population.describe(index));
+                        }
+                    }
+                },
+                glutin::WindowEvent::KeyboardInput {
+                    input: glutin::KeyboardInput {
+                        state: glutin::ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } => {
+                    let step = match key {
+                        glutin::VirtualKeyCode::Left => Some((-1, 0)),
+                        glutin::VirtualKeyCode::Right => Some((1, 0)),
+                        glutin::VirtualKeyCode::Up => Some((0, -1)),
+                        glutin::VirtualKeyCode::Down => Some((0, 1)),
+                        _ => None
+                    };
+                    match (key, step) {
+                        (_, Some((columns, rows))) => {
+                            let index = match population.focus {
+                                Some(index) => grid.step(index, columns, rows),
+                                None => 0
+                            };
+                            let index = index.min(population.variants.len() - 1);
+                            population.focus = Some(index);
+                            announce(&population.describe(index));
+                        },
+                        (glutin::VirtualKeyCode::Space, _) => {
+                            let index = population.focus.unwrap_or(0);
+                            population.focus = Some(index);
+                            population.toggle(index);
+                            announce(&population.describe(index));
+                        },
+                        (glutin::VirtualKeyCode::Return, _) => {
+                            let generation = population.generation;
+                            population.next_generation(pipeline.uniform_ranges());
+                            if population.generation != generation {
+                                announce(&format!("Generation {}", population.generation));
+                            }
+                        },
+                        (glutin::VirtualKeyCode::S, _) => population.print_favourites(),
+                        _ => ()
+                    }
+                },
+                _ => ()
+            }
+        });
+        if let Some(status) = status {
+            return Ok(status);
+        }
+
+        match watcher.poll()? {
+            Some(Change::Project) => return Ok(ProgramStatus::Reload),
+            Some(Change::Image) => {
+                match input_image(&display, &project.image, args) {
+                    Ok(new_texture) => texture = new_texture,
+                    Err(e) => eprintln!("Warning: {}", e)
+                }
+            },
+            None => ()
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Tools for exploring a shader's parameter space: randomizing uniforms,
+//! gliding between snapshots and breeding variants.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use snapshot::Snapshot;
+
+/// Small xorshift generator. Good enough for picking parameters.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new() -> Rng {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).
+            map(|d| d.as_secs() ^ u64::from(d.subsec_nanos()) << 32).
+            unwrap_or(0);
+        // Xorshift gets stuck on zero.
+        Rng { state: seed | 1 }
+    }
+
+    /// A number in `[min, max)`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let unit = (self.state >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    /// An index into a collection of `len` items, which mustn't be empty.
+    pub fn index(&mut self, len: usize) -> usize {
+        (self.range(0.0, len as f32) as usize).min(len - 1)
+    }
+}
+
+/// How far a child's uniform may stray from its parent's, as a fraction of
+/// the uniform's range.
+const MUTATION: f32 = 0.15;
+
+/// Uniform values for every uniform with a range, picked at random.
+pub fn random_variant(ranges: &BTreeMap<String, (f32, f32)>, rng: &mut Rng) -> BTreeMap<String, f32> {
+    ranges.iter().map(|(name, &(min, max))| (name.clone(), rng.range(min, max))).collect()
+}
+
+/// `count` variants: the parents themselves, followed by children that take
+/// each uniform from a random parent and nudge it within its range.
+pub fn breed(
+    parents: &[BTreeMap<String, f32>],
+    ranges: &BTreeMap<String, (f32, f32)>,
+    count: usize,
+    rng: &mut Rng
+) -> Vec<BTreeMap<String, f32>> {
+    let mut variants: Vec<_> = parents.iter().take(count).cloned().collect();
+    while variants.len() < count {
+        let child = ranges.iter().map(|(name, &(min, max))| {
+            let parent = &parents[rng.index(parents.len())];
+            let value = parent.get(name).cloned().unwrap_or(min);
+            let nudge = rng.range(-MUTATION, MUTATION) * (max - min);
+            (name.clone(), (value + nudge).clamp(min, max))
+        }).collect();
+        variants.push(child);
+    }
+
+    variants
+}
+
+/// A transition from one snapshot to another over a fixed time.
+pub struct Tween {
+    from: Snapshot,
+    to: Snapshot,
+    start: Instant,
+    duration: Duration
+}
+
+impl Tween {
+    pub fn new(from: Snapshot, to: Snapshot, seconds: f32) -> Tween {
+        Tween {
+            from,
+            to,
+            start: Instant::now(),
+            duration: Duration::from_millis((seconds.max(0.0) * 1000.0) as u64)
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.start.elapsed() >= self.duration
+    }
+
+    /// The state at the current time. Uniforms only in one of the snapshots
+    /// hold their value throughout.
+    pub fn sample(&self) -> Snapshot {
+        let elapsed = self.start.elapsed();
+        let linear = if self.duration == Duration::from_secs(0) {
+            1.0
+        } else {
+            let seconds = |d: Duration| d.as_secs() as f32 + d.subsec_nanos() as f32 * 1e-9;
+            (seconds(elapsed) / seconds(self.duration)).min(1.0)
+        };
+        // Ease in and out so the motion doesn't start or stop abruptly.
+        let t = linear * linear * (3.0 - 2.0 * linear);
+
+        let mut uniforms = self.from.uniforms.clone();
+        for (name, &to) in &self.to.uniforms {
+            let value = uniforms.get(name).map_or(to, |&from| from + (to - from) * t);
+            uniforms.insert(name.clone(), value);
+        }
+        let camera = match (self.from.camera.as_ref(), self.to.camera.as_ref()) {
+            (Some(from), Some(to)) => Some(from.lerp(to, t)),
+            (from, to) => to.or(from).cloned()
+        };
+
+        Snapshot { uniforms, camera }
+    }
+}
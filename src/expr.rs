@@ -0,0 +1,359 @@
+//! Tiny expression language used for CPU-side conditions such as a pass's
+//! `run_if`.
+//!
+//! Expressions operate on `f64` values. Comparisons and logical operators
+//! produce `1.0` for true and `0.0` for false, and any non-zero value is
+//! considered truthy.
+//!
+//! Function arguments are separated by `,` or `;`. Where numbers are written
+//! with a decimal comma, as in `0,5`, only `;` separates them.
+
+use error::ShadeyError;
+use numbers::Decimal;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Abs,
+    Floor,
+    Fract,
+    Sin,
+    Cos,
+    Min,
+    Max,
+    Mod
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "abs" => Some(Func::Abs),
+            "floor" => Some(Func::Floor),
+            "fract" => Some(Func::Fract),
+            "sin" => Some(Func::Sin),
+            "cos" => Some(Func::Cos),
+            "min" => Some(Func::Min),
+            "max" => Some(Func::Max),
+            "mod" => Some(Func::Mod),
+            _ => None
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Min | Func::Max | Func::Mod => 2,
+            _ => 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma
+}
+
+const OPERATORS: [&str; 15] = [
+    "&&", "||", "<=", ">=", "==", "!=",
+    "<", ">", "+", "-", "*", "/", "%", "!", "="
+];
+
+fn tokenize(src: &str, decimal: Decimal) -> Result<Vec<Token>, &'static str> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            let mark = |c: char| c == '.' || (c == ',' && decimal == Decimal::Comma);
+            while i < chars.len() && (chars[i].is_ascii_digit() || mark(chars[i])) {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = decimal.parse(&text).map_err(|_| "Invalid number in expression.")?;
+            tokens.push(Token::Num(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' || c == ';' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let op = OPERATORS.iter().find(|op| rest.starts_with(*op)).
+                ok_or("Unexpected character in expression.")?;
+            // A lone `=` is almost certainly a typo for `==`.
+            if *op == "=" {
+                return Err("Use `==` for comparisons in expressions.");
+            }
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Precedence climbing parser over the token stream.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn binary_op(&self) -> Option<(BinaryOp, u8)> {
+        match self.peek() {
+            Some(&Token::Op(op)) => match op {
+                "||" => Some((BinaryOp::Or, 1)),
+                "&&" => Some((BinaryOp::And, 2)),
+                "==" => Some((BinaryOp::Eq, 3)),
+                "!=" => Some((BinaryOp::Ne, 3)),
+                "<" => Some((BinaryOp::Lt, 4)),
+                "<=" => Some((BinaryOp::Le, 4)),
+                ">" => Some((BinaryOp::Gt, 4)),
+                ">=" => Some((BinaryOp::Ge, 4)),
+                "+" => Some((BinaryOp::Add, 5)),
+                "-" => Some((BinaryOp::Sub, 5)),
+                "*" => Some((BinaryOp::Mul, 6)),
+                "/" => Some((BinaryOp::Div, 6)),
+                "%" => Some((BinaryOp::Rem, 6)),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    fn expression(&mut self, min_prec: u8) -> Result<Expr, &'static str> {
+        let mut lhs = self.unary()?;
+
+        while let Some((op, prec)) = self.binary_op() {
+            if prec < min_prec {
+                break;
+            }
+            self.next();
+            let rhs = self.expression(prec + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, &'static str> {
+        match self.peek() {
+            Some(&Token::Op("-")) => {
+                self.next();
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            },
+            Some(&Token::Op("!")) => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            },
+            _ => self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, &'static str> {
+        match self.next() {
+            Some(Token::Num(value)) => Ok(Expr::Num(value)),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(match name.as_str() {
+                        "true" => Expr::Num(1.0),
+                        "false" => Expr::Num(0.0),
+                        _ => Expr::Var(name)
+                    });
+                }
+
+                let func = Func::from_name(&name).ok_or("Unknown function in expression.")?;
+                self.next();
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.expression(0)?);
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.next();
+                    }
+                }
+                if self.next() != Some(Token::RParen) {
+                    return Err("Expected `)` after function arguments.");
+                }
+                if args.len() != func.arity() {
+                    return Err("Wrong number of function arguments in expression.");
+                }
+
+                Ok(Expr::Call(func, args))
+            },
+            Some(Token::LParen) => {
+                let inner = self.expression(0)?;
+                if self.next() != Some(Token::RParen) {
+                    return Err("Unbalanced parentheses in expression.");
+                }
+                Ok(inner)
+            },
+            _ => Err("Unexpected end of expression.")
+        }
+    }
+}
+
+fn truthy(value: f64) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
+fn from_bool(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+impl Expr {
+    pub fn parse(src: &str, decimal: Decimal) -> Result<Expr, ShadeyError> {
+        Expr::parse_tokens(src, decimal).
+            map_err(|message| ShadeyError::invalid(format!("In expression `{}`: {}", src, message)))
+    }
+
+    fn parse_tokens(src: &str, decimal: Decimal) -> Result<Expr, &'static str> {
+        let mut parser = Parser { tokens: tokenize(src, decimal)?, pos: 0 };
+        let expr = parser.expression(0)?;
+
+        if parser.peek().is_some() {
+            return Err("Unexpected trailing input in expression.");
+        }
+
+        Ok(expr)
+    }
+
+    /// Every variable name referenced by the expression.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match *self {
+            Expr::Num(_) => (),
+            Expr::Var(ref name) => names.push(name),
+            Expr::Neg(ref inner) | Expr::Not(ref inner) => inner.collect_variables(names),
+            Expr::Binary(_, ref lhs, ref rhs) => {
+                lhs.collect_variables(names);
+                rhs.collect_variables(names);
+            },
+            Expr::Call(_, ref args) => {
+                for arg in args {
+                    arg.collect_variables(names);
+                }
+            }
+        }
+    }
+
+    /// Evaluate the expression, looking up variables with `lookup`. Unknown
+    /// variables evaluate to zero.
+    pub fn eval<F>(&self, lookup: &F) -> f64 where F: Fn(&str) -> Option<f64> {
+        match *self {
+            Expr::Num(value) => value,
+            Expr::Var(ref name) => lookup(name).unwrap_or(0.0),
+            Expr::Neg(ref inner) => -inner.eval(lookup),
+            Expr::Not(ref inner) => from_bool(!truthy(inner.eval(lookup))),
+            Expr::Binary(op, ref lhs, ref rhs) => {
+                let a = lhs.eval(lookup);
+
+                // Short-circuit logical operators.
+                match op {
+                    BinaryOp::And if !truthy(a) => return 0.0,
+                    BinaryOp::Or if truthy(a) => return 1.0,
+                    _ => ()
+                }
+
+                let b = rhs.eval(lookup);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a % b,
+                    BinaryOp::Lt => from_bool(a < b),
+                    BinaryOp::Le => from_bool(a <= b),
+                    BinaryOp::Gt => from_bool(a > b),
+                    BinaryOp::Ge => from_bool(a >= b),
+                    BinaryOp::Eq => from_bool(a == b),
+                    BinaryOp::Ne => from_bool(a != b),
+                    BinaryOp::And | BinaryOp::Or => from_bool(truthy(b))
+                }
+            },
+            Expr::Call(func, ref args) => {
+                let a = args[0].eval(lookup);
+                match func {
+                    Func::Abs => a.abs(),
+                    Func::Floor => a.floor(),
+                    Func::Fract => a - a.floor(),
+                    Func::Sin => a.sin(),
+                    Func::Cos => a.cos(),
+                    Func::Min => a.min(args[1].eval(lookup)),
+                    Func::Max => a.max(args[1].eval(lookup)),
+                    Func::Mod => {
+                        let b = args[1].eval(lookup);
+                        a - b * (a / b).floor()
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_true<F>(&self, lookup: &F) -> bool where F: Fn(&str) -> Option<f64> {
+        truthy(self.eval(lookup))
+    }
+}
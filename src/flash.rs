@@ -0,0 +1,127 @@
+//! Photosensitivity check of the output, following the general flash
+//! threshold of WCAG 2.3.1: a flash is a pair of opposing changes in relative
+//! luminance of 10% or more, where the darker side is below 0.8, and there
+//! should be no more than three flashes in any one second.
+//!
+//! Luminance is averaged over the whole frame, so flashes confined to a small
+//! part of the output are underestimated and red flashes aren't considered.
+//! A clean result isn't a substitute for a proper test before a public
+//! showing.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use glium::Display;
+use glium::texture::Texture2d;
+
+use error::ShadeyError;
+use metrics::Measure;
+
+/// Smallest change in relative luminance that counts towards a flash.
+const CHANGE: f32 = 0.1;
+/// Changes only count when the darker side is below this.
+const DARKER_BELOW: f32 = 0.8;
+/// Most flashes allowed in any one second.
+const MAX_FLASHES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlashMode {
+    /// Print a warning whenever the output starts flashing too often.
+    Warn,
+    /// Also dim the output until the flashes fall under the threshold.
+    Limit
+}
+
+impl FlashMode {
+    pub fn from_name(name: &str) -> Result<FlashMode, ShadeyError> {
+        match name {
+            "warn" => Ok(FlashMode::Warn),
+            "limit" => Ok(FlashMode::Limit),
+            _ => Err(ShadeyError::invalid(format!(
+                "Unknown flash check `{}`. Expected warn or limit.",
+                name
+            )))
+        }
+    }
+}
+
+pub struct FlashCheck {
+    mode: FlashMode,
+    measure: Measure,
+    start: Instant,
+    /// Luminance at the last turning point, or the first frame.
+    anchor: Option<f32>,
+    /// Direction of the last change that counted.
+    rising: Option<bool>,
+    /// Time and size of the changes in the last second.
+    changes: VecDeque<(f64, f32)>,
+    flashing: bool,
+    gain: f32
+}
+
+impl FlashCheck {
+    pub fn new(display: &Display, mode: FlashMode) -> Result<FlashCheck, ShadeyError> {
+        Ok(FlashCheck {
+            mode,
+            measure: Measure::new(display)?,
+            start: Instant::now(),
+            anchor: None,
+            rising: None,
+            changes: VecDeque::new(),
+            flashing: false,
+            gain: 1.0
+        })
+    }
+
+    /// How much to scale the brightness by in limit mode.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Measure a newly rendered frame.
+    pub fn update(&mut self, display: &Display, frame: &Texture2d) -> Result<(), ShadeyError> {
+        let luminance = self.measure.mean_luminance(display, frame)?;
+        let now = self.start.elapsed().as_secs_f64();
+
+        let anchor = *self.anchor.get_or_insert(luminance);
+        let (change, darker) = (luminance - anchor, luminance.min(anchor));
+        match self.rising {
+            // Follow the luminance while it keeps going the same way.
+            Some(true) if change > 0.0 => self.anchor = Some(luminance),
+            Some(false) if change < 0.0 => self.anchor = Some(luminance),
+            _ if change.abs() >= CHANGE && darker < DARKER_BELOW => {
+                self.changes.push_back((now, change.abs()));
+                self.rising = Some(change > 0.0);
+                self.anchor = Some(luminance);
+            },
+            _ => {}
+        }
+        while self.changes.front().is_some_and(|&(at, _)| now - at > 1.0) {
+            self.changes.pop_front();
+        }
+
+        let flashes = self.changes.len() / 2;
+        let flashing = flashes > MAX_FLASHES;
+        if flashing && !self.flashing {
+            eprintln!(
+                "Warning: The output flashes {} times a second, above the limit of {} for photosensitive viewers.",
+                flashes,
+                MAX_FLASHES
+            );
+        }
+        self.flashing = flashing;
+
+        if self.mode == FlashMode::Limit {
+            self.gain = if flashing {
+                // Keep the largest recent change just under the threshold.
+                // The gain applies to gamma encoded colours, hence the power.
+                let largest = self.changes.iter().map(|&(_, size)| size).fold(0.0, f32::max);
+                (CHANGE * 0.9 / largest).powf(1.0 / 2.2).min(1.0)
+            } else {
+                1.0
+            };
+        }
+
+        Ok(())
+    }
+}
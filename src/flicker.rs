@@ -0,0 +1,80 @@
+//! `render --flicker-metric=<frames>`: how much the output shimmers, for
+//! regression tests that single golden frames can't catch. Every pixel's
+//! relative luminance is tracked over a sliding window of frames. The
+//! variance over each window is averaged across the image, and the run is
+//! summed up by the mean and worst of those.
+//!
+//! Motion counts as well, so the figure is only meaningful compared with
+//! other runs of the same shader at the same settings.
+
+use std::collections::VecDeque;
+
+use image::RgbaImage;
+
+use error::ShadeyError;
+
+pub struct Flicker {
+    window: usize,
+    frames: VecDeque<Vec<f32>>,
+    total: f64,
+    windows: u32,
+    /// Highest variance and the frame its window ended on.
+    worst: Option<(f64, u32)>
+}
+
+fn linear(c: u8) -> f32 {
+    let c = f32::from(c) / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn luminance(img: &RgbaImage) -> Vec<f32> {
+    img.pixels().map(|p| 0.2126 * linear(p[0]) + 0.7152 * linear(p[1]) + 0.0722 * linear(p[2])).collect()
+}
+
+impl Flicker {
+    pub fn new(window: &str) -> Result<Flicker, ShadeyError> {
+        let window = window.parse().ok().filter(|&frames: &usize| frames >= 2).ok_or_else(|| ShadeyError::invalid(
+            format!("Invalid flicker window `{}`: give at least 2 frames.", window)
+        ))?;
+
+        Ok(Flicker { window, frames: VecDeque::with_capacity(window), total: 0.0, windows: 0, worst: None })
+    }
+
+    pub fn add(&mut self, img: &RgbaImage, frame: u32) {
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(luminance(img));
+        if self.frames.len() < self.window {
+            return;
+        }
+
+        let n = self.window as f64;
+        let pixels = self.frames[0].len();
+        let mut sum = 0.0;
+        for i in 0..pixels {
+            let mean = self.frames.iter().map(|f| f64::from(f[i])).sum::<f64>() / n;
+            sum += self.frames.iter().map(|f| (f64::from(f[i]) - mean).powi(2)).sum::<f64>() / n;
+        }
+        let variance = sum / pixels.max(1) as f64;
+
+        self.total += variance;
+        self.windows += 1;
+        if self.worst.is_none_or(|(worst, _)| variance > worst) {
+            self.worst = Some((variance, frame));
+        }
+    }
+
+    pub fn report(&self) {
+        match self.worst {
+            Some((worst, frame)) => println!(
+                "Flicker over {} frame windows: mean {:.6}, worst {:.6} ending at frame {}",
+                self.window,
+                self.total / f64::from(self.windows),
+                worst,
+                frame
+            ),
+            None => println!("Flicker: too few frames for a {} frame window", self.window)
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Values computed on the CPU every frame to give shaders structured motion,
+//! declared as `[generators.<name>]` tables in a project file:
+//!
+//! - `lissajous`: a `vec2` uniform tracing a Lissajous curve from 0 to 1,
+//!   with frequencies `a` and `b`, `speed` and `phase`
+//! - `random-walk`: a `count` by 1 texture of points wandering across 0 to 1,
+//!   moving about `step` a second, in the red and green channels
+//! - `flow-field`: a `size` by `size` texture of unit directions following 3D
+//!   Perlin noise at `scale`, drifting at `speed`
+//! - `spring`: a `vec2` from 0 to 1 pulled toward the cursor by a damped
+//!   spring of `stiffness` and `damping`
+//! - `bounce`: a `float` from 0 to 1 falling under `gravity` and bouncing
+//!   back up to `bounciness` of its speed, relaunched once it settles
+//! - `smooth`: a `float` following the project uniform `uniform`, taking
+//!   about `seconds` to catch up with changes
+//!
+//! The name is the uniform or sampler shaders read it as. New kinds are a
+//! variant of `GeneratorSpec` and of `State` each.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+use glium::{Display, Rect};
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue};
+
+use error::ShadeyError;
+use explore::Rng;
+use pipeline::FrameInputs;
+
+/// Largest texture a generator may fill every frame, per side.
+const MAX_SIZE: u32 = 256;
+/// Longest step physics generators take at once, so they stay stable when
+/// frames are slow.
+const MAX_STEP: f32 = 1.0 / 240.0;
+/// Frames longer than this, e.g. after a pause, count as this long.
+const MAX_DELTA: f32 = 0.25;
+/// Speed under which a bounce has settled.
+const SETTLED: f32 = 0.05;
+
+fn one() -> f32 {
+    1.0
+}
+
+fn default_step() -> f32 {
+    0.05
+}
+
+fn default_scale() -> f32 {
+    4.0
+}
+
+fn default_speed() -> f32 {
+    0.1
+}
+
+fn default_stiffness() -> f32 {
+    60.0
+}
+
+fn default_damping() -> f32 {
+    8.0
+}
+
+fn default_gravity() -> f32 {
+    4.0
+}
+
+fn default_bounciness() -> f32 {
+    0.7
+}
+
+fn default_seconds() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum GeneratorSpec {
+    Lissajous {
+        #[serde(default = "one")]
+        a: f32,
+        #[serde(default = "one")]
+        b: f32,
+        #[serde(default = "one")]
+        speed: f32,
+        #[serde(default)]
+        phase: f32
+    },
+    RandomWalk {
+        count: u32,
+        #[serde(default = "default_step")]
+        step: f32
+    },
+    FlowField {
+        size: u32,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default = "default_speed")]
+        speed: f32
+    },
+    Spring {
+        #[serde(default = "default_stiffness")]
+        stiffness: f32,
+        #[serde(default = "default_damping")]
+        damping: f32
+    },
+    Bounce {
+        #[serde(default = "default_gravity")]
+        gravity: f32,
+        #[serde(default = "default_bounciness")]
+        bounciness: f32
+    },
+    Smooth {
+        uniform: String,
+        #[serde(default = "default_seconds")]
+        seconds: f32
+    }
+}
+
+impl GeneratorSpec {
+    /// Check the settings make sense, given the project's `uniforms`.
+    pub fn validate(&self, name: &str, uniforms: &BTreeMap<String, f32>) -> Result<(), ShadeyError> {
+        let invalid = |problem: &str| Err(ShadeyError::invalid(format!("Generator `{}` {}.", name, problem)));
+        let size = match *self {
+            GeneratorSpec::RandomWalk { count, .. } => count,
+            GeneratorSpec::FlowField { size, .. } => size,
+            GeneratorSpec::Spring { stiffness, damping } if stiffness <= 0.0 || damping < 0.0 => {
+                return invalid("needs a positive stiffness and a damping of at least 0");
+            },
+            GeneratorSpec::Bounce { gravity, bounciness } if gravity <= 0.0 || !(0.0..1.0).contains(&bounciness) => {
+                return invalid("needs a positive gravity and a bounciness from 0 up to 1");
+            },
+            GeneratorSpec::Smooth { ref uniform, .. } if !uniforms.contains_key(uniform) => {
+                return invalid(&format!("follows `{}`, which isn't a project uniform", uniform));
+            },
+            GeneratorSpec::Smooth { seconds, .. } if seconds < 0.0 => {
+                return invalid("can't take a negative number of seconds");
+            },
+            _ => return Ok(())
+        };
+        if size == 0 || size > MAX_SIZE {
+            return invalid(&format!("must be from 1 to {} texels across", MAX_SIZE));
+        }
+        Ok(())
+    }
+}
+
+/// Ken Perlin's improved noise.
+struct Perlin {
+    permutation: Vec<u8>
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn gradient(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+impl Perlin {
+    fn new(rng: &mut Rng) -> Perlin {
+        let mut table: Vec<u8> = (0..=255).collect();
+        for i in (1..table.len()).rev() {
+            table.swap(i, rng.index(i + 1));
+        }
+        // Doubled so lookups can run past the end without wrapping.
+        let permutation = table.iter().chain(table.iter()).cloned().collect();
+        Perlin { permutation }
+    }
+
+    /// Noise at a point, roughly from -1 to 1.
+    fn noise(&self, x: f32, y: f32, z: f32) -> f32 {
+        let p = &self.permutation;
+        let (xi, yi, zi) = (x.floor() as i32 & 255, y.floor() as i32 & 255, z.floor() as i32 & 255);
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let (xi, yi, zi) = (xi as usize, yi as usize, zi as usize);
+
+        let a = p[xi] as usize + yi;
+        let (aa, ab) = (p[a] as usize + zi, p[a + 1] as usize + zi);
+        let b = p[xi + 1] as usize + yi;
+        let (ba, bb) = (p[b] as usize + zi, p[b + 1] as usize + zi);
+
+        lerp(w,
+            lerp(v,
+                lerp(u, gradient(p[aa], x, y, z), gradient(p[ba], x - 1.0, y, z)),
+                lerp(u, gradient(p[ab], x, y - 1.0, z), gradient(p[bb], x - 1.0, y - 1.0, z))),
+            lerp(v,
+                lerp(u, gradient(p[aa + 1], x, y, z - 1.0), gradient(p[ba + 1], x - 1.0, y, z - 1.0)),
+                lerp(u, gradient(p[ab + 1], x, y - 1.0, z - 1.0), gradient(p[bb + 1], x - 1.0, y - 1.0, z - 1.0))))
+    }
+}
+
+enum State {
+    Lissajous { a: f32, b: f32, speed: f32, phase: f32, point: [f32; 2] },
+    RandomWalk { step: f32, points: Vec<[f32; 2]>, rng: Rng },
+    FlowField { size: u32, scale: f32, speed: f32, noise: Perlin, directions: Vec<[f32; 2]> },
+    Spring { stiffness: f32, damping: f32, position: [f32; 2], velocity: [f32; 2] },
+    Bounce { gravity: f32, bounciness: f32, height: f32, velocity: f32 },
+    /// `value` is `None` until the first update, which starts it at the target.
+    Smooth { uniform: String, seconds: f32, value: Option<f32> }
+}
+
+/// Split `delta` seconds into steps no longer than `MAX_STEP`.
+fn steps(delta: f32) -> impl Iterator<Item = f32> {
+    let delta = delta.clamp(0.0, MAX_DELTA);
+    let count = (delta / MAX_STEP).ceil() as usize;
+    (0..count).map(move |_| delta / count as f32)
+}
+
+pub struct Generator {
+    pub name: String,
+    state: State,
+    /// Where texture generators put their output.
+    texture: Option<Texture2d>
+}
+
+fn float_texture(display: &Display, width: u32, height: u32) -> Result<Texture2d, ShadeyError> {
+    Texture2d::empty_with_format(display, UncompressedFloatFormat::F32F32, MipmapsOption::NoMipmap, width, height).
+        map_err(|_| ShadeyError::Gl("Could not create a generator texture."))
+}
+
+fn sampler(wrap: SamplerWrapFunction, filter: (MinifySamplerFilter, MagnifySamplerFilter)) -> SamplerBehavior {
+    SamplerBehavior {
+        wrap_function: (wrap, wrap, wrap),
+        minify_filter: filter.0,
+        magnify_filter: filter.1,
+        ..Default::default()
+    }
+}
+
+impl Generator {
+    pub fn new(display: &Display, name: &str, spec: &GeneratorSpec) -> Result<Generator, ShadeyError> {
+        let mut rng = Rng::new();
+        let (state, texture) = match *spec {
+            GeneratorSpec::Lissajous { a, b, speed, phase } => {
+                (State::Lissajous { a, b, speed, phase, point: [0.5, 0.5] }, None)
+            },
+            GeneratorSpec::RandomWalk { count, step } => {
+                let points = (0..count).map(|_| [rng.range(0.0, 1.0), rng.range(0.0, 1.0)]).collect();
+                (State::RandomWalk { step, points, rng }, Some(float_texture(display, count, 1)?))
+            },
+            GeneratorSpec::FlowField { size, scale, speed } => {
+                let state = State::FlowField {
+                    size,
+                    scale,
+                    speed,
+                    noise: Perlin::new(&mut rng),
+                    directions: vec![[1.0, 0.0]; (size * size) as usize]
+                };
+                (state, Some(float_texture(display, size, size)?))
+            },
+            GeneratorSpec::Spring { stiffness, damping } => {
+                (State::Spring { stiffness, damping, position: [0.5, 0.5], velocity: [0.0, 0.0] }, None)
+            },
+            GeneratorSpec::Bounce { gravity, bounciness } => {
+                (State::Bounce { gravity, bounciness, height: 1.0, velocity: 0.0 }, None)
+            },
+            GeneratorSpec::Smooth { ref uniform, seconds } => {
+                (State::Smooth { uniform: uniform.clone(), seconds, value: None }, None)
+            }
+        };
+
+        Ok(Generator { name: name.to_string(), state, texture })
+    }
+
+    /// Move on to the frame of `inputs`, rendered at `size` with the project
+    /// uniforms at `uniforms`.
+    pub fn update(&mut self, inputs: &FrameInputs, size: (u32, u32), uniforms: &BTreeMap<String, f32>) {
+        let (time, delta) = (inputs.time, inputs.time_delta);
+        let (data, width, height): (&[[f32; 2]], u32, u32) = match self.state {
+            State::Lissajous { a, b, speed, phase, ref mut point } => {
+                let t = time * speed * 2.0 * PI;
+                *point = [0.5 + 0.5 * (a * t + phase).sin(), 0.5 + 0.5 * (b * t).sin()];
+                return;
+            },
+            State::RandomWalk { step, ref mut points, ref mut rng } => {
+                // Brownian, so the distance covered doesn't depend on the frame rate.
+                let stride = step * delta.max(0.0).sqrt();
+                for point in points.iter_mut() {
+                    point[0] = (point[0] + rng.range(-stride, stride)).rem_euclid(1.0);
+                    point[1] = (point[1] + rng.range(-stride, stride)).rem_euclid(1.0);
+                }
+                (points, points.len() as u32, 1)
+            },
+            State::FlowField { size, scale, speed, ref noise, ref mut directions } => {
+                let z = time * speed;
+                for y in 0..size {
+                    for x in 0..size {
+                        let (u, v) = (x as f32 / size as f32 * scale, y as f32 / size as f32 * scale);
+                        let angle = noise.noise(u, v, z) * 2.0 * PI;
+                        directions[(y * size + x) as usize] = [angle.cos(), angle.sin()];
+                    }
+                }
+                (directions, size, size)
+            },
+            State::Spring { stiffness, damping, ref mut position, ref mut velocity } => {
+                let target = [inputs.cursor[0] / size.0 as f32, inputs.cursor[1] / size.1 as f32];
+                for dt in steps(delta) {
+                    for axis in 0..2 {
+                        let force = stiffness * (target[axis] - position[axis]) - damping * velocity[axis];
+                        velocity[axis] += force * dt;
+                        position[axis] += velocity[axis] * dt;
+                    }
+                }
+                return;
+            },
+            State::Bounce { gravity, bounciness, ref mut height, ref mut velocity } => {
+                for dt in steps(delta) {
+                    *velocity -= gravity * dt;
+                    *height += *velocity * dt;
+                    if *height < 0.0 {
+                        *height = -*height;
+                        *velocity *= -bounciness;
+                        if *velocity < SETTLED {
+                            // Fast enough to get back up to 1.
+                            *velocity = (2.0 * gravity).sqrt();
+                        }
+                    }
+                }
+                return;
+            },
+            State::Smooth { ref uniform, seconds, ref mut value } => {
+                let target = uniforms.get(uniform).cloned().unwrap_or(0.0);
+                let current = value.unwrap_or(target);
+                let blend = if seconds > 0.0 { 1.0 - (-delta.max(0.0) / seconds).exp() } else { 1.0 };
+                *value = Some(current + (target - current) * blend);
+                return;
+            }
+        };
+
+        if let Some(ref texture) = self.texture {
+            let flat: Vec<f32> = data.iter().flat_map(|texel| texel.iter().cloned()).collect();
+            let image = RawImage2d { data: Cow::Owned(flat), width, height, format: ClientFormat::F32F32 };
+            texture.write(Rect { left: 0, bottom: 0, width, height }, image);
+        }
+    }
+
+    pub fn uniform(&self) -> UniformValue<'_> {
+        let texture = match (&self.state, self.texture.as_ref()) {
+            (&State::Lissajous { point, .. }, _) => return UniformValue::Vec2(point),
+            (&State::Spring { position, .. }, _) => return UniformValue::Vec2(position),
+            (&State::Bounce { height, .. }, _) => return UniformValue::Float(height),
+            (&State::Smooth { value, .. }, _) => return UniformValue::Float(value.unwrap_or(0.0)),
+            (_, Some(texture)) => texture,
+            (_, None) => unreachable!("Texture generators always have a texture.")
+        };
+        // Walkers are looked up one by one, the flow field smoothly.
+        let behavior = match self.state {
+            State::RandomWalk { .. } => sampler(
+                SamplerWrapFunction::Clamp,
+                (MinifySamplerFilter::Nearest, MagnifySamplerFilter::Nearest)
+            ),
+            _ => sampler(SamplerWrapFunction::Repeat, (MinifySamplerFilter::Linear, MagnifySamplerFilter::Linear))
+        };
+        UniformValue::Texture2d(texture, Some(behavior))
+    }
+}
@@ -0,0 +1,218 @@
+//! `render --golden=<dir>`: checks every rendered frame against reference
+//! renders, for regression tests that must pass on more than one GPU.
+//!
+//! `frame-00000.png` in the directory is the reference for the first frame,
+//! and any `frame-00000.<variant>.png` next to it, e.g. one rendered on
+//! another vendor's driver, is accepted as well. A frame passes if it's
+//! within `--tolerance` of any of them.
+//!
+//! The tolerance is `exact` or a comma separated mix of the following, or a
+//! semicolon separated one with `--decimal-comma`:
+//!
+//! - `epsilon=<levels>`: how far a channel may be off, out of 255
+//! - `pixels=<percent>%`: how many pixels may be off by more than that
+//! - `ssim=<min>`: the lowest structural similarity accepted
+//!
+//! A `tolerance` file in the directory, holding the same, takes the place of
+//! `--tolerance` for that directory's frames, so each test can keep its own.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glium::Display;
+use glium::texture::{RawImage2d, Texture2d};
+use image::{self, RgbaImage};
+
+use error::ShadeyError;
+use metrics::Measure;
+use numbers::Decimal;
+
+#[derive(Copy, Clone)]
+pub struct Tolerance {
+    epsilon: u8,
+    /// Share of pixels that may be further off than `epsilon`, from 0 to 1.
+    pixels: f32,
+    ssim: Option<f32>
+}
+
+impl Tolerance {
+    pub fn parse(spec: &str, decimal: Decimal) -> Result<Tolerance, ShadeyError> {
+        let mut tolerance = Tolerance { epsilon: 0, pixels: 0.0, ssim: None };
+        if spec == "exact" {
+            return Ok(tolerance);
+        }
+
+        let example = match decimal {
+            Decimal::Point => "epsilon=2,pixels=0.5%,ssim=0.98",
+            Decimal::Comma => "epsilon=2;pixels=0,5%;ssim=0,98"
+        };
+        let invalid = || ShadeyError::invalid(format!(
+            "Invalid tolerance `{}`. Expected exact or e.g. {}.",
+            spec, example
+        ));
+        for part in decimal.split(spec) {
+            let (key, value) = part.trim().split_once('=').ok_or_else(invalid)?;
+            match key {
+                "epsilon" => tolerance.epsilon = value.trim().parse().map_err(|_| invalid())?,
+                "pixels" => {
+                    let percent: f32 = decimal.parse(value.trim().trim_end_matches('%')).map_err(|_| invalid())?;
+                    if !(0.0..=100.0).contains(&percent) {
+                        return Err(invalid());
+                    }
+                    tolerance.pixels = percent / 100.0;
+                },
+                "ssim" => tolerance.ssim = Some(decimal.parse(value.trim()).map_err(|_| invalid())?),
+                _ => return Err(invalid())
+            }
+        }
+
+        Ok(tolerance)
+    }
+
+    /// The tolerance in `dir`'s `tolerance` file, or `default` without one.
+    pub fn for_dir(dir: &str, default: Tolerance, decimal: Decimal) -> Result<Tolerance, ShadeyError> {
+        let path = Path::new(dir).join("tolerance");
+        match fs::read_to_string(&path) {
+            Ok(spec) => Tolerance::parse(spec.trim(), decimal),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(default),
+            Err(e) => Err(ShadeyError::io("Could not read", &path, e))
+        }
+    }
+}
+
+/// How far a frame is from one reference.
+struct Difference {
+    /// Share of pixels further off than the tolerance's epsilon.
+    pixels: f32,
+    ssim: Option<f32>
+}
+
+impl Difference {
+    fn within(&self, tolerance: &Tolerance) -> bool {
+        self.pixels <= tolerance.pixels && tolerance.ssim.is_none_or(|min| self.ssim.is_some_and(|ssim| ssim >= min))
+    }
+
+    fn describe(&self, epsilon: u8) -> String {
+        let mut text = format!("{:.3}% of pixels off by more than {}", self.pixels * 100.0, epsilon);
+        if let Some(ssim) = self.ssim {
+            text.push_str(&format!(", SSIM {:.6}", ssim));
+        }
+        text
+    }
+}
+
+pub struct Golden {
+    dir: PathBuf,
+    tolerance: Tolerance,
+    measure: Option<Measure>,
+    checked: u32,
+    failed: u32
+}
+
+impl Golden {
+    pub fn new(display: &Display, dir: &str, tolerance: Tolerance) -> Result<Golden, ShadeyError> {
+        let measure = match tolerance.ssim {
+            Some(_) => Some(Measure::new(display)?),
+            None => None
+        };
+        Ok(Golden { dir: PathBuf::from(dir), tolerance, measure, checked: 0, failed: 0 })
+    }
+
+    /// `name.png` and every `name.<variant>.png` in the directory.
+    fn references(&self, name: &str) -> Result<Vec<PathBuf>, ShadeyError> {
+        let entries = fs::read_dir(&self.dir).map_err(|e| ShadeyError::io("Could not read", &self.dir, e))?;
+        let variant = format!("{}.", name);
+        let mut paths: Vec<PathBuf> = entries.
+            filter_map(|entry| entry.ok().map(|entry| entry.path())).
+            filter(|path| {
+                let file = path.file_name().map(|file| file.to_string_lossy().into_owned()).unwrap_or_default();
+                file.ends_with(".png") && (file == format!("{}.png", name) || file.starts_with(&variant))
+            }).
+            collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn compare(&self, display: &Display, path: &Path, img: &RgbaImage, output: &Texture2d) ->
+        Result<Option<Difference>, ShadeyError>
+    {
+        let reference = image::open(path).map_err(|error| match error {
+            image::ImageError::IoError(e) => ShadeyError::io("Could not open", path, e),
+            error => ShadeyError::Image { path: path.to_path_buf(), error }
+        })?.to_rgba();
+        if reference.dimensions() != img.dimensions() {
+            return Ok(None);
+        }
+
+        let epsilon = self.tolerance.epsilon;
+        let off = reference.pixels().zip(img.pixels()).
+            filter(|&(a, b)| a.data.iter().zip(b.data.iter()).any(|(&a, &b)| a.max(b) - a.min(b) > epsilon)).
+            count();
+        let ssim = match self.measure {
+            Some(ref measure) => {
+                let dims = reference.dimensions();
+                let texture = Texture2d::new(display, RawImage2d::from_raw_rgba_reversed(&reference.into_raw(), dims)).
+                    map_err(|_| ShadeyError::Gl("Could not create texture from image."))?;
+                Some(measure.compare(display, &texture, output)?.ssim)
+            },
+            None => None
+        };
+
+        Ok(Some(Difference { pixels: off as f32 / (img.width() * img.height()).max(1) as f32, ssim }))
+    }
+
+    /// Check frame `name`, rendered as `img` from `output`, and say if it
+    /// fails.
+    pub fn check(&mut self, display: &Display, name: &str, img: &RgbaImage, output: &Texture2d) ->
+        Result<(), ShadeyError>
+    {
+        self.checked += 1;
+        let references = self.references(name)?;
+        if references.is_empty() {
+            self.failed += 1;
+            eprintln!("{}: no reference in {}", name, self.dir.display());
+            return Ok(());
+        }
+
+        let mut closest: Option<(PathBuf, Difference)> = None;
+        for path in references {
+            let difference = match self.compare(display, &path, img, output)? {
+                Some(difference) => difference,
+                None => continue
+            };
+            if difference.within(&self.tolerance) {
+                return Ok(());
+            }
+            if closest.as_ref().is_none_or(|(_, closest)| difference.pixels < closest.pixels) {
+                closest = Some((path, difference));
+            }
+        }
+
+        self.failed += 1;
+        match closest {
+            Some((path, difference)) => eprintln!(
+                "{}: closest reference {} has {}",
+                name,
+                path.display(),
+                difference.describe(self.tolerance.epsilon)
+            ),
+            None => eprintln!("{}: every reference is a different size", name)
+        }
+        Ok(())
+    }
+
+    /// Sum up the run, as an error if any frame failed.
+    pub fn finish(&self) -> Result<(), ShadeyError> {
+        if self.failed > 0 {
+            return Err(ShadeyError::invalid(format!(
+                "{} of {} frames didn't match {}.",
+                self.failed,
+                self.checked,
+                self.dir.display()
+            )));
+        }
+        println!("All {} frames match {}.", self.checked, self.dir.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! GPU temperature and clock readings, to tell when a benchmark was slowed
+//! down by thermal throttling rather than by the shader.
+//!
+//! AMD GPUs are read through the amdgpu driver's sysfs files and NVIDIA ones
+//! through `nvidia-smi`. Other GPUs just don't report anything.
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reading {
+    /// Degrees Celsius.
+    pub temperature: Option<f32>,
+    /// Current shader clock in MHz.
+    pub clock: Option<f32>
+}
+
+fn read_number(path: &Path) -> Option<f32> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// The hwmon directory of the first amdgpu card.
+fn amdgpu_hwmon() -> Option<PathBuf> {
+    for card in fs::read_dir("/sys/class/drm").ok()?.filter_map(Result::ok) {
+        let device = card.path().join("device");
+        let is_amdgpu = fs::read_link(device.join("driver")).ok().
+            is_some_and(|driver| driver.ends_with("amdgpu"));
+        if !is_amdgpu {
+            continue;
+        }
+        if let Some(hwmon) = fs::read_dir(device.join("hwmon")).ok().and_then(|mut dirs| dirs.next()) {
+            return hwmon.ok().map(|entry| entry.path());
+        }
+    }
+
+    None
+}
+
+fn read_amdgpu() -> Option<Reading> {
+    let hwmon = amdgpu_hwmon()?;
+    Some(Reading {
+        // Millidegrees and hertz.
+        temperature: read_number(&hwmon.join("temp1_input")).map(|t| t / 1000.0),
+        clock: read_number(&hwmon.join("freq1_input")).map(|f| f / 1e6)
+    })
+}
+
+fn read_nvidia() -> Option<Reading> {
+    let output = Command::new("nvidia-smi").
+        args(["--query-gpu=temperature.gpu,clocks.sm", "--format=csv,noheader,nounits", "--id=0"]).
+        output().
+        ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.trim().split(',').map(|field| field.trim().parse().ok());
+    Some(Reading {
+        temperature: fields.next().and_then(|t| t),
+        clock: fields.next().and_then(|c| c)
+    })
+}
+
+/// Sensors of the first GPU that reports any.
+pub fn read() -> Option<Reading> {
+    read_amdgpu().or_else(read_nvidia)
+}
+
+impl Reading {
+    pub fn describe(&self) -> String {
+        let temperature = self.temperature.map_or("?".to_string(), |t| format!("{:.0}°C", t));
+        let clock = self.clock.map_or("?".to_string(), |c| format!("{:.0} MHz", c));
+        format!("{} at {}", temperature, clock)
+    }
+}
+
+/// Readings taken over a benchmark.
+#[derive(Default)]
+pub struct Samples {
+    readings: Vec<Reading>
+}
+
+/// A clock this far below the starting one counts as throttled.
+const THROTTLE_FRACTION: f32 = 0.9;
+
+impl Samples {
+    pub fn new() -> Samples {
+        Default::default()
+    }
+
+    pub fn sample(&mut self) {
+        if let Some(reading) = read() {
+            self.readings.push(reading);
+        }
+    }
+
+    /// Lines to add to the benchmark report, warning about throttling.
+    pub fn report(&self) -> Vec<String> {
+        let (first, last) = match (self.readings.first(), self.readings.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return vec!["GPU: no temperature or clock sensors found".to_string()]
+        };
+
+        let mut lines = vec![format!("GPU: {} at the start, {} at the end", first.describe(), last.describe())];
+        let hottest = self.readings.iter().filter_map(|r| r.temperature).fold(None, |max: Option<f32>, t| {
+            Some(max.map_or(t, |max| max.max(t)))
+        });
+        if let Some(hottest) = hottest {
+            lines.push(format!("GPU: peaked at {:.0}°C", hottest));
+        }
+
+        let slowest = self.readings.iter().filter_map(|r| r.clock).fold(None, |min: Option<f32>, c| {
+            Some(min.map_or(c, |min| min.min(c)))
+        });
+        if let (Some(start), Some(slowest)) = (first.clock, slowest) {
+            if slowest < start * THROTTLE_FRACTION {
+                lines.push(format!(
+                    "Warning: The GPU clock fell from {:.0} to {:.0} MHz during the run, so it was likely \
+                     throttled and the timings can't be compared with other runs.",
+                    start, slowest
+                ));
+            }
+        }
+
+        lines
+    }
+}
@@ -0,0 +1,120 @@
+//! Splitting the window into a grid of equally sized viewports, e.g. to
+//! compare seeds of a generative shader with `--grid=3x3 --vary=seed`.
+
+use glium::{Display, Rect, Surface};
+use glium::texture::Texture2d;
+
+use error::ShadeyError;
+use offline::parse_size;
+use pipeline::{FrameInputs, Pipeline};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32
+}
+
+impl Grid {
+    /// Parse `<columns>x<rows>`.
+    pub fn parse(text: &str) -> Result<Grid, ShadeyError> {
+        let (columns, rows) = parse_size(text).
+            map_err(|_| ShadeyError::invalid(format!("Invalid grid `{}`. Expected e.g. 3x3.", text)))?;
+        Ok(Grid { columns, rows })
+    }
+
+    pub fn cells(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Size in pixels of each cell of a window of `size`.
+    pub fn cell_size(&self, size: (u32, u32)) -> (u32, u32) {
+        ((size.0 / self.columns).max(1), (size.1 / self.rows).max(1))
+    }
+
+    /// Top left corner of cell `index` in pixels from the top left. Cells are
+    /// numbered along rows, starting at the top left.
+    pub fn origin(&self, index: usize, size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = self.cell_size(size);
+        let index = index as u32;
+        ((index % self.columns) * width, (index / self.columns) * height)
+    }
+
+    /// The viewport to draw cell `index` with.
+    pub fn viewport(&self, index: usize, size: (u32, u32)) -> Rect {
+        let (width, height) = self.cell_size(size);
+        let (left, top) = self.origin(index, size);
+        // Viewports count from the bottom left.
+        Rect { left, bottom: size.1.saturating_sub(top + height), width, height }
+    }
+
+    /// The cell under a point in pixels from the top left.
+    pub fn cell_at(&self, x: f32, y: f32, size: (u32, u32)) -> Option<usize> {
+        let (width, height) = self.cell_size(size);
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (column, row) = (x as u32 / width, y as u32 / height);
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        Some((row * self.columns + column) as usize)
+    }
+
+    /// The cell `columns` across and `rows` down from cell `index`, stopping
+    /// at the edges.
+    pub fn step(&self, index: usize, columns: i32, rows: i32) -> usize {
+        let index = index as i32;
+        let (last_column, last_row) = (self.columns as i32 - 1, self.rows as i32 - 1);
+        let column = (index % self.columns as i32 + columns).clamp(0, last_column);
+        let row = (index / self.columns as i32 + rows).clamp(0, last_row);
+        (row * self.columns as i32 + column) as usize
+    }
+}
+
+/// One shader drawn in every cell of a grid, with a different value of one
+/// uniform in each.
+pub struct Variation {
+    grid: Grid,
+    uniform: String,
+    values: Vec<f32>
+}
+
+impl Variation {
+    /// Values are spread evenly over `range` if given, otherwise they count up
+    /// from 0 like seeds.
+    pub fn new(grid: Grid, uniform: &str, range: Option<(f32, f32)>) -> Variation {
+        let count = grid.cells();
+        let values = (0..count).map(|i| match range {
+            Some((min, max)) if count > 1 => min + (max - min) * i as f32 / (count - 1) as f32,
+            Some((min, _)) => min,
+            None => i as f32
+        }).collect();
+
+        Variation { grid, uniform: uniform.to_string(), values }
+    }
+
+    /// Render every cell and draw it onto `target`. The pipeline is left
+    /// holding the last cell.
+    pub fn draw<S: Surface>(
+        &self,
+        display: &Display,
+        image: &Texture2d,
+        inputs: &FrameInputs,
+        pipeline: &mut Pipeline,
+        target: &mut S
+    ) -> Result<(), ShadeyError> {
+        let size = target.get_dimensions();
+        pipeline.fix_size(self.grid.cell_size(size));
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+        for (index, &value) in self.values.iter().enumerate() {
+            pipeline.set_uniform(&self.uniform, value);
+            pipeline.invalidate();
+            pipeline.render(display, image, inputs)?;
+            pipeline.present_in(target, self.grid.viewport(index, size))?;
+        }
+
+        Ok(())
+    }
+}
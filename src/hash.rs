@@ -0,0 +1,43 @@
+//! Frame fingerprints, so scripts can spot changes without keeping golden
+//! images: an exact CRC-32 of the pixels and a perceptual hash that only
+//! changes when the picture visibly does.
+
+use image::{imageops, RgbaImage};
+use image::imageops::FilterType;
+
+/// CRC-32 (IEEE) of `bytes`, as used by zip and PNG.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+
+    !bytes.iter().fold(!0u32, |crc, &byte| table[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// Difference hash: each bit says whether a pixel of a 9x8 grayscale
+/// thumbnail is brighter than its right neighbour. Similar pictures differ in
+/// few bits.
+pub fn perceptual(img: &RgbaImage) -> u64 {
+    let thumbnail = imageops::grayscale(&imageops::resize(img, 9, 8, FilterType::Triangle));
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = thumbnail.get_pixel(x, y).data[0];
+            let right = thumbnail.get_pixel(x + 1, y).data[0];
+            hash = hash << 1 | u64::from(left > right);
+        }
+    }
+
+    hash
+}
+
+/// Both hashes in the form `--hash` prints them.
+pub fn describe(img: &RgbaImage) -> String {
+    format!("crc32={:08x} dhash={:016x}", crc32(img), perceptual(img))
+}
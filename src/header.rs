@@ -0,0 +1,86 @@
+//! Optional helpers a shader can ask shadey to insert with a
+//! `#pragma shadey <helper>` line, which is replaced by the helper's code.
+//!
+//! `#pragma shadey aspect` defines:
+//!
+//! - `fragCoordNorm`: `gl_FragCoord.xy` from 0 to 1 across the output
+//! - `uvAspect`: coordinates centred on the output, running from -1 to 1
+//!   along its shorter side so circles stay round
+//! - `aspectRatio`: the output's width divided by its height
+
+use error::ShadeyError;
+
+const PRAGMA: &str = "#pragma shadey";
+
+/// Defined as macros so they can be used anywhere after the pragma,
+/// whether `iResolution` is declared before or after it.
+const ASPECT: &str = "\
+#define fragCoordNorm (gl_FragCoord.xy / iResolution.xy)
+#define uvAspect ((2.0 * gl_FragCoord.xy - iResolution.xy) / min(iResolution.x, iResolution.y))
+#define aspectRatio (iResolution.x / iResolution.y)";
+
+fn declares_resolution(source: &str) -> bool {
+    source.lines().any(|line| {
+        let words: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == ';').filter(|w| !w.is_empty()).collect();
+        words.windows(3).any(|w| w == ["uniform", "vec3", "iResolution"])
+    })
+}
+
+/// Replace the `#pragma shadey` lines of `source` with the helpers they ask
+/// for. `name` is the shader's name for errors.
+pub fn inject(source: &str, name: &str) -> Result<String, ShadeyError> {
+    if !source.contains(PRAGMA) {
+        return Ok(source.to_string());
+    }
+    expand(source, name, false)
+}
+
+/// `source` exactly as shadey compiles it, for use without shadey: helpers
+/// are inserted as by `inject`, but marked with comments rather than
+/// `#line` directives, which would confuse anyone reading the file.
+pub fn flatten(source: &str, name: &str) -> Result<String, ShadeyError> {
+    let mut output = format!("// {}, flattened by shadey bundle-source.\n", name);
+    output.push_str(&expand(source, name, true)?);
+    Ok(output)
+}
+
+fn expand(source: &str, name: &str, annotate: bool) -> Result<String, ShadeyError> {
+    let mut output = String::new();
+    for (number, line) in source.lines().enumerate() {
+        let helper = match line.trim().strip_prefix(PRAGMA) {
+            Some(helper) => helper.trim(),
+            None => {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+        };
+
+        if annotate {
+            output.push_str(&format!("// {} {}: inserted by shadey\n", PRAGMA, helper));
+        }
+        match helper {
+            "aspect" => {
+                if !declares_resolution(source) {
+                    output.push_str("uniform vec3 iResolution;\n");
+                }
+                output.push_str(ASPECT);
+                output.push('\n');
+            },
+            _ => return Err(ShadeyError::invalid(format!(
+                "Unknown `{} {}` in {}. The only helper is `aspect`.",
+                PRAGMA,
+                helper,
+                name
+            )))
+        }
+        if annotate {
+            output.push_str(&format!("// line {} of {}\n", number + 2, name));
+        } else {
+            // Keep compile errors pointing at the lines of the original file.
+            output.push_str(&format!("#line {}\n", number + 2));
+        }
+    }
+
+    Ok(output)
+}
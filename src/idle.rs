@@ -0,0 +1,88 @@
+//! Idle detection for ambient installations. Shaders see the seconds since
+//! the last keyboard or mouse input as `iIdleTime`, and after `--idle-after`
+//! seconds shadey can switch to an attract-mode shader or dim the output,
+//! going back to how things were on the next input.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use error::ShadeyError;
+use Args;
+
+/// What to go back to once someone interacts again.
+pub struct Engaged {
+    /// Whether an attract shader replaced `shader`.
+    pub reload: bool,
+    pub shader: Option<PathBuf>,
+    pub brightness: f32
+}
+
+pub struct Idle {
+    after: Option<Duration>,
+    shader: Option<PathBuf>,
+    brightness: Option<f32>,
+    last_input: Instant,
+    pointer: Option<(f64, f64)>,
+    /// Set while idle actions are in effect.
+    engaged: Option<Engaged>
+}
+
+impl Idle {
+    pub fn new(args: &Args) -> Result<Idle, ShadeyError> {
+        let after = match args.idle_after {
+            Some(seconds) if seconds > 0.0 => Some(Duration::from_secs_f32(seconds)),
+            Some(_) => return Err(ShadeyError::invalid("--idle-after must be a positive number of seconds.")),
+            None => None
+        };
+        if after.is_none() && (args.flag_idle_shader.is_some() || args.idle_brightness.is_some()) {
+            return Err(ShadeyError::invalid("--idle-shader and --idle-brightness need --idle-after."));
+        }
+        if args.idle_brightness.is_some_and(|b| !(0.0..=1.0).contains(&b)) {
+            return Err(ShadeyError::invalid("--idle-brightness must be from 0 to 1."));
+        }
+        if let Some(ref path) = args.flag_idle_shader {
+            if !Path::new(path).is_file() {
+                return Err(ShadeyError::invalid(format!("Idle shader {} is not a file.", path)));
+            }
+        }
+
+        Ok(Idle {
+            after,
+            shader: args.flag_idle_shader.as_ref().map(PathBuf::from),
+            brightness: args.idle_brightness,
+            last_input: Instant::now(),
+            pointer: None,
+            engaged: None
+        })
+    }
+
+    /// Seconds since the last input, or since starting if there's been none.
+    pub fn seconds(&self) -> f32 {
+        self.last_input.elapsed().as_secs_f32()
+    }
+
+    /// Record an input. Returns what to restore if idle actions were in
+    /// effect.
+    pub fn input(&mut self) -> Option<Engaged> {
+        self.last_input = Instant::now();
+        self.engaged.take()
+    }
+
+    /// Whether the pointer really moved. A new window reports the pointer's
+    /// position as a move even when it's still.
+    pub fn pointer_moved(&mut self, position: (f64, f64)) -> bool {
+        self.pointer.replace(position).is_some_and(|last| last != position)
+    }
+
+    /// Whether the idle actions should start now, given the state to come
+    /// back to. Returns the shader to switch to, if any, and the brightness.
+    pub fn check(&mut self, shader: &Option<PathBuf>, brightness: f32) -> Option<(Option<&Path>, Option<f32>)> {
+        let after = self.after?;
+        if self.engaged.is_some() || self.last_input.elapsed() < after {
+            return None;
+        }
+
+        self.engaged = Some(Engaged { reload: self.shader.is_some(), shader: shader.clone(), brightness });
+        Some((self.shader.as_deref(), self.brightness))
+    }
+}
@@ -0,0 +1,52 @@
+/// Mouse state in the form Shadertoy's `iMouse` expects: all positions are in
+/// framebuffer pixels with the origin at the bottom left.
+#[derive(Default)]
+pub struct Mouse {
+    cursor: (f32, f32),
+    /// Where the cursor was the last time the button was held.
+    position: (f32, f32),
+    click: (f32, f32),
+    down: bool
+}
+
+impl Mouse {
+    pub fn new() -> Mouse {
+        Default::default()
+    }
+
+    /// Where the cursor is now, held or not.
+    pub fn cursor(&self) -> (f32, f32) {
+        self.cursor
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.down
+    }
+
+    /// Record a cursor move and return how far it moved.
+    pub fn moved(&mut self, x: f32, y: f32) -> (f32, f32) {
+        let delta = (x - self.cursor.0, y - self.cursor.1);
+        self.cursor = (x, y);
+        if self.down {
+            self.position = self.cursor;
+        }
+        delta
+    }
+
+    pub fn pressed(&mut self) {
+        self.down = true;
+        self.position = self.cursor;
+        self.click = self.cursor;
+    }
+
+    pub fn released(&mut self) {
+        self.down = false;
+    }
+
+    /// `xy` follows the cursor while the button is held, `zw` is where it was
+    /// pressed and is negated once it's released.
+    pub fn uniform(&self) -> [f32; 4] {
+        let sign = if self.down { 1.0 } else { -1.0 };
+        [self.position.0, self.position.1, sign * self.click.0, sign * self.click.1]
+    }
+}
@@ -0,0 +1,171 @@
+//! `shadey emit-jobs`: splits an offline render of a project into chunks of
+//! frames for a render farm, without rendering anything itself. Each chunk
+//! becomes a `shadey render --start-frame --end-frame` command, written out
+//! as:
+//!
+//! - `job-0000.json` and so on, one per chunk, for managers that take JSON;
+//! - `commands.txt`, one shell command per line, for anything that runs
+//!   lines of a file, e.g. GNU parallel;
+//! - `deadline_job.txt` and `deadline_plugin.txt`, a job for Deadline's
+//!   CommandLine plugin, which hands out the same chunks itself.
+//!
+//! Paths are made absolute and frames go to `frames/` beside the jobs, so
+//! the directory should be on storage every worker shares.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chromakey::ChromaKey;
+use constants;
+use error::ShadeyError;
+use events::{push_number, push_string};
+use offline;
+use project::Project;
+use Args;
+
+fn absolute(path: &str) -> Result<PathBuf, ShadeyError> {
+    fs::canonicalize(path).map_err(|e| ShadeyError::io("Could not find", Path::new(path), e))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), ShadeyError> {
+    fs::write(path, contents).map_err(|e| ShadeyError::io("Could not write", path, e))
+}
+
+/// `arg` quoted for a POSIX shell, if it needs to be.
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-+=/.,:@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// `arg` quoted the way Deadline splits arguments, which isn't a shell's.
+fn quote_deadline(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        arg.to_string()
+    } else {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    }
+}
+
+/// What to call the job in farm managers: the project file's name, or its
+/// directory's if it's the usual shadey.toml.
+fn job_name(project: &Path) -> String {
+    let stem = project.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    match project.parent().and_then(Path::file_name) {
+        Some(dir) if stem == "shadey" => dir.to_string_lossy().into_owned(),
+        _ => stem
+    }
+}
+
+/// Arguments to `shadey render` shared by every chunk.
+fn render_args(args: &Args, project: &Path, frames_dir: &Path) -> Result<Vec<String>, ShadeyError> {
+    let mut render = vec![
+        "render".to_string(),
+        format!("--project={}", project.display()),
+        format!("--size={}", args.flag_size),
+        format!("--fps={}", args.flag_fps),
+        format!("--capture-speed={}", args.flag_capture_speed),
+        format!("--time={}", args.flag_time),
+        format!("--time-wrap={}", args.flag_time_wrap)
+    ];
+    if let Some(ref image) = args.flag_image {
+        render.push(format!("--image={}", absolute(image)?.display()));
+    }
+    for uniform in &args.flag_uniform {
+        render.push(format!("--uniform={}", uniform));
+    }
+    if args.flag_decimal_comma {
+        render.push("--decimal-comma".to_string());
+    }
+    if let Some(ref key) = args.flag_chromakey {
+        render.push(format!("--chromakey={}", key));
+    }
+    if args.flag_capture_friendly {
+        render.push("--capture-friendly".to_string());
+    }
+    render.push(format!("--output={}", frames_dir.display()));
+    Ok(render)
+}
+
+pub fn run(args: &Args) -> Result<(), ShadeyError> {
+    let frames = offline::frame_range(args)?;
+    let chunk = args.flag_chunk.as_deref().unwrap_or("");
+    let chunk: u32 = chunk.parse().ok().filter(|&chunk| chunk > 0).
+        ok_or_else(|| ShadeyError::invalid(format!("Invalid chunk size `{}`.", chunk)))?;
+    // Caught now rather than on every worker.
+    offline::parse_size(&args.flag_size)?;
+    constants::parse_all(&args.flag_uniform, args.decimal())?;
+    if let Some(ref key) = args.flag_chromakey {
+        ChromaKey::parse(key, args.decimal())?;
+    }
+    Project::load(&args.arg_project)?;
+
+    let dir = args.flag_output.as_ref().ok_or_else(|| ShadeyError::invalid("Give a directory for the jobs with -o."))?;
+    fs::create_dir_all(dir).map_err(|e| ShadeyError::io("Could not create", Path::new(dir), e))?;
+    let dir = absolute(dir)?;
+    let project = absolute(&args.arg_project)?;
+    let name = job_name(&project);
+    let shadey = env::current_exe().map_err(|error| ShadeyError::Io {
+        action: "Could not find the shadey executable",
+        path: None,
+        error
+    })?;
+    let shadey = shadey.to_string_lossy().into_owned();
+    let render = render_args(args, &project, &dir.join("frames"))?;
+
+    let mut commands = String::new();
+    let mut jobs = 0;
+    for start in frames.clone().step_by(chunk as usize) {
+        let end = start.saturating_add(chunk).min(frames.end) - 1;
+        let mut command = vec![shadey.clone()];
+        command.extend(render.iter().cloned());
+        command.push(format!("--start-frame={}", start));
+        command.push(format!("--end-frame={}", end));
+
+        let mut json = String::from("{\"name\":");
+        push_string(&mut json, &format!("{} {}-{}", name, start, end));
+        json.push_str(",\"start\":");
+        push_number(&mut json, f64::from(start));
+        json.push_str(",\"end\":");
+        push_number(&mut json, f64::from(end));
+        json.push_str(",\"command\":[");
+        for (i, arg) in command.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_string(&mut json, arg);
+        }
+        json.push_str("]}\n");
+        write(&dir.join(format!("job-{:04}.json", jobs)), &json)?;
+
+        let line: Vec<String> = command.iter().map(|arg| quote(arg)).collect();
+        commands.push_str(&line.join(" "));
+        commands.push('\n');
+        jobs += 1;
+    }
+    write(&dir.join("commands.txt"), &commands)?;
+
+    // Deadline fills in each chunk's frames itself.
+    let job_info = format!(
+        "Plugin=CommandLine\nName={}\nFrames={}-{}\nChunkSize={}\n",
+        name,
+        frames.start,
+        frames.end - 1,
+        chunk
+    );
+    let arguments: Vec<String> = render.iter().map(|arg| quote_deadline(arg)).collect();
+    let plugin_info = format!(
+        "Executable={}\nArguments={} --start-frame=<STARTFRAME> --end-frame=<ENDFRAME>\n",
+        shadey,
+        arguments.join(" ")
+    );
+    write(&dir.join("deadline_job.txt"), &job_info)?;
+    write(&dir.join("deadline_plugin.txt"), &plugin_info)?;
+
+    eprintln!("Wrote {} jobs of up to {} frames to {}.", jobs, chunk, dir.display());
+    Ok(())
+}
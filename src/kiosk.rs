@@ -0,0 +1,126 @@
+//! `--kiosk` for unattended installations: only the quit combo does anything
+//! on the keyboard, closing the window is ignored, errors restart the render
+//! loop instead of exiting and the uptime is logged every hour.
+
+use std::time::{Duration, Instant};
+
+use glium::glutin::{KeyboardInput, ModifiersState, VirtualKeyCode};
+
+use error::ShadeyError;
+
+/// How often uptime is logged.
+const REPORT_EVERY: Duration = Duration::from_secs(60 * 60);
+/// Wait before restarting after an error, so a persistent one doesn't spin.
+pub const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+const LETTERS: [VirtualKeyCode; 26] = [
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E,
+    VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J,
+    VirtualKeyCode::K, VirtualKeyCode::L, VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O,
+    VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+    VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y,
+    VirtualKeyCode::Z
+];
+const FUNCTION_KEYS: [VirtualKeyCode; 12] = [
+    VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4,
+    VirtualKeyCode::F5, VirtualKeyCode::F6, VirtualKeyCode::F7, VirtualKeyCode::F8,
+    VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11, VirtualKeyCode::F12
+];
+
+fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c @ 'a'..='z'), None) = (chars.next(), chars.next()) {
+        return Some(LETTERS[c as usize - 'a' as usize]);
+    }
+    if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return number.checked_sub(1).and_then(|i| FUNCTION_KEYS.get(i)).cloned();
+    }
+
+    match name {
+        "escape" | "esc" => Some(VirtualKeyCode::Escape),
+        "space" => Some(VirtualKeyCode::Space),
+        "enter" | "return" => Some(VirtualKeyCode::Return),
+        "backspace" => Some(VirtualKeyCode::Back),
+        "delete" => Some(VirtualKeyCode::Delete),
+        _ => None
+    }
+}
+
+/// A key with the modifiers that must be held with it.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyCombo {
+    key: VirtualKeyCode,
+    modifiers: ModifiersState
+}
+
+impl KeyCombo {
+    /// Parse e.g. `ctrl+shift+q` or `alt+f4`.
+    pub fn parse(spec: &str) -> Result<KeyCombo, ShadeyError> {
+        let invalid = || ShadeyError::invalid(format!(
+            "Invalid key combo `{}`. Expected e.g. ctrl+shift+q.",
+            spec
+        ));
+        let lower = spec.to_lowercase();
+        let mut parts: Vec<&str> = lower.split('+').map(str::trim).collect();
+        let key = parts.pop().and_then(key_from_name).ok_or_else(invalid)?;
+
+        let mut modifiers = ModifiersState::default();
+        for part in parts {
+            match part {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "super" | "logo" => modifiers.logo = true,
+                _ => return Err(invalid())
+            }
+        }
+
+        Ok(KeyCombo { key, modifiers })
+    }
+
+    pub fn matches(&self, input: &KeyboardInput) -> bool {
+        let (held, wanted) = (input.modifiers, self.modifiers);
+        input.virtual_keycode == Some(self.key) &&
+            held.ctrl == wanted.ctrl &&
+            held.shift == wanted.shift &&
+            held.alt == wanted.alt &&
+            held.logo == wanted.logo
+    }
+}
+
+pub struct Kiosk {
+    pub quit: KeyCombo,
+    start: Instant,
+    last_report: Instant,
+    restarts: u32
+}
+
+impl Kiosk {
+    pub fn new(quit: KeyCombo) -> Kiosk {
+        let now = Instant::now();
+        Kiosk { quit, start: now, last_report: now, restarts: 0 }
+    }
+
+    pub fn restarted(&mut self) {
+        self.restarts += 1;
+    }
+
+    /// Call every frame.
+    pub fn tick(&mut self) {
+        if self.last_report.elapsed() >= REPORT_EVERY {
+            self.last_report = Instant::now();
+            self.report();
+        }
+    }
+
+    pub fn report(&self) {
+        let minutes = self.start.elapsed().as_secs() / 60;
+        eprintln!(
+            "Kiosk: up {}h {:02}m with {} restart{}.",
+            minutes / 60,
+            minutes % 60,
+            self.restarts,
+            if self.restarts == 1 { "" } else { "s" }
+        );
+    }
+}
@@ -0,0 +1,31 @@
+#version 140
+
+out vec4 color;
+
+uniform sampler2D image;
+
+// Each output texel sums a BLOCK x BLOCK square, like reduce.frag, so the
+// full size image never needs a float copy.
+const int BLOCK = 8;
+
+vec3 linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// Writes the summed relative luminance to red.
+void main() {
+    ivec2 size = textureSize(image, 0);
+    ivec2 origin = ivec2(gl_FragCoord.xy) * BLOCK;
+
+    float sum = 0.0;
+    for (int y = 0; y < BLOCK; y++) {
+        for (int x = 0; x < BLOCK; x++) {
+            ivec2 q = origin + ivec2(x, y);
+            if (all(lessThan(q, size))) {
+                vec3 c = clamp(texelFetch(image, q, 0).rgb, 0.0, 1.0);
+                sum += dot(linear(c), vec3(0.2126, 0.7152, 0.0722));
+            }
+        }
+    }
+    color = vec4(sum, 0.0, 0.0, 0.0);
+}
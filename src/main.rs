@@ -1,166 +1,1563 @@
 #[macro_use]
 extern crate serde_derive;
+extern crate color_quant;
 extern crate docopt;
+extern crate font8x8;
+extern crate gif;
 #[macro_use]
 extern crate glium;
 extern crate image;
 extern crate inotify;
+extern crate libc;
+extern crate toml;
+extern crate x11_dl;
 
+mod animation;
+mod announce;
+#[cfg(feature = "avif")]
+mod avif;
+mod camera;
+mod chromakey;
+mod clean_feed;
+mod clock;
+mod constants;
+mod control;
+mod cursor;
+mod diff;
+mod editor;
+mod error;
+mod events;
+mod evolve;
+mod explore;
+mod flash;
+mod flicker;
+mod generators;
+mod golden;
+mod gpu;
+mod expr;
+mod grid;
+mod hash;
+mod idle;
+mod header;
+mod input;
+mod jobs;
+mod kiosk;
+mod metrics;
+mod monitor;
+mod numbers;
+mod motion;
+mod offline;
+mod overlay;
+mod package;
+mod palette;
+mod pipeline;
+mod present;
+mod project;
+mod prometheus;
+mod recorder;
+mod render;
+mod scaffold;
+mod schedule;
+mod scope;
+mod scrub;
+mod session_file;
+mod settings;
+mod snapshot;
+mod stdin;
+mod sweep;
+mod tabs;
+mod uniforms;
+mod vision;
+mod watcher;
+#[cfg(feature = "webp")]
+mod webp;
+
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use docopt::Docopt;
-use glium::{glutin, Surface, Display};
+use glium::{glutin, Display, Surface};
 use glium::texture::Texture2d;
-use inotify::{
-    event_mask,
-    watch_mask,
-    Inotify,
-};
 
-const USAGE: &'static str = "
+use announce::Announcer;
+use camera::{Camera, CameraMode};
+use chromakey::ChromaKey;
+use clean_feed::CleanFeed;
+use clock::{Clock, TimeEpoch};
+use control::ControlServer;
+use cursor::Cursor;
+use editor::Editor;
+use error::ShadeyError;
+use events::{EventLog, Field};
+use explore::{Rng, Tween};
+use flash::{FlashCheck, FlashMode};
+use grid::{Grid, Variation};
+use idle::Idle;
+use input::Mouse;
+use kiosk::{KeyCombo, Kiosk};
+use motion::MotionView;
+use numbers::Decimal;
+use overlay::Overlay;
+use palette::{Action, Command, Palette};
+use pipeline::{FrameInputs, Pipeline, PresentedFrame, SavedState};
+use present::PresentStats;
+use project::{OnReload, Project};
+use prometheus::MetricsServer;
+use recorder::Recorder;
+use session_file::SessionFile;
+use scope::Vectorscope;
+use scrub::ScrubPanel;
+use settings::Settings;
+use snapshot::Snapshot;
+use stdin::ShaderStream;
+use tabs::{TabState, Tabs};
+use vision::ColorVision;
+use watcher::{Change, Watcher};
+
+const USAGE: &str = "
 shadey
 Shader testing environment.
 
 Usage:
-  shadey <image> <shader>
+  shadey new <name> [--template=<kind>] [--with-project]
+  shadey bundle-source <shader> [-o <path>]
+  shadey package <format> -o <path>
+  shadey diff [options] [--uniform=<spec>...] <image> <shader-a> <shader-b>
+  shadey diff [options] <image-a> <image-b>
+  shadey evolve [options] [--uniform=<spec>...] --project=<file>
+  shadey render [options] [--uniform=<spec>...] (<shader> | --project=<file>)
+  shadey emit-jobs [options] [--uniform=<spec>...] <project> --chunk=<frames> -o <path>
+  shadey monitors
+  shadey status [--socket=<path>]
+  shadey quit [--socket=<path>]
+  shadey sweep [options] [--uniform=<spec>...] <shader> (--param=<spec>)...
+  shadey [options] [--uniform=<spec>...] <image> <shader>
+  shadey [options] [--uniform=<spec>...] --project=<file>
+  shadey [options] [--uniform=<spec>...] (--tab <tab-image> <tab-shader>)...
+  shadey [options] [--uniform=<spec>...] --session=<name>
   shadey (-h | --help)
 
 Options:
   -h --help          Show this screen.
+  --project=<file>   Run a multi-pass project file (shadey.toml).
+  --tab              Open an image and shader in a tab of its own, switched
+                     between with Ctrl+Tab.
+  --session=<name>   Save the session as <name> rather than last. Given
+                     without a shader, reopen the session saved as <name>.
+  --template=<kind>  Starter shader for `new`: shadertoy, raymarch or
+                     image-filter [default: image-filter].
+  --with-project     Also write a shadey.toml next to the new shader, and a
+                     placeholder image.png if there isn't one.
+  --record=<dir>     Save every presented frame to <dir> as PNGs, along with
+                     the real time each was shown.
+  --camera=<mode>    Drive uCameraPos, uCameraDir and uViewMatrix with the
+                     mouse, as either an orbit or fly camera.
+  --capture-friendly  Request a plain 8-bit RGBA, vsynced, non-multisampled
+                     context that screen capture tools handle reliably.
+  --time-epoch=<epoch>  What iTime counts from: start, the monotonic clock, or
+                     wallclock, the system clock [default: start].
+  --time-wrap=<seconds>  Period iTimeLow wraps around after [default: 3600].
+  --announce         Describe what changes in the window in plain text on
+                     stdout, for screen readers and for use over SSH.
+  --on-reload=<mode>  Whether iTime, iFrame, pass outputs and buffers go on
+                     from where they were when the shaders are reloaded after
+                     an edit, keep-state, or start again, reset
+                     [default: reset].
+  --brightness=<level>  Master brightness of the output from 0 to 1, also
+                     changed with [ and ] [default: 1].
+  --flash-check=<mode>  Measure how often the output flashes and warn above
+                     the photosensitivity threshold, or with limit also dim
+                     it until it's under.
+  --present-stats    Report dropped and late frames as seen by the display,
+                     where GLX_OML_sync_control is available.
+  --chromakey=<key>  Make a colour of the input image transparent, given as
+                     #rrggbb,tolerance, e.g. #00ff00,0.1.
+  --fullscreen=<n>   Open fullscreen on monitor <n> as numbered by
+                     `shadey monitors`. Remembered for the shader or project.
+  --monitor=<name>   Open fullscreen on the monitor called <name>, e.g. DP-2.
+  --windowed         Open in a window, forgetting any remembered monitor.
+  --hide-cursor      Never show the mouse cursor over the window.
+  --cursor-timeout=<seconds>  Hide the cursor after this long without moving.
+  --cursor-shader=<file>  Draw the cursor with a fragment shader instead.
+  --cursor-size=<px>  Size of the square the cursor shader draws
+                     [default: 32].
+  --idle-after=<seconds>  Seconds without input before going idle, switching
+                     to --idle-shader and dimming to --idle-brightness.
+  --idle-shader=<file>  Attract-mode shader to show while idle.
+  --idle-brightness=<level>  Brightness while idle, from 0 to 1.
+  --event-log=<file>  Append a JSON line to <file> for every reload, error,
+                     resize, dropped frame and other notable event.
+  --metrics=<addr>   Serve Prometheus metrics on <addr>, e.g. :9090 for this
+                     machine only or 0.0.0.0:9090 for every interface.
+  --socket=<path>    Control socket to listen on, or for `status` and `quit`
+                     to use, rather than $XDG_RUNTIME_DIR/shadey.sock.
+                     `render` only listens when given one.
+  --kiosk            For unattended installations: ignore every key but the
+                     quit combo and attempts to close the window, hide the
+                     cursor, restart after errors and log the uptime.
+  --quit-key=<combo>  Keys that quit in kiosk mode [default: ctrl+shift+q].
+  --scrub            Make float literals tagged /*@scrub*/ draggable values,
+                     written back into the shader with Ctrl+S.
+  --clean-feed       Also open a borderless window showing only the shader
+                     output, without any overlays.
+  --heatmap=<file>   When diffing two images, also save a heatmap of where they
+                     differ.
+  --grid=<CxR>       Split the window into columns and rows of viewports.
+                     `evolve` shows 3x3 variants if not given.
+  --vary=<uniform>   Uniform that differs between the viewports of --grid.
+  --uniform=<spec>   Constant uniform as type:name=value, where type is float,
+                     int, vec2, vec3 or vec4, e.g. vec3:tint=1,0.5,0.2.
+  --decimal-comma    Read 1,5 as one and a half in every numeric option and
+                     prompt, separating lists such as vec3:tint=1;0,5;0,2
+                     with semicolons.
+  --param=<spec>     Uniform to sweep, as name=start..end:steps. Give two for
+                     a grid.
+  -o <path>, --output=<path>  Directory to write renders to, or the file
+                     bundle-source writes the flattened shader to, or the
+                     directory package lays the package out in.
+  --image=<file>     Image bound to `tex` when rendering offline.
+  --size=<WxH>       Size of offline renders [default: 256x256].
+  --time=<seconds>   Value of iTime for offline renders [default: 0].
+  --frames=<n>       Number of frames to render [default: 1].
+  --start-frame=<n>  First frame to render, as numbered and timed in a longer
+                     render [default: 0].
+  --end-frame=<n>    Last frame to render, instead of giving --frames.
+  --fps=<n>          Frame rate rendered frames advance iTime by [default: 60].
+  --capture-speed=<factor>  How fast iTime advances relative to the rendered
+                     frame rate, e.g. 0.1 for slow motion [default: 1].
+  --bench            Time every rendered frame and report it along with the
+                     GPU's temperature and clock.
+  --hash             Print a CRC-32 and a perceptual hash of every rendered
+                     frame.
+  --gif=<file>       Also write the rendered frames to a looping GIF.
+  --gif-colors=<n>   Colours in each GIF palette, 2 to 256 [default: 256].
+  --gif-palette=<kind>  frame to make a palette for each frame, or global to
+                     share one between them all [default: frame].
+  --gif-dither=<kind>  none, ordered or floyd-steinberg [default: none].
+  --webp=<file>      Also write the rendered frames to a looping WebP, with
+                     libwebp if built with it and otherwise through ffmpeg.
+  --avif=<file>      Also write the rendered frames to an AVIF, with rav1e if
+                     built with it and otherwise through ffmpeg.
+  --archival=<file>  Also write the rendered frames losslessly to FFV1 in
+                     Matroska, through ffmpeg.
+  --find-loop=<max>  Render only up to the frame that best matches the first,
+                     searching up to <max>, e.g. max=10s, for a seamless loop.
+  --chunk=<frames>   Frames in each job written by emit-jobs.
+  --golden=<dir>     Check rendered frames against the references in <dir>,
+                     failing if any is off by more than --tolerance.
+  --tolerance=<spec>  How far frames may be from a reference: exact, or e.g.
+                     epsilon=2,pixels=0.5%,ssim=0.98, unless <dir> has a
+                     tolerance file [default: exact].
+  --flicker-metric=<frames>  Report how much rendered frames vary over
+                     windows of <frames>, to catch shimmer and aliasing.
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
+    cmd_new: bool,
+    cmd_package: bool,
+    cmd_bundle_source: bool,
+    cmd_diff: bool,
+    cmd_evolve: bool,
+    cmd_monitors: bool,
+    cmd_status: bool,
+    cmd_quit: bool,
+    cmd_render: bool,
+    cmd_emit_jobs: bool,
+    cmd_sweep: bool,
+    arg_name: String,
+    arg_format: String,
     arg_image: String,
-    arg_shader: String
+    arg_shader: String,
+    arg_project: String,
+    arg_shader_a: String,
+    arg_shader_b: String,
+    arg_image_a: String,
+    arg_image_b: String,
+    arg_tab_image: Vec<String>,
+    arg_tab_shader: Vec<String>,
+    flag_project: Option<String>,
+    flag_tab: usize,
+    flag_session: Option<String>,
+    flag_template: String,
+    flag_with_project: bool,
+    flag_record: Option<String>,
+    flag_camera: Option<String>,
+    flag_capture_friendly: bool,
+    flag_clean_feed: bool,
+    flag_scrub: bool,
+    flag_chromakey: Option<String>,
+    flag_present_stats: bool,
+    flag_fullscreen: Option<String>,
+    flag_monitor: Option<String>,
+    flag_windowed: bool,
+    flag_hide_cursor: bool,
+    flag_cursor_timeout: Option<String>,
+    flag_cursor_shader: Option<String>,
+    flag_cursor_size: u32,
+    flag_idle_after: Option<String>,
+    flag_idle_shader: Option<String>,
+    flag_idle_brightness: Option<String>,
+    flag_event_log: Option<String>,
+    flag_metrics: Option<String>,
+    flag_socket: Option<String>,
+    flag_kiosk: bool,
+    flag_quit_key: String,
+    flag_flash_check: Option<String>,
+    flag_time_epoch: String,
+    flag_time_wrap: String,
+    flag_on_reload: String,
+    flag_announce: bool,
+    flag_brightness: String,
+    flag_heatmap: Option<String>,
+    flag_grid: Option<String>,
+    flag_vary: Option<String>,
+    flag_uniform: Vec<String>,
+    flag_decimal_comma: bool,
+    flag_param: Vec<String>,
+    flag_output: Option<String>,
+    flag_image: Option<String>,
+    flag_size: String,
+    flag_time: String,
+    flag_frames: String,
+    flag_start_frame: String,
+    flag_end_frame: Option<String>,
+    flag_fps: String,
+    flag_capture_speed: String,
+    flag_hash: bool,
+    flag_flicker_metric: Option<String>,
+    flag_gif: Option<String>,
+    flag_gif_colors: String,
+    flag_gif_palette: String,
+    flag_gif_dither: String,
+    flag_webp: Option<String>,
+    flag_avif: Option<String>,
+    flag_archival: Option<String>,
+    flag_find_loop: Option<String>,
+    flag_chunk: Option<String>,
+    flag_golden: Option<String>,
+    flag_tolerance: String,
+    flag_bench: bool,
+    // The numeric options above, read with `decimal()` by `parse_numbers`.
+    #[serde(skip)]
+    time: f32,
+    #[serde(skip)]
+    time_wrap: f64,
+    #[serde(skip)]
+    brightness: f32,
+    #[serde(skip)]
+    idle_after: Option<f32>,
+    #[serde(skip)]
+    idle_brightness: Option<f32>,
+    #[serde(skip)]
+    cursor_timeout: Option<f32>
+}
+
+impl Args {
+    /// How to read the numbers typed into options and prompts.
+    fn decimal(&self) -> Decimal {
+        Decimal::from_flag(self.flag_decimal_comma)
+    }
+
+    /// Read the numeric options that docopt leaves as text, so they follow
+    /// `--decimal-comma` like every other typed number.
+    fn parse_numbers(&mut self) -> Result<(), ShadeyError> {
+        let decimal = self.decimal();
+        let number = |option: &str, text: &str| decimal.parse::<f32>(text).
+            map_err(|_| ShadeyError::invalid(format!("Invalid {} `{}`: expected a number.", option, text)));
+        let optional = |option: &str, text: &Option<String>| match *text {
+            Some(ref text) => number(option, text).map(Some),
+            None => Ok(None)
+        };
+
+        self.time = number("--time", &self.flag_time)?;
+        self.time_wrap = f64::from(number("--time-wrap", &self.flag_time_wrap)?);
+        self.brightness = number("--brightness", &self.flag_brightness)?;
+        self.idle_after = optional("--idle-after", &self.flag_idle_after)?;
+        self.idle_brightness = optional("--idle-brightness", &self.flag_idle_brightness)?;
+        self.cursor_timeout = optional("--cursor-timeout", &self.flag_cursor_timeout)?;
+
+        if !(0.0..=1.0).contains(&self.brightness) {
+            return Err(ShadeyError::invalid("--brightness must be from 0 to 1."));
+        }
+        if self.time_wrap <= 0.0 {
+            return Err(ShadeyError::invalid("--time-wrap must be a positive number of seconds."));
+        }
+        Ok(())
+    }
+}
+
+/// State that outlives a single reload.
+struct Session {
+    recorder: Option<Recorder>,
+    camera: Option<Camera>,
+    /// Shader picked from the command palette, replacing the final pass's.
+    shader: Option<PathBuf>,
+    /// Uniform values set from the command palette.
+    uniforms: BTreeMap<String, f32>,
+    /// The shader, or project file, that `uniforms`, `camera` and `monitor`
+    /// are saved against.
+    settings_for: Option<PathBuf>,
+    /// Snapshot slots. Mirrors the project file in project mode.
+    snapshots: BTreeMap<u32, Snapshot>,
+    /// Shader source pushed over stdin, when the shader path is `-`.
+    stdin: Option<ShaderStream>,
+    rng: Rng,
+    /// Index of the schedule entry last applied.
+    scheduled: Option<usize>,
+    /// Uniforms of a scheduled entry that also loads a shader, to be set
+    /// once it has loaded.
+    scheduled_uniforms: BTreeMap<String, f32>,
+    /// Master brightness of the output, from 0 to 1.
+    brightness: f32,
+    /// Colour vision deficiency simulated in the window, cycled with F3.
+    vision: ColorVision,
+    /// Whether the F4 vectorscope is showing.
+    vectorscope: bool,
+    /// Whether F5 motion estimation is showing.
+    motion: bool,
+    /// Output the window is fullscreen on, saved with the settings.
+    monitor: Option<String>,
+    /// Whether --windowed asked to forget the saved monitor.
+    windowed: bool,
+    /// Transition between snapshots in progress.
+    tween: Option<Tween>,
+    kiosk: Option<Kiosk>,
+    idle: Idle,
+    events: Option<EventLog>,
+    /// Tabs from --tab, or none for a single shader or project.
+    tabs: Option<Tabs>,
+    /// Where a single shader or project left off in a saved session.
+    resume: Option<TabState>,
+    /// Playback time of the showing shader.
+    time: f64,
+    /// `[x, y, width, height]` of the window, when it isn't fullscreen.
+    window: Option<[i32; 4]>,
+    /// Name the session is saved under, if it can be reopened.
+    saved_as: Option<String>,
+    /// The F2 editor, kept open over reloads such as its own saves.
+    editor: Editor,
+    metrics: Option<MetricsServer>,
+    control: Option<ControlServer>,
+    on_reload: OnReload,
+    /// What to carry on from after a reload, from `--on-reload`.
+    carried: Option<Carried>,
+    announcer: Option<Announcer>
+}
+
+/// State read back just before the shaders were reloaded.
+struct Carried {
+    /// Time and number of the next frame, when they go on too.
+    clock: Option<(f64, i32)>,
+    pipeline: SavedState
 }
 
+/// Frames before the smoothed frame rate is trusted for spotting drops.
+const FRAME_RATE_SETTLED: i32 = 30;
+
+/// How often the session is saved while running, in case of a crash.
+const SESSION_SAVE_EVERY: Duration = Duration::from_secs(10);
+
+/// How much [ and ] change the brightness by.
+const BRIGHTNESS_STEP: f32 = 0.1;
+
 #[derive(PartialEq)]
 enum ProgramStatus {
     Done,
     Reload
 }
 
-#[derive(Copy, Clone)]
-struct Vertex {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
-}
-implement_vertex!(Vertex, position, tex_coords);
-
 fn main() {
-    let args: Args = Docopt::new(USAGE).
+    let mut args: Args = Docopt::new(USAGE).
         and_then(|d| d.deserialize()).
         unwrap_or_else(|e| e.exit());
 
+    if let Err(e) = args.parse_numbers() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+
+    if args.cmd_monitors {
+        if let Err(e) = monitor::list() {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if args.cmd_status || args.cmd_quit {
+        let socket = args.flag_socket.as_deref();
+        let result = if args.cmd_status { control::status(socket) } else { control::quit(socket) };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if args.cmd_bundle_source {
+        if let Err(e) = bundle_source(&args.arg_shader, args.flag_output.as_deref()) {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if args.cmd_package {
+        if let Err(e) = package::run(&args.arg_format, args.flag_output.as_deref()) {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if args.cmd_new {
+        if let Err(e) = scaffold::create(&args.arg_name, &args.flag_template, args.flag_with_project) {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if args.cmd_diff || args.cmd_evolve || args.cmd_render || args.cmd_emit_jobs || args.cmd_sweep {
+        let result = if args.cmd_diff {
+            diff::run(&args)
+        } else if args.cmd_evolve {
+            evolve::run(&args)
+        } else if args.cmd_render {
+            render::run(&args)
+        } else if args.cmd_emit_jobs {
+            jobs::run(&args)
+        } else {
+            sweep::run(&args)
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            // So scripts and CI can tell, e.g. when frames miss --golden.
+            process::exit(1);
+        }
+        return;
+    }
+
+    // A session name on its own reopens that session.
+    let reopened = match args.flag_session {
+        Some(ref name) if args.arg_shader.is_empty() && args.flag_project.is_none() && args.flag_tab == 0 => {
+            match SessionFile::load(name) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return;
+                }
+            }
+        },
+        _ => None
+    };
+    if let Some(ref file) = reopened {
+        file.apply(&mut args);
+    }
+
+    let mut session = match start_session(&args) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    if let Some(ref file) = reopened {
+        file.restore(&mut session);
+    }
+
+    let shader = if args.flag_tab > 0 {
+        args.arg_tab_shader.join(",")
+    } else {
+        args.flag_project.as_ref().unwrap_or(&args.arg_shader).clone()
+    };
+    log_event(&mut session, "start", &[("shader", Field::Str(&shader))]);
     loop {
-        match run_shader(&args) {
+        let result = run_shader(&args, &mut session);
+        save_session(&args, &mut session);
+        match result {
             Ok(status) => {
                 if status == ProgramStatus::Done {
-                    return;
+                    break;
+                }
+                log_event(&mut session, "reload", &[]);
+                if let Some(ref metrics) = session.metrics {
+                    metrics.reloaded();
                 }
             },
             Err(e) => {
                 eprintln!("Error: {}", e);
-                return;
+                log_error(&mut session, &e);
+                match session.kiosk {
+                    Some(ref mut kiosk) => {
+                        eprintln!("Restarting in {} seconds.", kiosk::RESTART_DELAY.as_secs());
+                        thread::sleep(kiosk::RESTART_DELAY);
+                        kiosk.restarted();
+                    },
+                    None => break
+                }
+                log_event(&mut session, "restart", &[]);
             }
         }
     }
+    log_event(&mut session, "exit", &[]);
+
+    if let Some(ref kiosk) = session.kiosk {
+        kiosk.report();
+    }
+    save_settings(&session);
+
+    if let Some(mut recorder) = session.recorder {
+        if let Err(e) = recorder.finish() {
+            eprintln!("Error: {}", e);
+        }
+    }
+    // Tells anyone waiting on `shadey quit` that everything is saved.
+    drop(session.control);
+}
+
+fn start_session(args: &Args) -> Result<Session, ShadeyError> {
+    let recorder = match args.flag_record {
+        Some(ref dir) => Some(Recorder::new(dir)?),
+        None => None
+    };
+    let camera = match args.flag_camera {
+        Some(ref mode) => Some(Camera::new(CameraMode::from_name(mode)?)),
+        None => None
+    };
+
+    let events = match args.flag_event_log {
+        Some(ref path) => Some(EventLog::open(path)?),
+        None => None
+    };
+    let metrics = match args.flag_metrics {
+        Some(ref address) => Some(MetricsServer::start(address)?),
+        None => None
+    };
+    let kiosk = if args.flag_kiosk {
+        Some(Kiosk::new(KeyCombo::parse(&args.flag_quit_key)?))
+    } else {
+        None
+    };
+
+    let stdin = if args.flag_project.is_none() && args.arg_shader == stdin::PATH {
+        Some(ShaderStream::spawn())
+    } else {
+        None
+    };
+    // Shaders pushed over stdin are gone once shadey exits.
+    let saved_as = match stdin {
+        Some(_) => None,
+        None => Some(args.flag_session.clone().unwrap_or_else(|| session_file::LAST.to_string()))
+    };
+
+    Ok(Session {
+        recorder,
+        camera,
+        shader: None,
+        uniforms: BTreeMap::new(),
+        settings_for: None,
+        snapshots: BTreeMap::new(),
+        stdin,
+        rng: Rng::new(),
+        scheduled: None,
+        scheduled_uniforms: BTreeMap::new(),
+        brightness: args.brightness,
+        vision: ColorVision::Normal,
+        vectorscope: false,
+        motion: false,
+        monitor: monitor::from_args(args)?,
+        windowed: args.flag_windowed,
+        tween: None,
+        kiosk,
+        idle: Idle::new(args)?,
+        events,
+        tabs: Tabs::from_args(args)?,
+        resume: None,
+        time: 0.0,
+        window: None,
+        saved_as,
+        editor: Editor::new(),
+        metrics,
+        control: ControlServer::start(args.flag_socket.as_deref()),
+        on_reload: OnReload::from_name(&args.flag_on_reload)?,
+        carried: None,
+        announcer: if args.flag_announce { Some(Announcer::new()) } else { None }
+    })
+}
+
+fn log_event(session: &mut Session, event: &str, fields: &[(&str, Field)]) {
+    if let Some(ref mut log) = session.events {
+        log.log(event, fields);
+    }
+}
+
+/// Describe a change for `--announce`.
+fn announce(session: &Session, text: &str) {
+    if let Some(ref announcer) = session.announcer {
+        announcer.say(text);
+    }
 }
 
-fn init_display(events_loop: &glutin::EventsLoop) -> Result<Display, &'static str> {
-    let window = glutin::WindowBuilder::new().with_title("Shadey");
+fn log_error(session: &mut Session, error: &ShadeyError) {
+    if let Some(ref metrics) = session.metrics {
+        metrics.error();
+    }
+    let message = error.to_string();
+    if let Some(ref control) = session.control {
+        control.error(&message);
+    }
+    match *error {
+        ShadeyError::Compile { ref shader, .. } => log_event(session, "compile_error", &[
+            ("shader", Field::Str(shader)),
+            ("message", Field::Str(&message))
+        ]),
+        _ => log_event(session, "error", &[("message", Field::Str(&message))])
+    }
+}
+
+/// Save the session so it can be reopened, giving up on it after a failure.
+fn save_session(args: &Args, session: &mut Session) {
+    let name = match session.saved_as {
+        Some(ref name) => name,
+        None => return
+    };
+    let result = match SessionFile::capture(args, session) {
+        Some(file) => file.save(name),
+        None => return
+    };
+    if let Err(e) = result {
+        eprintln!("Warning: {}. The session won't be saved.", e);
+        session.saved_as = None;
+    }
+}
+
+fn save_settings(session: &Session) {
+    let shader = match session.settings_for {
+        Some(ref shader) => shader,
+        None => return
+    };
+    if session.uniforms.is_empty() && session.camera.is_none() && session.monitor.is_none() && !session.windowed {
+        return;
+    }
+
+    let settings = Settings {
+        uniforms: session.uniforms.clone(),
+        camera: session.camera.clone(),
+        monitor: session.monitor.clone()
+    };
+    if let Err(e) = settings.save(shader) {
+        eprintln!("Warning: {}", e);
+    }
+}
+
+/// Restore the settings saved for `owner`, a shader or project file, first
+/// saving those of the one that was running before.
+fn switch_settings(session: &mut Session, owner: &Path) {
+    if session.settings_for.as_deref() == Some(owner) || owner == Path::new(stdin::PATH) {
+        return;
+    }
+    save_settings(session);
+
+    match Settings::load(owner) {
+        Ok(settings) => {
+            session.uniforms = settings.uniforms;
+            // An explicit --camera mode wins over a saved camera of another kind.
+            if let Some(camera) = settings.camera {
+                if session.camera.as_ref().is_none_or(|c| c.mode() == camera.mode()) {
+                    session.camera = Some(camera);
+                }
+            }
+            if session.monitor.is_none() && !session.windowed {
+                session.monitor = settings.monitor;
+            }
+        },
+        Err(e) => eprintln!("Warning: {}", e)
+    }
+    session.settings_for = Some(owner.to_path_buf());
+}
+
+fn context_builder(args: &Args) -> glutin::ContextBuilder<'static> {
     let context = glutin::ContextBuilder::new();
+    if !args.flag_capture_friendly {
+        return context;
+    }
+
+    // Capture tools tend to choke on sRGB, multisampled or tearing output.
+    context.
+        with_pixel_format(24, 8).
+        with_srgb(false).
+        with_multisampling(0).
+        with_vsync(true)
+}
 
-    Display::new(window, context, &events_loop).
-        map_err(|_| "Could not initialize the display.")
+/// Open the main window, fullscreen on `monitor` if it's connected.
+fn init_display(events_loop: &glutin::EventsLoop, args: &Args, monitor: Option<&str>) -> Result<Display, ShadeyError> {
+    let mut window = glutin::WindowBuilder::new().with_title("Shadey");
+    let output = monitor.and_then(|name| {
+        let output = monitor::find(name);
+        if output.is_none() {
+            eprintln!("Warning: Monitor {} isn't connected, opening a window instead.", name);
+        }
+        output
+    });
+    // Shown once it's been moved onto the output.
+    if let Some(ref output) = output {
+        window = window.
+            with_dimensions(output.size.0, output.size.1).
+            with_decorations(false).
+            with_visibility(false);
+    }
+
+    let display = Display::new(window, context_builder(args), events_loop).
+        map_err(|e| ShadeyError::Context(e.to_string()))?;
+    if let Some(output) = output {
+        let window = display.gl_window();
+        window.set_position(output.position.0, output.position.1);
+        window.show();
+        monitor::request_fullscreen(&display)?;
+    }
+
+    Ok(display)
 }
 
-fn texture_from_path(display: &Display, image_path: &String) -> Result<Texture2d, &'static str> {
-    let img = image::open(&Path::new(image_path)).map_err(|_| "Could not open file.")?.to_rgba();
+fn texture_from_path(display: &Display, image_path: &Path) -> Result<Texture2d, ShadeyError> {
+    let img = image::open(image_path).map_err(|error| match error {
+        image::ImageError::IoError(e) => ShadeyError::io("Could not open image", image_path, e),
+        error => ShadeyError::Image { path: image_path.to_path_buf(), error }
+    })?.to_rgba();
     let dims = img.dimensions();
     let gl_image = glium::texture::RawImage2d::from_raw_rgba_reversed(&img.into_raw(), dims);
 
     glium::texture::Texture2d::new(display, gl_image).
-        map_err(|_| "Could not create texture from image.")
+        map_err(|_| ShadeyError::Gl("Could not create texture from image."))
 }
 
-fn fullscreen() -> Vec<Vertex> {
-    vec![
-        Vertex { position: [-1.0, -1.0], tex_coords: [0.0, 0.0] },
-        Vertex { position: [-1.0,  1.0], tex_coords: [0.0, 1.0] },
-        Vertex { position: [ 1.0,  1.0], tex_coords: [1.0, 1.0] },
-
-        Vertex { position: [-1.0, -1.0], tex_coords: [0.0, 0.0] },
-        Vertex { position: [ 1.0,  1.0], tex_coords: [1.0, 1.0] },
-        Vertex { position: [ 1.0, -1.0], tex_coords: [1.0, 0.0] }
-    ]
+/// The image shaders sample as `tex`, keyed if `--chromakey` is given.
+fn input_image(display: &Display, path: &Path, args: &Args) -> Result<Texture2d, ShadeyError> {
+    let texture = texture_from_path(display, path)?;
+    match args.flag_chromakey {
+        Some(ref spec) => ChromaKey::parse(spec, args.decimal())?.apply(display, &texture),
+        None => Ok(texture)
+    }
 }
 
-fn read_shader(shader_path: &String) -> Result<String, &'static str> {
-    let mut file = File::open(shader_path).map_err(|_| "Could not open shader path.")?;
+fn read_shader<P: AsRef<Path>>(shader_path: P) -> Result<String, ShadeyError> {
+    let path = shader_path.as_ref();
+    let mut file = File::open(path).map_err(|e| ShadeyError::io("Could not open shader", path, e))?;
     let mut contents = String::new();
-    file.read_to_string(&mut contents).map_err(|_| "Could not read shader file.")?;
+    file.read_to_string(&mut contents).map_err(|e| ShadeyError::io("Could not read shader", path, e))?;
 
     Ok(contents)
 }
 
-fn run_shader(args: &Args) -> Result<ProgramStatus, &'static str> {
-    // Set up inotify
-    let mut file_updates = Inotify::init().map_err(|_| "Failed to initialize an inotify.")?;
-    file_updates.add_watch(&args.arg_image, watch_mask::MODIFY).
-        map_err(|_| "Could not add watch to image file.")?;
-    file_updates.add_watch(&args.arg_shader, watch_mask::MODIFY).
-        map_err(|_| "Could not add watch to shader file.")?;
+/// Write `shader` as shadey compiles it to `output`, or to stdout.
+fn bundle_source(shader: &str, output: Option<&str>) -> Result<(), ShadeyError> {
+    let flat = header::flatten(&read_shader(shader)?, shader)?;
+    match output {
+        Some(path) => File::create(path).
+            and_then(|mut file| file.write_all(flat.as_bytes())).
+            map_err(|e| ShadeyError::io("Could not write", Path::new(path), e)),
+        None => {
+            print!("{}", flat);
+            Ok(())
+        }
+    }
+}
+
+/// `iTime` gets choppy after running for hours, which wallclock time is for.
+fn warn_plain_time(project: &Project) {
+    for pass in project.passes.iter().filter(|pass| pass.shader != Path::new(stdin::PATH)) {
+        if read_shader(&pass.shader).is_ok_and(|source| clock::uses_plain_time(&source)) {
+            eprintln!(
+                "Warning: {} uses iTime, which loses precision after a few hours. Use iTimeLow or \
+                 iTimeHigh + iTimeLow to keep long runs smooth.",
+                pass.shader.display()
+            );
+        }
+    }
+}
+
+fn load_project(args: &Args) -> Result<Project, ShadeyError> {
+    match args.flag_project {
+        Some(ref path) => Project::load(path),
+        None => Ok(Project::single(&args.arg_image, &args.arg_shader))
+    }
+}
+
+fn palette_commands(pipeline: &Pipeline) -> Vec<Command> {
+    let mut commands = vec![
+        Command::immediate("Screenshot", Action::Screenshot),
+        Command::immediate("Reload", Action::Reload),
+        Command::immediate("Quit", Action::Quit),
+        Command::load_shader(),
+        Command::immediate("Randomize uniforms", Action::Randomize),
+        Command::interpolate()
+    ];
+    if !pipeline.scrubs().is_empty() {
+        commands.push(Command::immediate("Save scrubbed values", Action::SaveScrubs));
+    }
+    commands.push(Command::immediate("Toggle vectorscope", Action::ToggleVectorscope));
+    commands.push(Command::immediate("Toggle motion view", Action::ToggleMotion));
+    for &vision in &vision::ALL {
+        commands.push(Command::immediate(&format!("Colour vision: {}", vision.name()), Action::SetVision(vision)));
+    }
+    for name in pipeline.pass_names() {
+        commands.push(Command::immediate(&format!("Toggle pass: {}", name), Action::TogglePass(name.to_string())));
+    }
+    for name in pipeline.uniform_names() {
+        commands.push(Command::set_uniform(name));
+    }
+
+    commands
+}
+
+fn apply_snapshot(snapshot: Snapshot, pipeline: &mut Pipeline, session: &mut Session) {
+    for (name, value) in snapshot.uniforms {
+        if pipeline.set_uniform(&name, value) {
+            session.uniforms.insert(name, value);
+        }
+    }
+    if snapshot.camera.is_some() {
+        session.camera = snapshot.camera;
+    }
+}
+
+/// Carry out a palette action. Returns a status when the action ends the
+/// current run.
+fn run_action(action: Action, args: &Args, pipeline: &mut Pipeline, session: &mut Session) ->
+    Option<ProgramStatus>
+{
+    match action {
+        Action::Quit => return Some(ProgramStatus::Done),
+        Action::Reload => return Some(ProgramStatus::Reload),
+        Action::LoadShader(path) => {
+            if !Path::new(&path).is_file() {
+                eprintln!("Warning: {} is not a file.", path);
+                return None;
+            }
+            session.shader = Some(PathBuf::from(path));
+            return Some(ProgramStatus::Reload);
+        },
+        Action::Screenshot => {
+            let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let path = format!("shadey-{}.png", seconds);
+            match recorder::save_texture(pipeline.output(), Path::new(&path)) {
+                Ok(()) => println!("Saved {}", path),
+                Err(e) => eprintln!("Warning: {}", e)
+            }
+        },
+        Action::TogglePass(name) => {
+            if let Some(enabled) = pipeline.toggle_pass(&name) {
+                println!("Pass {} {}", name, if enabled { "enabled" } else { "disabled" });
+            }
+        },
+        Action::SetUniform(name, value) => {
+            if pipeline.set_uniform(&name, value) {
+                announce(session, &format!("{} = {}", name, value));
+                session.uniforms.insert(name, value);
+            }
+        },
+        Action::SaveSnapshot(slot) => {
+            let snapshot = Snapshot {
+                uniforms: pipeline.uniforms().clone(),
+                camera: session.camera.clone()
+            };
+            session.snapshots.insert(slot, snapshot);
+            match args.flag_project {
+                Some(ref path) => match snapshot::write(Path::new(path), &session.snapshots) {
+                    Ok(()) => println!("Saved snapshot {} to {}", slot, path),
+                    Err(e) => eprintln!("Warning: {}", e)
+                },
+                None => println!("Saved snapshot {} for this session", slot)
+            }
+        },
+        Action::RecallSnapshot(slot) => {
+            match session.snapshots.get(&slot).cloned() {
+                Some(snapshot) => {
+                    session.tween = None;
+                    apply_snapshot(snapshot, pipeline, session);
+                    announce(session, &format!("Recalled snapshot {}", slot));
+                },
+                None => eprintln!("Warning: Snapshot {} is empty.", slot)
+            }
+        },
+        Action::Randomize => {
+            if pipeline.uniform_ranges().is_empty() {
+                eprintln!("Warning: No uniforms declare a min and max to randomize within.");
+            }
+            let ranges = pipeline.uniform_ranges().clone();
+            let uniforms: BTreeMap<String, f32> = ranges.into_iter().
+                map(|(name, (min, max))| (name, session.rng.range(min, max))).
+                collect();
+            for (name, value) in &uniforms {
+                announce(session, &format!("{} = {}", name, value));
+            }
+            session.tween = None;
+            apply_snapshot(Snapshot { uniforms, camera: None }, pipeline, session);
+        },
+        Action::Interpolate(from, to, seconds) => {
+            match (session.snapshots.get(&from), session.snapshots.get(&to)) {
+                (Some(a), Some(b)) => {
+                    session.tween = Some(Tween::new(a.clone(), b.clone(), seconds));
+                    announce(session, &format!("Interpolating from snapshot {} to {} over {}s", from, to, seconds));
+                },
+                _ => eprintln!("Warning: Both snapshots must have been saved first.")
+            }
+        },
+        Action::SaveScrubs => {
+            if let Err(e) = scrub::write_back(pipeline.scrubs()) {
+                eprintln!("Warning: {}", e);
+            }
+        },
+        Action::SetBrightness(brightness) => {
+            session.brightness = brightness.clamp(0.0, 1.0);
+            announce(session, &format!("Brightness: {:.0}%", session.brightness * 100.0));
+        },
+        Action::SetVision(vision) => {
+            session.vision = vision;
+            announce(session, &format!("Colour vision: {}", vision.name()));
+        },
+        Action::ToggleVectorscope => {
+            session.vectorscope = !session.vectorscope;
+            announce(session, if session.vectorscope { "Vectorscope shown" } else { "Vectorscope hidden" });
+        },
+        Action::ToggleMotion => {
+            session.motion = !session.motion;
+            announce(session, if session.motion { "Motion view shown" } else { "Motion view hidden" });
+        }
+    }
+
+    None
+}
+
+/// Read back what `--on-reload` and the passes' `on_reload` keep, before
+/// reloading after an edit.
+fn carry_state(session: &mut Session, clock: &Clock, pipeline: &Pipeline) {
+    let clock = match session.on_reload {
+        OnReload::KeepState => Some((clock.time(), clock.frame())),
+        OnReload::Reset => None
+    };
+    session.carried = Some(Carried { clock, pipeline: pipeline.save_state(session.on_reload) });
+}
+
+fn run_shader(args: &Args, session: &mut Session) -> Result<ProgramStatus, ShadeyError> {
+    // Only for the reload straight after, not e.g. a restart after an error.
+    let carried = session.carried.take();
+    let resumed = match session.tabs {
+        Some(ref mut tabs) => tabs.resume(),
+        None => session.resume.take()
+    };
+    let mut project = match session.tabs {
+        Some(ref tabs) => tabs.project(),
+        None => load_project(args)?
+    };
+    if let Some(ref state) = resumed {
+        session.shader = state.shader.clone();
+    }
+    if let Some(ref shader) = session.shader {
+        project.replace_shader(shader);
+    }
+    project.scrub = args.flag_scrub;
+    let shader = project.passes[project.passes.len() - 1].shader.clone();
+    // A project's settings go with it, whichever shaders it runs.
+    match args.flag_project {
+        Some(ref path) if session.tabs.is_none() => switch_settings(session, Path::new(path)),
+        _ => switch_settings(session, &shader)
+    }
+    if let Some(ref control) = session.control {
+        control.shader(&shader);
+    }
+    session.editor.follow(&shader);
+    let scheduled = mem::take(&mut session.scheduled_uniforms);
+    session.uniforms.extend(scheduled);
+    if let Some(ref state) = resumed {
+        session.uniforms.extend(state.uniforms.clone());
+    }
+    if args.flag_project.is_some() {
+        session.snapshots = project.snapshots.clone();
+    }
+
+    let constants = constants::parse_all(&args.flag_uniform, args.decimal())?;
+    let epoch = TimeEpoch::from_name(&args.flag_time_epoch)?;
+    if epoch == TimeEpoch::Wallclock {
+        warn_plain_time(&project);
+    }
+    let variation = match (args.flag_grid.as_ref(), args.flag_vary.as_ref()) {
+        (Some(grid), Some(name)) => {
+            // The varied uniform doesn't have to be declared by the project.
+            project.uniforms.entry(name.clone()).or_insert(0.0);
+            Some(Variation::new(Grid::parse(grid)?, name, project.ranges.get(name).cloned()))
+        },
+        (None, None) => None,
+        _ => return Err(ShadeyError::invalid("--grid and --vary must be given together."))
+    };
+    let flash_mode = match args.flag_flash_check {
+        Some(_) if variation.is_some() => {
+            return Err(ShadeyError::invalid("--flash-check can't be combined with --grid."));
+        },
+        Some(ref name) => Some(FlashMode::from_name(name)?),
+        None => None
+    };
+    if let Some(ref path) = args.flag_cursor_shader {
+        project.watched.push(PathBuf::from(path));
+    }
+    let mut watcher = Watcher::new(&project)?;
 
     // Set up window
     let mut events_loop = glutin::EventsLoop::new();
-    let display = init_display(&events_loop)?;
-    let texture = texture_from_path(&display, &args.arg_image)?;
-    let shape = fullscreen();
+    let display = init_display(&events_loop, args, session.monitor.as_deref())?;
+    let mut texture = input_image(&display, &project.image, args)?;
+    let mut pipeline = Pipeline::new(&display, &project)?;
+    pipeline.set_constants(constants)?;
+    if session.metrics.is_some() {
+        pipeline.time_passes();
+    }
+    for (name, &value) in &session.uniforms {
+        pipeline.set_uniform(name, value);
+    }
+    announce(session, &format!("Running {}", shader.display()));
+    // A shader loaded from the palette takes over from stdin.
+    let from_stdin = session.shader.is_none();
+    if let Some(source) = session.stdin.as_ref().and_then(ShaderStream::latest) {
+        if from_stdin {
+            pipeline.set_final_shader(&display, source)?;
+        }
+    }
+    let main_window = display.gl_window().id();
+    if session.monitor.is_none() {
+        let window = display.gl_window();
+        match session.window {
+            Some([x, y, width, height]) => {
+                window.set_position(x, y);
+                window.set_inner_size(width as u32, height as u32);
+            },
+            None => if let (Some((x, y)), Some((width, height))) = (window.get_position(), window.get_inner_size()) {
+                session.window = Some([x, y, width as i32, height as i32]);
+            }
+        }
+    }
+    let mut cursor = Cursor::new(&display, args)?;
+    let mut clean_feed = if args.flag_clean_feed {
+        Some(CleanFeed::new(&events_loop, context_builder(args))?)
+    } else {
+        None
+    };
+    let mut overlay = Overlay::new(&display)?;
+    let mut presented = PresentedFrame::new(&display)?;
+    let mut palette = Palette::new(args.decimal());
+    let mut scrub_panel = ScrubPanel::new();
+    let scope = Vectorscope::new(&display)?;
+    let mut motion = MotionView::new(&display)?;
+    let mut shift_held = false;
+    let mut present_stats = if args.flag_present_stats {
+        let stats = PresentStats::new(&display);
+        if stats.is_none() {
+            eprintln!("Warning: Present stats need an X11 window and GLX_OML_sync_control.");
+        }
+        stats
+    } else {
+        None
+    };
 
-    let vertex_buffer = glium::VertexBuffer::new(&display, &shape).unwrap();
-    let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+    let mut flash_check = match flash_mode {
+        Some(mode) => Some(FlashCheck::new(&display, mode)?),
+        None => None
+    };
 
-    // Compile shaders
-    let vertex_shader_src = include_str!("main.vert");
-    let fragment_shader_src = read_shader(&args.arg_shader)?;
-    let program = glium::Program::from_source(&display, vertex_shader_src, &fragment_shader_src, None).unwrap();
+    let mut clock = Clock::new(epoch, args.time_wrap);
+    if let Some(state) = resumed {
+        clock.set_time(state.time);
+    }
+    if let Some(carried) = carried {
+        if let Some((time, frame)) = carried.clock {
+            clock.resume(time, frame);
+        }
+        pipeline.restore_state(&display, carried.pipeline)?;
+    }
+    let mut mouse = Mouse::new();
+    let mut window_size = None;
+    let mut session_saved = Instant::now();
+    loop {
+        if let Some(source) = session.stdin.as_mut().and_then(ShaderStream::poll) {
+            if from_stdin {
+                if let Err(e) = pipeline.set_final_shader(&display, source) {
+                    eprintln!("Warning: {}", e);
+                    log_error(session, &e);
+                }
+            }
+        }
+
+        if let Some(state) = session.tween.as_ref().map(Tween::sample) {
+            apply_snapshot(state, &mut pipeline, session);
+            if session.tween.as_ref().is_some_and(Tween::is_done) {
+                session.tween = None;
+            }
+        }
+
+        let inputs = FrameInputs {
+            mouse: mouse.uniform(),
+            cursor: [mouse.cursor().0, mouse.cursor().1],
+            idle_time: session.idle.seconds(),
+            camera: session.camera.as_ref().map(Camera::uniforms),
+            ..clock.tick()
+        };
+        session.time = clock.time();
+        // A frame that took well over the usual time means some were missed.
+        let missed = (inputs.time_delta * inputs.frame_rate).round() - 1.0;
+        if inputs.frame > FRAME_RATE_SETTLED && missed >= 2.0 {
+            log_event(session, "dropped_frames", &[
+                ("count", Field::Num(f64::from(missed))),
+                ("frame_time", Field::Num(f64::from(inputs.time_delta)))
+            ]);
+        }
+        if variation.is_none() {
+            pipeline.render(&display, &texture, &inputs)?;
+        }
+        if let Some(ref mut check) = flash_check {
+            check.update(&display, pipeline.output())?;
+        }
+        if session.motion {
+            motion.update(&display, pipeline.output())?;
+        } else {
+            motion.reset();
+        }
+
+        let brightness = session.brightness * flash_check.as_ref().map_or(1.0, FlashCheck::gain);
+        pipeline.set_brightness(brightness);
+        pipeline.set_vision(session.vision);
 
-    let mut closed = false;
-    while !closed {
-        let uniforms = uniform! {tex: &texture};
         let mut target = display.draw();
-        target.clear_color(1.0, 1.0, 1.0, 1.0);
-        target.draw(&vertex_buffer, &indices, &program, &uniforms, &Default::default()).
-            map_err(|_| "Could not draw shader.")?;
-        target.finish().unwrap();
+        let (width, height) = target.get_dimensions();
+        if window_size != Some((width, height)) {
+            window_size = Some((width, height));
+            log_event(session, "resize", &[
+                ("width", Field::Num(f64::from(width))),
+                ("height", Field::Num(f64::from(height)))
+            ]);
+        }
+        scrub_panel.draw(&mut overlay, pipeline.scrubs(), width as f32);
+        // The editor covers the same corner.
+        let show_scope = session.vectorscope && !session.editor.is_open();
+        if show_scope {
+            scope.draw_backdrop(&mut overlay, height as f32);
+        }
+        session.editor.draw(&mut overlay, width as f32, height as f32);
+        palette.draw(&mut overlay, width as f32);
+        presented.resize(&display, (width, height))?;
+        let drawn = match variation {
+            Some(ref variation) => {
+                variation.draw(&display, &texture, &inputs, &mut pipeline, &mut presented.texture().as_surface())
+            },
+            None => pipeline.present(&mut presented.texture().as_surface())
+        }.
+            and_then(|_| presented.draw(&mut target)).
+            and_then(|_| if session.motion { motion.draw(&mut target) } else { Ok(()) }).
+            and_then(|_| overlay.draw(&display, &mut target)).
+            and_then(|_| if show_scope { scope.draw(&mut target, pipeline.output()) } else { Ok(()) }).
+            and_then(|_| cursor.draw(&mut target, mouse.cursor(), inputs.time, mouse.is_down()));
+        target.finish().map_err(|_| ShadeyError::Gl("Could not present frame."))?;
+        drawn?;
+        if let Some(ref mut stats) = present_stats {
+            stats.update();
+        }
+        if let Some(ref metrics) = session.metrics {
+            metrics.frame(&inputs, &pipeline);
+        }
+        if let Some(ref control) = session.control {
+            control.frame(&inputs, &pipeline);
+        }
 
-        events_loop.poll_events(|event| {
-            match event {
-                glutin::Event::WindowEvent { event, .. } => match event {
+        if let Some(ref mut recorder) = session.recorder {
+            recorder.capture(presented.texture())?;
+        }
+        if let Some(ref mut feed) = clean_feed {
+            feed.present(presented.texture())?;
+        }
+
+        if let Some(ref mut kiosk) = session.kiosk {
+            kiosk.tick();
+        }
+        if session_saved.elapsed() >= SESSION_SAVE_EVERY {
+            session_saved = Instant::now();
+            save_session(args, session);
+        }
+        cursor.update(&display);
+
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+
+        let mut actions = Vec::new();
+        if session.control.as_ref().is_some_and(ControlServer::quit_requested) {
+            actions.push(Action::Quit);
+        }
+        let mut tab_step = None;
+        for event in events {
+            let event = match event {
+                glutin::Event::WindowEvent { window_id, event } => {
+                    if window_id != main_window {
+                        if let glutin::WindowEvent::Closed = event {
+                            clean_feed = None;
+                        }
+                        continue;
+                    }
+                    event
+                },
+                _ => continue
+            };
+
+            let input = match event {
+                glutin::WindowEvent::MouseMoved { position, .. } => session.idle.pointer_moved(position),
+                glutin::WindowEvent::KeyboardInput { .. } |
+                    glutin::WindowEvent::MouseInput { .. } |
+                    glutin::WindowEvent::MouseWheel { .. } => true,
+                _ => false
+            };
+            if input {
+                if let Some(engaged) = session.idle.input() {
+                    let restored = engaged.shader.as_ref().filter(|_| engaged.reload);
+                    let shader = restored.map(|shader| shader.display().to_string());
+                    let mut fields = vec![("brightness", Field::Num(f64::from(engaged.brightness)))];
+                    if let Some(ref shader) = shader {
+                        fields.push(("shader", Field::Str(shader)));
+                    }
+                    log_event(session, "active", &fields);
+                    session.brightness = engaged.brightness;
+                    if engaged.reload {
+                        session.shader = engaged.shader;
+                        actions.push(Action::Reload);
+                    }
+                }
+            }
+
+            if let Some(ref kiosk) = session.kiosk {
+                match event {
                     glutin::WindowEvent::Closed => {
-                        closed = true;
+                        eprintln!("Ignoring a request to close the window in kiosk mode.");
+                        continue;
+                    },
+                    glutin::WindowEvent::KeyboardInput { input, .. } => {
+                        if input.state == glutin::ElementState::Pressed && kiosk.quit.matches(&input) {
+                            actions.push(Action::Quit);
+                        }
+                        continue;
                     },
                     _ => ()
+                }
+            }
+
+            match event {
+                glutin::WindowEvent::Moved(x, y) => if let Some(ref mut window) = session.window {
+                    window[0] = x;
+                    window[1] = y;
+                },
+                glutin::WindowEvent::Resized(width, height) => if let Some(ref mut window) = session.window {
+                    window[2] = width as i32;
+                    window[3] = height as i32;
+                },
+                glutin::WindowEvent::Closed => actions.push(Action::Quit),
+                glutin::WindowEvent::ReceivedCharacter(c) if session.editor.is_open() => session.editor.character(c),
+                glutin::WindowEvent::ReceivedCharacter(c) if palette.is_open() => palette.character(c),
+                glutin::WindowEvent::KeyboardInput { input, .. } => {
+                    if let Some(glutin::VirtualKeyCode::LShift) | Some(glutin::VirtualKeyCode::RShift) =
+                        input.virtual_keycode
+                    {
+                        shift_held = input.state == glutin::ElementState::Pressed;
+                    }
+                    let key = match (input.state, input.virtual_keycode) {
+                        (glutin::ElementState::Pressed, Some(key)) => key,
+                        _ => continue
+                    };
+
+                    if key == glutin::VirtualKeyCode::F2 {
+                        if shader == Path::new(stdin::PATH) {
+                            eprintln!("Warning: The editor needs a shader file rather than stdin.");
+                        } else {
+                            session.editor.toggle(&shader);
+                            announce(session, if session.editor.is_open() { "Editor open" } else { "Editor closed" });
+                        }
+                    } else if session.editor.is_open() {
+                        session.editor.key(key, input.modifiers);
+                    } else if key == glutin::VirtualKeyCode::Tab && input.modifiers.ctrl && session.tabs.is_some() {
+                        tab_step = Some(if input.modifiers.shift { -1 } else { 1 });
+                    } else if key == glutin::VirtualKeyCode::P && input.modifiers.ctrl {
+                        if palette.is_open() {
+                            palette.close();
+                        } else {
+                            palette.open(palette_commands(&pipeline));
+                        }
+                    } else if palette.is_open() {
+                        actions.extend(palette.key(key));
+                    } else if key == glutin::VirtualKeyCode::S && input.modifiers.ctrl && args.flag_scrub {
+                        actions.push(Action::SaveScrubs);
+                    } else if session.camera.as_mut().is_some_and(|camera| camera.key(key, input.modifiers.ctrl)) {
+                        if let Some(position) = session.camera.as_ref().map(Camera::position) {
+                            announce(session, &format!(
+                                "Camera at {:.2}, {:.2}, {:.2}",
+                                position[0],
+                                position[1],
+                                position[2]
+                            ));
+                        }
+                    } else if key == glutin::VirtualKeyCode::Up || key == glutin::VirtualKeyCode::Down {
+                        scrub_panel.select(if key == glutin::VirtualKeyCode::Up { -1 } else { 1 }, pipeline.scrubs());
+                    } else if key == glutin::VirtualKeyCode::Left || key == glutin::VirtualKeyCode::Right {
+                        let direction = if key == glutin::VirtualKeyCode::Left { -1.0 } else { 1.0 };
+                        if let Some((index, value)) = scrub_panel.nudge(direction, shift_held, pipeline.scrubs()) {
+                            pipeline.set_scrub(index, value);
+                        }
+                    } else if let Some(slot) = snapshot::slot(key) {
+                        actions.push(if input.modifiers.ctrl {
+                            Action::SaveSnapshot(slot)
+                        } else {
+                            Action::RecallSnapshot(slot)
+                        });
+                    } else if key == glutin::VirtualKeyCode::F3 {
+                        actions.push(Action::SetVision(session.vision.next()));
+                    } else if key == glutin::VirtualKeyCode::F4 {
+                        actions.push(Action::ToggleVectorscope);
+                    } else if key == glutin::VirtualKeyCode::F5 {
+                        actions.push(Action::ToggleMotion);
+                    } else if key == glutin::VirtualKeyCode::LBracket {
+                        actions.push(Action::SetBrightness(session.brightness - BRIGHTNESS_STEP));
+                    } else if key == glutin::VirtualKeyCode::RBracket {
+                        actions.push(Action::SetBrightness(session.brightness + BRIGHTNESS_STEP));
+                    }
+                },
+                glutin::WindowEvent::MouseMoved { position: (x, y), .. } => {
+                    let (dx, dy) = mouse.moved(x as f32, height as f32 - y as f32);
+                    cursor.moved();
+                    if let Some((index, value)) = scrub_panel.drag(dx, shift_held, pipeline.scrubs()) {
+                        pipeline.set_scrub(index, value);
+                    } else if let Some(ref mut camera) = session.camera {
+                        if mouse.is_down() {
+                            camera.drag(dx, -dy);
+                        }
+                    }
+                },
+                glutin::WindowEvent::MouseInput { state, button: glutin::MouseButton::Left, .. } => {
+                    // Dragging a scrubbed value doesn't reach the shader.
+                    let (x, y) = mouse.cursor();
+                    match state {
+                        glutin::ElementState::Pressed => {
+                            if !scrub_panel.press((x, height as f32 - y), pipeline.scrubs()) {
+                                mouse.pressed();
+                            }
+                        },
+                        glutin::ElementState::Released => {
+                            if !scrub_panel.release() {
+                                mouse.released();
+                            }
+                        }
+                    }
+                },
+                glutin::WindowEvent::MouseWheel { delta, .. } => {
+                    if let Some(ref mut camera) = session.camera {
+                        match delta {
+                            glutin::MouseScrollDelta::LineDelta(_, lines) => camera.scroll(lines),
+                            glutin::MouseScrollDelta::PixelDelta(_, pixels) => camera.scroll_pixels(pixels)
+                        }
+                    }
                 },
-                _ => (),
+                _ => ()
             }
-        });
+        }
+        if let Some(ref mut announcer) = session.announcer {
+            announcer.show("Palette", palette.describe());
+            if !pipeline.scrubs().is_empty() {
+                announcer.show("Scrub", scrub_panel.describe(pipeline.scrubs()));
+            }
+        }
 
-        // Check for file changes
-        let mut event_buffer = [0; 1024];
-        let events = file_updates.read_events(&mut event_buffer).
-            map_err(|_| "Could not read inotify events.")?;
+        let idle = session.idle.check(&session.shader, session.brightness).
+            map(|(shader, brightness)| (shader.map(|shader| shader.display().to_string()), brightness));
+        if let Some((shader, brightness)) = idle {
+            let mut fields = Vec::new();
+            if let Some(ref shader) = shader {
+                fields.push(("shader", Field::Str(shader)));
+            }
+            if let Some(brightness) = brightness {
+                fields.push(("brightness", Field::Num(f64::from(brightness))));
+            }
+            log_event(session, "idle", &fields);
+            if let Some(brightness) = brightness {
+                actions.push(Action::SetBrightness(brightness));
+            }
+            if let Some(shader) = shader {
+                actions.push(Action::LoadShader(shader));
+            }
+        }
 
-        for event in events {
-            if event.mask.contains(event_mask::MODIFY) {
-                return Ok(ProgramStatus::Reload);
+        // Entries apply once when their time comes, or straight away on start.
+        if let Some(index) = schedule::active(&project.schedule, inputs.date[3]) {
+            if session.scheduled != Some(index) {
+                session.scheduled = Some(index);
+                log_event(session, "schedule", &[("index", Field::Num(index as f64))]);
+                let entry = &project.schedule[index];
+                if let Some(brightness) = entry.brightness {
+                    actions.push(Action::SetBrightness(brightness));
+                }
+                match entry.shader {
+                    Some(ref shader) => {
+                        session.scheduled_uniforms = entry.uniforms.clone();
+                        actions.push(Action::LoadShader(shader.display().to_string()));
+                    },
+                    None => actions.extend(entry.uniforms.iter().
+                        map(|(name, &value)| Action::SetUniform(name.clone(), value)))
+                }
             }
         }
-    }
 
-    Ok(ProgramStatus::Done)
+        if let Some(step) = tab_step {
+            // The next tab starts from its own saved settings, not these.
+            save_settings(session);
+            session.settings_for = None;
+            session.tween = None;
+            let state = TabState {
+                uniforms: mem::take(&mut session.uniforms),
+                shader: session.shader.take(),
+                time: mem::replace(&mut session.time, 0.0)
+            };
+            if let Some(ref mut tabs) = session.tabs {
+                tabs.switch(step, state);
+            }
+            let number = session.tabs.as_ref().map_or(0, Tabs::number);
+            log_event(session, "tab", &[("index", Field::Num(number as f64))]);
+            announce(session, &format!("Tab {}", number));
+            return Ok(ProgramStatus::Reload);
+        }
+
+        let saved_snapshot = actions.iter().any(|action| matches!(*action, Action::SaveSnapshot(_)));
+        for action in actions {
+            let reload = matches!(action, Action::Reload);
+            if let Some(status) = run_action(action, args, &mut pipeline, session) {
+                if reload {
+                    carry_state(session, &clock, &pipeline);
+                }
+                return Ok(status);
+            }
+        }
+
+        // Writing snapshots to the project file shouldn't restart everything.
+        let change = match args.flag_project {
+            Some(ref path) if saved_snapshot => watcher.poll_except(Path::new(path))?,
+            _ => watcher.poll()?
+        };
+        match change {
+            Some(Change::Project) => {
+                carry_state(session, &clock, &pipeline);
+                return Ok(ProgramStatus::Reload);
+            },
+            Some(Change::Image) => {
+                // The image may be caught mid-write; keep the old one until a
+                // complete file shows up.
+                match input_image(&display, &project.image, args) {
+                    Ok(new_texture) => {
+                        texture = new_texture;
+                        pipeline.invalidate();
+                        log_event(session, "image_reload", &[]);
+                    },
+                    Err(e) => eprintln!("Warning: {}", e)
+                }
+            },
+            None => ()
+        }
+    }
 }
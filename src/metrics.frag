@@ -0,0 +1,50 @@
+#version 140
+
+out vec4 color;
+
+uniform sampler2D a;
+uniform sampler2D b;
+
+// SSIM compares the (2 * RADIUS + 1) square window around each pixel.
+const int RADIUS = 3;
+// Keep SSIM stable where both windows are nearly black or flat.
+const float C1 = 0.0001;
+const float C2 = 0.0009;
+
+float luma(vec4 c) {
+    return dot(c.rgb, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Writes the squared error to red and the local SSIM to green. Both are
+// summed over the image afterwards.
+void main() {
+    ivec2 size = textureSize(a, 0);
+    ivec2 p = ivec2(gl_FragCoord.xy);
+    vec3 d = (texelFetch(a, p, 0) - texelFetch(b, p, 0)).rgb;
+
+    float n = 0.0;
+    float mean_a = 0.0, mean_b = 0.0;
+    float aa = 0.0, bb = 0.0, ab = 0.0;
+    for (int y = -RADIUS; y <= RADIUS; y++) {
+        for (int x = -RADIUS; x <= RADIUS; x++) {
+            ivec2 q = clamp(p + ivec2(x, y), ivec2(0), size - 1);
+            float la = luma(texelFetch(a, q, 0));
+            float lb = luma(texelFetch(b, q, 0));
+            mean_a += la;
+            mean_b += lb;
+            aa += la * la;
+            bb += lb * lb;
+            ab += la * lb;
+            n += 1.0;
+        }
+    }
+    mean_a /= n;
+    mean_b /= n;
+    float var_a = aa / n - mean_a * mean_a;
+    float var_b = bb / n - mean_b * mean_b;
+    float cov = ab / n - mean_a * mean_b;
+
+    float ssim = ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2)) /
+        ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+    color = vec4(dot(d, d) / 3.0, ssim, 0.0, 1.0);
+}
@@ -0,0 +1,124 @@
+//! Image measurements on the GPU: the root mean square error and the mean
+//! structural similarity (SSIM) of two images, and the mean luminance of one.
+//!
+//! Every pixel's contribution is computed in one pass, then summed by
+//! repeatedly shrinking the result until a single texel is left to read back.
+
+use glium::{Display, Program, Rect, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::Sampler;
+
+use error::ShadeyError;
+use pipeline::{self, Vertex};
+
+const METRICS_SHADER: &str = include_str!("metrics.frag");
+const REDUCE_SHADER: &str = include_str!("reduce.frag");
+const LUMINANCE_SHADER: &str = include_str!("luminance.frag");
+/// How much each reduction pass shrinks by. Matches `BLOCK` in reduce.frag
+/// and luminance.frag.
+const BLOCK: u32 = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    /// 0 for identical images, 1 for black against white.
+    pub rmse: f32,
+    /// 1 for identical images, lower the more their structure differs.
+    pub ssim: f32
+}
+
+pub struct Measure {
+    vertex_buffer: VertexBuffer<Vertex>,
+    metrics: Program,
+    reduce: Program,
+    luminance: Program
+}
+
+fn float_target(display: &Display, size: (u32, u32)) -> Result<Texture2d, ShadeyError> {
+    Texture2d::empty_with_format(
+        display,
+        UncompressedFloatFormat::F32F32F32F32,
+        MipmapsOption::NoMipmap,
+        size.0,
+        size.1
+    ).map_err(|_| ShadeyError::Gl("Could not create a floating point render target."))
+}
+
+impl Measure {
+    pub fn new(display: &Display) -> Result<Measure, ShadeyError> {
+        let program = |name: &str, source: &str| Program::from_source(display, pipeline::VERTEX_SHADER, source, None).
+            map_err(|e| ShadeyError::compile(name, e));
+
+        Ok(Measure {
+            vertex_buffer: pipeline::quad(display)?,
+            metrics: program("the metrics shader", METRICS_SHADER)?,
+            reduce: program("the reduction shader", REDUCE_SHADER)?,
+            luminance: program("the luminance shader", LUMINANCE_SHADER)?
+        })
+    }
+
+    pub fn compare(&self, display: &Display, a: &Texture2d, b: &Texture2d) -> Result<Metrics, ShadeyError> {
+        let size = a.dimensions();
+        if b.dimensions() != size {
+            let (bw, bh) = b.dimensions();
+            return Err(ShadeyError::invalid(format!(
+                "Can't compare images of different sizes: {}x{} and {}x{}.",
+                size.0, size.1, bw, bh
+            )));
+        }
+
+        let sums = float_target(display, size)?;
+        sums.as_surface().draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.metrics,
+            &uniform! {a: Sampler::new(a), b: Sampler::new(b)},
+            &Default::default()
+        ).map_err(|_| ShadeyError::Gl("Could not draw image metrics."))?;
+
+        let (squared_error, ssim, _, _) = self.sum(display, sums)?;
+        let pixels = (size.0 * size.1) as f32;
+
+        Ok(Metrics { rmse: (squared_error / pixels).sqrt(), ssim: ssim / pixels })
+    }
+
+    /// Mean relative luminance of `image`, from 0 for black to 1 for white.
+    pub fn mean_luminance(&self, display: &Display, image: &Texture2d) -> Result<f32, ShadeyError> {
+        let size = image.dimensions();
+        let sums = float_target(display, (size.0.div_ceil(BLOCK), size.1.div_ceil(BLOCK)))?;
+        sums.as_surface().draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.luminance,
+            &uniform! {image: Sampler::new(image)},
+            &Default::default()
+        ).map_err(|_| ShadeyError::Gl("Could not draw image luminance."))?;
+
+        let (luminance, _, _, _) = self.sum(display, sums)?;
+
+        Ok(luminance / (size.0 * size.1) as f32)
+    }
+
+    /// Add up every texel of `sums`.
+    fn sum(&self, display: &Display, mut sums: Texture2d) -> Result<(f32, f32, f32, f32), ShadeyError> {
+        let mut current = sums.dimensions();
+        while current != (1, 1) {
+            current = (current.0.div_ceil(BLOCK), current.1.div_ceil(BLOCK));
+            let smaller = float_target(display, current)?;
+            smaller.as_surface().draw(
+                &self.vertex_buffer,
+                NoIndices(PrimitiveType::TrianglesList),
+                &self.reduce,
+                &uniform! {source: Sampler::new(&sums)},
+                &Default::default()
+            ).map_err(|_| ShadeyError::Gl("Could not sum image metrics."))?;
+            sums = smaller;
+        }
+
+        let texel: Vec<Vec<(f32, f32, f32, f32)>> = sums.main_level().first_layer().into_image(None).
+            ok_or(ShadeyError::Gl("Could not read image metrics."))?.
+            raw_read(&Rect { left: 0, bottom: 0, width: 1, height: 1 });
+
+        Ok(texel[0][0])
+    }
+}
@@ -0,0 +1,162 @@
+//! Choosing the monitor a fullscreen window lands on.
+//!
+//! winit only knows about X screens, and a multi-monitor desktop is usually
+//! a single screen spanning every output, so outputs are listed with RandR
+//! instead. Fullscreen windows are opened borderless over the output's area
+//! and then asked to go fullscreen, which window managers do on the output
+//! the window is on.
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_long, c_ulong};
+use std::ptr;
+use std::slice;
+
+use glium::Display;
+use glium::glutin::os::unix::WindowExt;
+use x11_dl::xlib::{self, Xlib};
+use x11_dl::xrandr::{self, Xrandr};
+
+use error::ShadeyError;
+use Args;
+
+pub struct Output {
+    pub name: String,
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    pub primary: bool
+}
+
+/// Every connected output that's showing something, left to right.
+pub fn outputs() -> Result<Vec<Output>, ShadeyError> {
+    let unavailable = || ShadeyError::invalid("Listing monitors needs an X11 display with RandR.");
+    let xlib = Xlib::open().map_err(|_| unavailable())?;
+    let xrandr = Xrandr::open().map_err(|_| unavailable())?;
+
+    let mut outputs = Vec::new();
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return Err(unavailable());
+        }
+        let root = (xlib.XDefaultRootWindow)(display);
+        let resources = (xrandr.XRRGetScreenResourcesCurrent)(display, root);
+        let primary = (xrandr.XRRGetOutputPrimary)(display, root);
+
+        if !resources.is_null() {
+            let ids = slice::from_raw_parts((*resources).outputs, (*resources).noutput as usize);
+            for &id in ids {
+                let info = (xrandr.XRRGetOutputInfo)(display, resources, id);
+                if info.is_null() {
+                    continue;
+                }
+                if c_int::from((*info).connection) == xrandr::RR_Connected && (*info).crtc != 0 {
+                    let crtc = (xrandr.XRRGetCrtcInfo)(display, resources, (*info).crtc);
+                    if !crtc.is_null() {
+                        outputs.push(Output {
+                            name: CStr::from_ptr((*info).name).to_string_lossy().into_owned(),
+                            position: ((*crtc).x, (*crtc).y),
+                            size: ((*crtc).width, (*crtc).height),
+                            primary: id == primary
+                        });
+                        (xrandr.XRRFreeCrtcInfo)(crtc);
+                    }
+                }
+                (xrandr.XRRFreeOutputInfo)(info);
+            }
+            (xrandr.XRRFreeScreenResources)(resources);
+        }
+        (xlib.XCloseDisplay)(display);
+    }
+    outputs.sort_by_key(|output| output.position);
+
+    Ok(outputs)
+}
+
+/// `shadey monitors`: print the outputs with the numbers `--fullscreen`
+/// takes.
+pub fn list() -> Result<(), ShadeyError> {
+    for (i, output) in outputs()?.iter().enumerate() {
+        println!(
+            "{}: {} {}x{} at {},{}{}",
+            i + 1,
+            output.name,
+            output.size.0,
+            output.size.1,
+            output.position.0,
+            output.position.1,
+            if output.primary { " (primary)" } else { "" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Name of the output picked with `--fullscreen` or `--monitor`, if any.
+pub fn from_args(args: &Args) -> Result<Option<String>, ShadeyError> {
+    if args.flag_windowed && (args.flag_fullscreen.is_some() || args.flag_monitor.is_some()) {
+        return Err(ShadeyError::invalid("--windowed can't be combined with --fullscreen or --monitor."));
+    }
+    match (args.flag_fullscreen.as_ref(), args.flag_monitor.as_ref()) {
+        (Some(_), Some(_)) => Err(ShadeyError::invalid("Give either --fullscreen or --monitor, not both.")),
+        (Some(number), None) => {
+            let index = number.parse::<usize>().ok().and_then(|n| n.checked_sub(1));
+            let outputs = outputs()?;
+            index.and_then(|i| outputs.into_iter().nth(i)).
+                map(|output| Some(output.name)).
+                ok_or_else(|| ShadeyError::invalid(format!(
+                    "There's no monitor {}. `shadey monitors` lists them.",
+                    number
+                )))
+        },
+        (None, Some(name)) => {
+            if outputs()?.iter().any(|output| output.name == *name) {
+                Ok(Some(name.clone()))
+            } else {
+                Err(ShadeyError::invalid(format!(
+                    "There's no connected monitor named {}. `shadey monitors` lists them.",
+                    name
+                )))
+            }
+        },
+        (None, None) => Ok(None)
+    }
+}
+
+/// The connected output called `name`.
+pub fn find(name: &str) -> Option<Output> {
+    outputs().ok()?.into_iter().find(|output| output.name == name)
+}
+
+/// Ask the window manager to make the window fullscreen, once it's shown.
+pub fn request_fullscreen(display: &Display) -> Result<(), ShadeyError> {
+    let xlib = Xlib::open().map_err(|_| ShadeyError::Context("could not load Xlib".to_string()))?;
+    let window = display.gl_window();
+    let (x_display, x_window) = match (window.get_xlib_display(), window.get_xlib_window()) {
+        (Some(d), Some(w)) => (d as *mut xlib::Display, w as c_ulong),
+        _ => return Err(ShadeyError::Context("fullscreen on a monitor needs an X11 window".to_string()))
+    };
+
+    unsafe {
+        let atom = |name: &[u8]| (xlib.XInternAtom)(x_display, name.as_ptr() as *const c_char, 0);
+        let mut data = xlib::ClientMessageData::new();
+        // Add the fullscreen state.
+        data.set_long(0, 1);
+        data.set_long(1, atom(b"_NET_WM_STATE_FULLSCREEN\0") as c_long);
+        let mut event = xlib::XEvent::from(xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: 1,
+            display: x_display,
+            window: x_window,
+            message_type: atom(b"_NET_WM_STATE\0"),
+            format: 32,
+            data
+        });
+        let root = (xlib.XDefaultRootWindow)(x_display);
+        let mask = xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask;
+        (xlib.XSendEvent)(x_display, root, 0, mask, &mut event);
+        (xlib.XFlush)(x_display);
+    }
+
+    Ok(())
+}
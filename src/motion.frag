@@ -0,0 +1,58 @@
+#version 140
+
+out vec4 color;
+
+uniform sampler2D current;
+uniform sampler2D previous;
+
+// Matches BLOCK in motion.rs.
+const int BLOCK = 16;
+// Furthest a block is searched for, in pixels, and the step between offsets.
+const int RANGE = 8;
+const int STEP = 2;
+// Pixels between the samples compared within a block.
+const int SAMPLE = 4;
+
+float luma(vec3 c) {
+    return dot(clamp(c, 0.0, 1.0), vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Mean absolute difference between the block at `origin` now and the same
+// block `offset` pixels away in the previous frame.
+float cost(ivec2 origin, ivec2 offset, ivec2 size) {
+    float sum = 0.0;
+    float count = 0.0;
+    for (int y = SAMPLE / 2; y < BLOCK; y += SAMPLE) {
+        for (int x = SAMPLE / 2; x < BLOCK; x += SAMPLE) {
+            ivec2 q = origin + ivec2(x, y);
+            ivec2 p = clamp(q + offset, ivec2(0), size - 1);
+            if (all(lessThan(q, size))) {
+                sum += abs(luma(texelFetch(current, q, 0).rgb) - luma(texelFetch(previous, p, 0).rgb));
+                count += 1.0;
+            }
+        }
+    }
+    return sum / max(count, 1.0);
+}
+
+// Writes the block's motion since the previous frame in pixels to red and
+// green, and how badly even the best match differs to blue.
+void main() {
+    ivec2 size = textureSize(current, 0);
+    ivec2 origin = ivec2(gl_FragCoord.xy) * BLOCK;
+
+    // Staying put wins ties, so flat areas don't wander.
+    vec2 best = vec2(0.0);
+    float best_cost = cost(origin, ivec2(0), size);
+    for (int y = -RANGE; y <= RANGE; y += STEP) {
+        for (int x = -RANGE; x <= RANGE; x += STEP) {
+            float c = cost(origin, ivec2(x, y), size);
+            if (c < best_cost - 0.001) {
+                best_cost = c;
+                best = vec2(x, y);
+            }
+        }
+    }
+    // Matched `best` away in the previous frame, so it moved the other way.
+    color = vec4(-best, best_cost, 1.0);
+}
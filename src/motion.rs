@@ -0,0 +1,112 @@
+//! F5 shows how the output moves from frame to frame, for checking animated
+//! shaders are temporally stable before exporting video. The output is split
+//! into 16 pixel blocks and each is matched against the previous frame up to
+//! 8 pixels away, on the GPU. Moving blocks are tinted by the direction they
+//! moved in, more strongly the further they went. Blocks that match nothing
+//! nearby, which usually means flicker, show up white.
+
+use glium::{Blend, Display, DrawParameters, Program, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler};
+
+use error::ShadeyError;
+use pipeline::{self, Vertex};
+
+const ESTIMATE_SHADER: &str = include_str!("motion.frag");
+const VIEW_SHADER: &str = include_str!("motion_view.frag");
+/// Side of the blocks matched. Matches `BLOCK` in motion.frag.
+const BLOCK: u32 = 16;
+
+pub struct MotionView {
+    vertex_buffer: VertexBuffer<Vertex>,
+    estimate: Program,
+    view: Program,
+    /// Copy of the last frame, to match the next one against.
+    previous: Option<Texture2d>,
+    /// Motion of each block, as written by motion.frag.
+    vectors: Option<Texture2d>
+}
+
+fn texture(display: &Display, size: (u32, u32)) -> Result<Texture2d, ShadeyError> {
+    Texture2d::empty_with_format(display, UncompressedFloatFormat::F32F32F32F32, MipmapsOption::NoMipmap, size.0, size.1).
+        map_err(|_| ShadeyError::Gl("Could not create a motion estimation target."))
+}
+
+impl MotionView {
+    pub fn new(display: &Display) -> Result<MotionView, ShadeyError> {
+        let program = |name: &str, source: &str| Program::from_source(display, pipeline::VERTEX_SHADER, source, None).
+            map_err(|e| ShadeyError::compile(name, e));
+
+        Ok(MotionView {
+            vertex_buffer: pipeline::quad(display)?,
+            estimate: program("the motion estimation shader", ESTIMATE_SHADER)?,
+            view: program("the motion view shader", VIEW_SHADER)?,
+            previous: None,
+            vectors: None
+        })
+    }
+
+    /// Forget the last frame, e.g. while the view is hidden, so the next one
+    /// isn't compared with something long gone.
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.vectors = None;
+    }
+
+    /// Estimate the motion from the last frame to `frame`.
+    pub fn update(&mut self, display: &Display, frame: &Texture2d) -> Result<(), ShadeyError> {
+        let size = frame.dimensions();
+        let previous = match self.previous.take() {
+            Some(previous) if previous.dimensions() == size => previous,
+            _ => {
+                // Nothing to compare the first frame at this size with.
+                let previous = texture(display, size)?;
+                frame.as_surface().fill(&previous.as_surface(), MagnifySamplerFilter::Nearest);
+                self.previous = Some(previous);
+                self.vectors = None;
+                return Ok(());
+            }
+        };
+
+        let blocks = (size.0.div_ceil(BLOCK), size.1.div_ceil(BLOCK));
+        let vectors = match self.vectors.take() {
+            Some(vectors) if vectors.dimensions() == blocks => vectors,
+            _ => texture(display, blocks)?
+        };
+        vectors.as_surface().draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.estimate,
+            &uniform! {current: Sampler::new(frame), previous: Sampler::new(&previous)},
+            &Default::default()
+        ).map_err(|_| ShadeyError::Gl("Could not estimate motion."))?;
+
+        frame.as_surface().fill(&previous.as_surface(), MagnifySamplerFilter::Nearest);
+        self.previous = Some(previous);
+        self.vectors = Some(vectors);
+        Ok(())
+    }
+
+    /// Tint `target` with the latest motion estimate.
+    pub fn draw<S: Surface>(&self, target: &mut S) -> Result<(), ShadeyError> {
+        let vectors = match self.vectors {
+            Some(ref vectors) => vectors,
+            None => return Ok(())
+        };
+        let sampler = vectors.sampled().
+            minify_filter(MinifySamplerFilter::Nearest).
+            magnify_filter(MagnifySamplerFilter::Nearest);
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target.draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.view,
+            &uniform! {vectors: sampler},
+            &params
+        ).map_err(|_| ShadeyError::Gl("Could not draw the motion view."))
+    }
+}
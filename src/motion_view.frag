@@ -0,0 +1,26 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D vectors;
+// Matches RANGE in motion.frag.
+const float RANGE = 8.0;
+// Mean difference above which a block counts as unmatched.
+const float UNMATCHED = 0.08;
+
+vec3 hue(float h) {
+    return clamp(abs(mod(h * 6.0 + vec3(0.0, 4.0, 2.0), 6.0) - 3.0) - 1.0, 0.0, 1.0);
+}
+
+void main() {
+    vec3 v = texture(vectors, pos).rgb;
+    if (v.b > UNMATCHED) {
+        // Changed too much to be moving: flicker, or something appearing.
+        color = vec4(1.0, 1.0, 1.0, 0.6);
+        return;
+    }
+    float strength = length(v.rg) / (RANGE * sqrt(2.0));
+    float direction = atan(v.g, v.r) / 6.2831853 + 0.5;
+    color = vec4(hue(direction), clamp(strength * 2.0, 0.0, 0.75));
+}
@@ -0,0 +1,53 @@
+//! Numbers typed in by people, who write one and a half as `1.5` or `1,5`
+//! depending on where they learned to. By default a comma separates the
+//! items of lists such as `vec3:tint=1,0.5,0.2`. With `--decimal-comma`,
+//! `1,5` is one and a half and lists are separated with `;` instead. A
+//! point is still read as a decimal mark, so `1.5` means the same to
+//! everyone. `;` separates lists either way.
+//!
+//! Shader sources always use a point. A project file's `run_if` expressions
+//! do too, unless the file itself says `decimal = "comma"`, so a project reads
+//! the same on every machine. Numbers shadey prints or exports in the
+//! HUD, logs, JSON and TOML always use a point, because Rust's formatting
+//! ignores the locale.
+
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decimal {
+    #[default]
+    Point,
+    Comma
+}
+
+impl Decimal {
+    pub fn from_flag(comma: bool) -> Decimal {
+        if comma { Decimal::Comma } else { Decimal::Point }
+    }
+
+    /// Parse one number, surrounding whitespace and all.
+    pub fn parse<T: FromStr>(self, text: &str) -> Result<T, T::Err> {
+        let text = text.trim();
+        match self {
+            Decimal::Comma if !text.contains('.') => text.replacen(',', ".", 1).parse(),
+            _ => text.parse()
+        }
+    }
+
+    /// Split a list of numbers into its items.
+    pub fn split(self, text: &str) -> Vec<&str> {
+        match self {
+            Decimal::Point => text.split([',', ';']).collect(),
+            Decimal::Comma => text.split(';').collect()
+        }
+    }
+
+    /// Split `text` once at a list separator.
+    pub fn split_once(self, text: &str) -> Option<(&str, &str)> {
+        match self {
+            Decimal::Point => text.split_once([',', ';']),
+            Decimal::Comma => text.split_once(';')
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Rendering without showing anything, shared by the batch subcommands.
+
+use std::ops::Range;
+use std::path::Path;
+
+use glium::{glutin, Display};
+use glium::texture::{RawImage2d, Texture2d};
+
+use clock;
+use error::ShadeyError;
+use pipeline::FrameInputs;
+use {context_builder, input_image, Args};
+
+/// Parse a size given as `<width>x<height>`.
+pub fn parse_size(text: &str) -> Result<(u32, u32), ShadeyError> {
+    let invalid = || ShadeyError::invalid(format!("Invalid size `{}`. Expected e.g. 640x480.", text));
+    let (width, height) = text.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+
+    Ok((width, height))
+}
+
+/// Frames to render: `--frames` of them from `--start-frame`, or up to and
+/// including `--end-frame`.
+pub fn frame_range(args: &Args) -> Result<Range<u32>, ShadeyError> {
+    let parse = |text: &str| text.parse::<u32>().
+        map_err(|_| ShadeyError::invalid(format!("Invalid frame `{}`.", text)));
+    let start = parse(&args.flag_start_frame)?;
+    let end = match args.flag_end_frame {
+        Some(ref end) => {
+            let end = parse(end)?;
+            if end < start {
+                return Err(ShadeyError::invalid(format!("--end-frame {} comes before --start-frame {}.", end, start)));
+            }
+            end.checked_add(1)
+        },
+        None => {
+            let frames: u32 = args.flag_frames.parse().
+                map_err(|_| ShadeyError::invalid(format!("Invalid frame count `{}`.", args.flag_frames)))?;
+            start.checked_add(frames)
+        }
+    };
+    let end = end.ok_or_else(|| ShadeyError::invalid("Too many frames."))?;
+    if start == end {
+        return Err(ShadeyError::invalid("No frames to render: give --frames of at least 1."));
+    }
+
+    Ok(start..end)
+}
+
+/// A context to render with. Its window is never shown; pipelines should be
+/// given a fixed size as the window's own is meaningless.
+pub fn hidden_display(events_loop: &glutin::EventsLoop, args: &Args) -> Result<Display, ShadeyError> {
+    let window = glutin::WindowBuilder::new().
+        with_title("Shadey").
+        with_dimensions(64, 64).
+        with_visibility(false);
+
+    Display::new(window, context_builder(args), events_loop).
+        map_err(|e| ShadeyError::Context(e.to_string()))
+}
+
+/// The image shaders sample as `tex`: `--image` if given, otherwise black.
+pub fn input_texture(display: &Display, args: &Args) -> Result<Texture2d, ShadeyError> {
+    match args.flag_image {
+        Some(ref path) => input_image(display, Path::new(path), args),
+        None => Texture2d::new(display, RawImage2d::from_raw_rgba(vec![0u8, 0, 0, 255], (1, 1))).
+            map_err(|_| ShadeyError::Gl("Could not create blank input texture."))
+    }
+}
+
+/// Inputs for a still frame at `--time`.
+pub fn still_inputs(args: &Args) -> FrameInputs {
+    let (time_high, time_low) = clock::split(f64::from(args.time), args.time_wrap);
+    FrameInputs {
+        time: args.time,
+        time_high,
+        time_low,
+        time_delta: 0.0,
+        frame: 0,
+        frame_rate: 0.0,
+        date: clock::date(),
+        mouse: [0.0; 4],
+        cursor: [0.0; 2],
+        idle_time: 0.0,
+        camera: None
+    }
+}
@@ -0,0 +1,27 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D tex;
+// Master brightness, for dimming the output without touching the shaders.
+uniform float gain;
+// Colour vision deficiency to simulate, see vision.rs.
+uniform bool simulate;
+uniform mat3 vision;
+
+vec3 to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+vec3 to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+void main() {
+    vec4 c = texture(tex, pos);
+    if (simulate) {
+        c.rgb = to_srgb(clamp(vision * to_linear(clamp(c.rgb, 0.0, 1.0)), 0.0, 1.0));
+    }
+    color = vec4(c.rgb * gain, c.a);
+}
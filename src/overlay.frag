@@ -0,0 +1,13 @@
+#version 140
+
+in vec2 uv;
+in vec4 vertex_tint;
+out vec4 color;
+
+uniform sampler2D glyphs;
+
+void main() {
+    // Negative texture coordinates mark solid rectangles.
+    float coverage = uv.x < 0.0 ? 1.0 : texture(glyphs, uv).a;
+    color = vec4(vertex_tint.rgb, vertex_tint.a * coverage);
+}
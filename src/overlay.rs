@@ -0,0 +1,137 @@
+//! Immediate mode 2D overlay for drawing text and panels over the shader
+//! output. Shapes are queued during a frame and drawn with a single call.
+
+use font8x8::legacy::BASIC_LEGACY;
+use glium::{Blend, Display, DrawParameters, Program, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, RawImage2d, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+
+use error::ShadeyError;
+
+/// Size of a glyph in the font, in texels.
+const GLYPH_SIZE: usize = 8;
+const GLYPH_COUNT: usize = 128;
+/// Glyphs are drawn at this multiple of their native size.
+pub const SCALE: f32 = 2.0;
+/// Width and height of a character cell on screen, in pixels.
+pub const CHAR_SIZE: f32 = GLYPH_SIZE as f32 * SCALE;
+
+#[derive(Copy, Clone)]
+struct OverlayVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    tint: [f32; 4]
+}
+implement_vertex!(OverlayVertex, position, tex_coords, tint);
+
+pub struct Overlay {
+    program: Program,
+    glyphs: Texture2d,
+    vertices: Vec<OverlayVertex>
+}
+
+/// One long strip of every glyph in the basic ASCII range, white with the
+/// glyph shape in the alpha channel.
+fn glyph_atlas(display: &Display) -> Result<Texture2d, ShadeyError> {
+    let width = GLYPH_SIZE * GLYPH_COUNT;
+    let mut data = vec![0u8; width * GLYPH_SIZE * 4];
+
+    for (index, glyph) in BASIC_LEGACY.iter().enumerate() {
+        for (row, bits) in glyph.iter().enumerate() {
+            // Texture rows run bottom to top, glyph rows top to bottom.
+            let y = GLYPH_SIZE - 1 - row;
+            for x in 0..GLYPH_SIZE {
+                let offset = (y * width + index * GLYPH_SIZE + x) * 4;
+                let alpha = if bits & (1 << x) != 0 { 255 } else { 0 };
+                data[offset..offset + 4].copy_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+    }
+
+    let image = RawImage2d::from_raw_rgba(data, (width as u32, GLYPH_SIZE as u32));
+    Texture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap).
+        map_err(|_| ShadeyError::Gl("Could not create the overlay font texture."))
+}
+
+impl Overlay {
+    pub fn new(display: &Display) -> Result<Overlay, ShadeyError> {
+        let program = Program::from_source(
+            display,
+            include_str!("overlay.vert"),
+            include_str!("overlay.frag"),
+            None
+        ).map_err(|e| ShadeyError::compile("the overlay shader", e))?;
+
+        Ok(Overlay {
+            program,
+            glyphs: glyph_atlas(display)?,
+            vertices: Vec::new()
+        })
+    }
+
+    fn quad(&mut self, rect: [f32; 4], uv: [f32; 4], tint: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        let [u0, v0, u1, v1] = uv;
+        let corner = |px, py, u, v| OverlayVertex {
+            position: [px, py],
+            tex_coords: [u, v],
+            tint
+        };
+
+        self.vertices.extend_from_slice(&[
+            corner(x, y, u0, v1),
+            corner(x, y + h, u0, v0),
+            corner(x + w, y + h, u1, v0),
+
+            corner(x, y, u0, v1),
+            corner(x + w, y + h, u1, v0),
+            corner(x + w, y, u1, v1)
+        ]);
+    }
+
+    /// Queue a solid rectangle. Coordinates are pixels from the top left.
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, tint: [f32; 4]) {
+        self.quad([x, y, w, h], [-1.0, -1.0, -1.0, -1.0], tint);
+    }
+
+    /// Queue a line of text with its top left corner at `x`, `y`. Characters
+    /// outside of ASCII are drawn as `?`.
+    pub fn text(&mut self, x: f32, y: f32, text: &str, tint: [f32; 4]) {
+        let atlas_width = (GLYPH_SIZE * GLYPH_COUNT) as f32;
+        let glyph_width = GLYPH_SIZE as f32 / atlas_width;
+
+        for (i, c) in text.chars().enumerate() {
+            let index = if (c as usize) < GLYPH_COUNT { c as usize } else { '?' as usize };
+            let u = index as f32 * glyph_width;
+            let left = x + i as f32 * CHAR_SIZE;
+            self.quad([left, y, CHAR_SIZE, CHAR_SIZE], [u, 0.0, u + glyph_width, 1.0], tint);
+        }
+    }
+
+    /// Draw everything queued since the last call.
+    pub fn draw<S: Surface>(&mut self, display: &Display, target: &mut S) -> Result<(), ShadeyError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let vertex_buffer = VertexBuffer::new(display, &self.vertices).
+            map_err(|_| ShadeyError::Gl("Could not create overlay vertex buffer."))?;
+        self.vertices.clear();
+
+        let (width, height) = target.get_dimensions();
+        let uniforms = uniform! {
+            screen: [width as f32, height as f32],
+            glyphs: self.glyphs.sampled().
+                minify_filter(MinifySamplerFilter::Nearest).
+                magnify_filter(MagnifySamplerFilter::Nearest)
+        };
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        target.draw(&vertex_buffer, NoIndices(PrimitiveType::TrianglesList), &self.program, &uniforms, &params).
+            map_err(|_| ShadeyError::Gl("Could not draw overlay."))
+    }
+}
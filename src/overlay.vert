@@ -0,0 +1,18 @@
+#version 140
+
+in vec2 position;
+in vec2 tex_coords;
+in vec4 tint;
+
+out vec2 uv;
+out vec4 vertex_tint;
+
+// Window size in pixels; positions are given in pixels from the top left.
+uniform vec2 screen;
+
+void main() {
+    vec2 ndc = vec2(position.x / screen.x * 2.0 - 1.0, 1.0 - position.y / screen.y * 2.0);
+    gl_Position = vec4(ndc, 0.0, 1.0);
+    uv = tex_coords;
+    vertex_tint = tint;
+}
@@ -0,0 +1,141 @@
+//! `shadey package`: lays out the running binary, the starter shaders, an
+//! icon and a desktop file for an AppImage or a flatpak. The desktop file
+//! starts `shadey-start`, which opens a starter shader and image when
+//! there's nothing else to open. Shaders shadey uses
+//! itself are built into the binary and settings live beside shaders or under
+//! `$XDG_STATE_HOME`, so nothing needs the source tree at run time.
+//!
+//! - `appimage` writes an AppDir, for `appimagetool <path>`;
+//! - `flatpak` writes the app's `/app` prefix to `files/` and a manifest that
+//!   installs it, for `flatpak-builder build <path>/<id>.yml`.
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use error::ShadeyError;
+use scaffold::{self, TEMPLATES};
+
+const APP_ID: &str = "io.github.danthedaniel.Shadey";
+const DESKTOP: &str = include_str!("packaging/shadey.desktop");
+const ICON: &str = include_str!("packaging/shadey.svg");
+const METAINFO: &str = include_str!("packaging/shadey.metainfo.xml");
+const START: &str = include_str!("packaging/shadey-start");
+const EXAMPLES: &[(&str, &str)] = &[("simple.frag", include_str!("../examples/simple.frag"))];
+
+const APP_RUN: &str = "#!/bin/sh\n\
+    here=\"$(dirname \"$(readlink -f \"$0\")\")\"\n\
+    exec \"$here/usr/bin/shadey-start\" \"$@\"\n";
+
+fn write(path: &Path, contents: &str) -> Result<(), ShadeyError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ShadeyError::io("Could not create", dir, e))?;
+    }
+    fs::write(path, contents).map_err(|e| ShadeyError::io("Could not write", path, e))
+}
+
+fn write_executable(path: &Path, contents: &str) -> Result<(), ShadeyError> {
+    write(path, contents)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).
+        map_err(|e| ShadeyError::io("Could not make executable", path, e))
+}
+
+/// Lay out `prefix` the way it would be installed, e.g. `/usr`.
+fn install(prefix: &Path) -> Result<(), ShadeyError> {
+    let shadey = env::current_exe().map_err(|error| ShadeyError::Io {
+        action: "Could not find the shadey executable",
+        path: None,
+        error
+    })?;
+    let bin = prefix.join("bin");
+    fs::create_dir_all(&bin).map_err(|e| ShadeyError::io("Could not create", &bin, e))?;
+    fs::copy(&shadey, bin.join("shadey")).map_err(|e| ShadeyError::io("Could not copy", &shadey, e))?;
+    write_executable(&bin.join("shadey-start"), START)?;
+
+    let share = prefix.join("share");
+    write(&share.join(format!("applications/{}.desktop", APP_ID)), DESKTOP)?;
+    write(&share.join(format!("icons/hicolor/scalable/apps/{}.svg", APP_ID)), ICON)?;
+    write(&share.join(format!("metainfo/{}.metainfo.xml", APP_ID)), METAINFO)?;
+    for &(name, source) in TEMPLATES {
+        write(&share.join(format!("shadey/templates/{}.frag", name)), source)?;
+    }
+    for &(name, source) in EXAMPLES {
+        write(&share.join("shadey/examples").join(name), source)?;
+    }
+    let starter = share.join("shadey/starter.png");
+    scaffold::starter_image().save(&starter).map_err(|e| ShadeyError::io("Could not write", &starter, e))?;
+    Ok(())
+}
+
+fn appimage(dir: &Path) -> Result<(), ShadeyError> {
+    install(&dir.join("usr"))?;
+
+    // appimagetool looks for these at the top of the AppDir.
+    write_executable(&dir.join("AppRun"), APP_RUN)?;
+    write(&dir.join(format!("{}.desktop", APP_ID)), DESKTOP)?;
+    let icon = format!("{}.svg", APP_ID);
+    write(&dir.join(&icon), ICON)?;
+    let dir_icon = dir.join(".DirIcon");
+    symlink(&icon, &dir_icon).map_err(|e| ShadeyError::io("Could not link", &dir_icon, e))?;
+
+    eprintln!("Wrote an AppDir to {}. Build it with: appimagetool {}", dir.display(), dir.display());
+    Ok(())
+}
+
+fn flatpak_manifest() -> String {
+    format!(
+        "app-id: {id}\n\
+         runtime: org.freedesktop.Platform\n\
+         runtime-version: '23.08'\n\
+         sdk: org.freedesktop.Sdk\n\
+         command: shadey-start\n\
+         finish-args:\n  \
+           - --socket=x11\n  \
+           - --share=ipc\n  \
+           - --device=dri\n  \
+           # Settings are saved beside shaders, wherever they are.\n  \
+           - --filesystem=home\n\
+         modules:\n  \
+           - name: shadey\n    \
+             buildsystem: simple\n    \
+             build-commands:\n      \
+               - cp -a . /app\n    \
+             sources:\n      \
+               - type: dir\n        \
+                 path: files\n",
+        id = APP_ID
+    )
+}
+
+fn flatpak(dir: &Path) -> Result<(), ShadeyError> {
+    install(&dir.join("files"))?;
+    let manifest = dir.join(format!("{}.yml", APP_ID));
+    write(&manifest, &flatpak_manifest())?;
+
+    eprintln!(
+        "Wrote a flatpak layout to {}. Build it with: flatpak-builder build {}",
+        dir.display(),
+        manifest.display()
+    );
+    Ok(())
+}
+
+pub fn run(format: &str, output: Option<&str>) -> Result<(), ShadeyError> {
+    let layout: fn(&Path) -> Result<(), ShadeyError> = match format {
+        "appimage" => appimage,
+        "flatpak" => flatpak,
+        _ => return Err(ShadeyError::invalid(format!(
+            "Unknown package format `{}`. Expected appimage or flatpak.",
+            format
+        )))
+    };
+    let dir = PathBuf::from(output.ok_or_else(|| ShadeyError::invalid("Give a directory for the package with -o."))?);
+    // Stale files from an older layout would end up in the package.
+    let empty = fs::read_dir(&dir).map(|mut entries| entries.next().is_none()).unwrap_or(true);
+    if !empty {
+        return Err(ShadeyError::invalid(format!("{} already exists and isn't empty.", dir.display())));
+    }
+
+    layout(&dir)
+}
@@ -0,0 +1,285 @@
+//! Ctrl+P command palette: fuzzy search over everything that can be done
+//! from inside the window.
+
+use std::cmp::Reverse;
+
+use glium::glutin::VirtualKeyCode;
+
+use numbers::Decimal;
+use overlay::{Overlay, CHAR_SIZE};
+use vision::ColorVision;
+
+/// How many matching commands are listed at once.
+const VISIBLE_RESULTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Screenshot,
+    Reload,
+    Quit,
+    TogglePass(String),
+    SetUniform(String, f32),
+    LoadShader(String),
+    SaveSnapshot(u32),
+    RecallSnapshot(u32),
+    Randomize,
+    /// Glide from one snapshot to another over a number of seconds.
+    Interpolate(u32, u32, f32),
+    /// Set the master brightness of the output, from 0 to 1.
+    SetBrightness(f32),
+    /// Write `/*@scrub*/` values back into the shader files.
+    SaveScrubs,
+    /// Simulate a colour vision deficiency in the window.
+    SetVision(ColorVision),
+    ToggleVectorscope,
+    ToggleMotion
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    /// Runs as soon as it's picked.
+    Immediate(Action),
+    /// Asks for a value for the named uniform first.
+    SetUniform(String),
+    /// Asks for a shader path first.
+    LoadShader,
+    /// Asks for two snapshot slots and a duration first.
+    Interpolate
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    label: String,
+    kind: Kind
+}
+
+impl Command {
+    pub fn immediate(label: &str, action: Action) -> Command {
+        Command { label: label.to_string(), kind: Kind::Immediate(action) }
+    }
+
+    pub fn set_uniform(name: &str) -> Command {
+        Command { label: format!("Set uniform: {}", name), kind: Kind::SetUniform(name.to_string()) }
+    }
+
+    pub fn load_shader() -> Command {
+        Command { label: "Load shader...".to_string(), kind: Kind::LoadShader }
+    }
+
+    pub fn interpolate() -> Command {
+        Command { label: "Interpolate snapshots...".to_string(), kind: Kind::Interpolate }
+    }
+}
+
+/// Parse "<from> <to> <seconds>" as typed into the interpolation prompt.
+fn parse_interpolation(input: &str, decimal: Decimal) -> Option<Action> {
+    let mut words = input.split_whitespace();
+    let from = words.next()?.parse().ok()?;
+    let to = words.next()?.parse().ok()?;
+    let seconds = decimal.parse(words.next()?).ok()?;
+    if words.next().is_some() {
+        return None;
+    }
+
+    Some(Action::Interpolate(from, to, seconds))
+}
+
+/// Score `label` against `query` as a case-insensitive subsequence match,
+/// favouring consecutive runs and matches near the start. `None` means the
+/// label doesn't match at all.
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = label[position..].iter().position(|&c| c == q)? + position;
+        score += match previous {
+            Some(p) if p + 1 == found => 5,
+            _ => 1
+        };
+        if found == 0 {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score * 100 - label.len() as i32)
+}
+
+#[derive(Default)]
+pub struct Palette {
+    open: bool,
+    query: String,
+    selected: usize,
+    commands: Vec<Command>,
+    /// The command waiting for a typed argument, and what's been typed.
+    prompt: Option<(Kind, String)>,
+    decimal: Decimal
+}
+
+impl Palette {
+    pub fn new(decimal: Decimal) -> Palette {
+        Palette { decimal, ..Default::default() }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, commands: Vec<Command>) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.commands = commands;
+        self.prompt = None;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    fn matches(&self) -> Vec<&Command> {
+        let mut scored: Vec<(i32, &Command)> = self.commands.iter().
+            filter_map(|command| fuzzy_score(&self.query, &command.label).map(|score| (score, command))).
+            collect();
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        scored.into_iter().map(|(_, command)| command).take(VISIBLE_RESULTS).collect()
+    }
+
+    pub fn character(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+
+        match self.prompt {
+            Some((_, ref mut input)) => input.push(c),
+            None => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+        }
+    }
+
+    /// Handle a key press, returning the action to run if one was chosen.
+    pub fn key(&mut self, key: VirtualKeyCode) -> Option<Action> {
+        match key {
+            VirtualKeyCode::Escape => self.close(),
+            VirtualKeyCode::Back => {
+                match self.prompt {
+                    Some((_, ref mut input)) => { input.pop(); },
+                    None => { self.query.pop(); }
+                }
+            },
+            VirtualKeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            VirtualKeyCode::Down => {
+                let count = self.matches().len();
+                if self.selected + 1 < count {
+                    self.selected += 1;
+                }
+            },
+            VirtualKeyCode::Return => return self.confirm(),
+            _ => ()
+        }
+
+        None
+    }
+
+    fn confirm(&mut self) -> Option<Action> {
+        if let Some((kind, input)) = self.prompt.take() {
+            let action = match kind {
+                Kind::SetUniform(name) => match self.decimal.parse(&input) {
+                    Ok(value) => Some(Action::SetUniform(name, value)),
+                    Err(_) => {
+                        // Let the user fix the number instead of losing it.
+                        self.prompt = Some((Kind::SetUniform(name), input));
+                        return None;
+                    }
+                },
+                Kind::LoadShader => Some(Action::LoadShader(input.trim().to_string())),
+                Kind::Interpolate => match parse_interpolation(&input, self.decimal) {
+                    Some(action) => Some(action),
+                    None => {
+                        self.prompt = Some((Kind::Interpolate, input));
+                        return None;
+                    }
+                },
+                Kind::Immediate(action) => Some(action)
+            };
+            self.close();
+            return action;
+        }
+
+        let kind = self.matches().get(self.selected)?.kind.clone();
+        match kind {
+            Kind::Immediate(action) => {
+                self.close();
+                Some(action)
+            },
+            kind => {
+                self.prompt = Some((kind, String::new()));
+                None
+            }
+        }
+    }
+
+    /// The prompt for a typed argument, as it's shown.
+    fn prompt_text(&self) -> Option<String> {
+        match self.prompt {
+            Some((Kind::SetUniform(ref name), ref input)) => Some(format!("{} = {}", name, input)),
+            Some((Kind::Interpolate, ref input)) => Some(format!("From, to, seconds: {}", input)),
+            Some((_, ref input)) => Some(format!("Shader path: {}", input)),
+            None => None
+        }
+    }
+
+    /// What the palette shows, in words, for `--announce`.
+    pub fn describe(&self) -> String {
+        if !self.open {
+            return "closed".to_string();
+        }
+        if let Some(prompt) = self.prompt_text() {
+            return prompt;
+        }
+
+        let matches = self.matches();
+        match matches.get(self.selected) {
+            Some(command) => format!("{}, {} of {}", command.label, self.selected + 1, matches.len()),
+            None => format!("nothing matches \"{}\"", self.query)
+        }
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, width: f32) {
+        if !self.open {
+            return;
+        }
+
+        let margin = CHAR_SIZE;
+        let line = CHAR_SIZE * 1.5;
+        let panel_width = (width - margin * 2.0).max(CHAR_SIZE);
+        let text = [1.0, 1.0, 1.0, 1.0];
+        let dim = [0.6, 0.6, 0.6, 1.0];
+
+        let (header, rows): (String, Vec<&str>) = match self.prompt_text() {
+            Some(prompt) => (format!("{}_", prompt), vec![]),
+            None => (
+                format!("> {}_", self.query),
+                self.matches().into_iter().map(|command| command.label.as_str()).collect()
+            )
+        };
+
+        let height = line * (rows.len() + 1) as f32 + margin * 0.5;
+        overlay.rect(margin, margin, panel_width, height, [0.1, 0.1, 0.1, 0.9]);
+        overlay.text(margin * 1.25, margin * 1.25, &header, text);
+
+        for (i, label) in rows.iter().enumerate() {
+            let y = margin * 1.25 + line * (i + 1) as f32;
+            if i == self.selected {
+                overlay.rect(margin, y - CHAR_SIZE * 0.25, panel_width, line, [0.25, 0.35, 0.6, 0.9]);
+            }
+            overlay.text(margin * 1.25, y, label, if i == self.selected { text } else { dim });
+        }
+    }
+}
@@ -0,0 +1,671 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use glium::{Display, DrawParameters, Program, Rect, Surface, VertexBuffer};
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::program::ComputeShader;
+use glium::texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{
+    LayoutMismatchError,
+    MinifySamplerFilter,
+    SamplerBehavior,
+    SamplerWrapFunction,
+    UniformBlock,
+    UniformBuffer,
+    UniformValue,
+};
+
+use camera::CameraUniforms;
+use constants::Constant;
+use error::ShadeyError;
+use expr::Expr;
+use generators::Generator;
+use header;
+use project::{OnReload, PassKind, Project};
+use scrub::{self, Scrub};
+use uniforms::UniformSet;
+use vision::ColorVision;
+use read_shader;
+use stdin;
+
+pub const VERTEX_SHADER: &str = include_str!("main.vert");
+const BLIT_SHADER: &str = include_str!("blit.frag");
+const OUTPUT_SHADER: &str = include_str!("output.frag");
+
+/// Uniforms every pass gets from shadey itself.
+pub const BUILT_IN_UNIFORMS: [&str; 14] = [
+    "tex", "iResolution", "iTime", "iTimeHigh", "iTimeLow", "iTimeDelta", "iFrame", "iFrameRate", "iMouse", "iDate",
+    "iIdleTime", "uCameraPos", "uCameraDir", "uViewMatrix"
+];
+
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+implement_vertex!(Vertex, position, tex_coords);
+
+/// Per-frame values exposed to shaders and to `run_if` expressions.
+pub struct FrameInputs {
+    pub time: f32,
+    /// `time` split for precision, see `clock::split`.
+    pub time_high: f32,
+    pub time_low: f32,
+    /// Seconds since the previous frame.
+    pub time_delta: f32,
+    pub frame: i32,
+    pub frame_rate: f32,
+    /// Local date and time, see `clock::date`.
+    pub date: [f32; 4],
+    pub mouse: [f32; 4],
+    /// Where the cursor is, held or not, in the same pixels as `mouse`.
+    pub cursor: [f32; 2],
+    /// Seconds since the last keyboard or mouse input.
+    pub idle_time: f32,
+    pub camera: Option<CameraUniforms>
+}
+
+enum Stage {
+    Fragment { program: Program, output: Texture2d },
+    Compute { shader: ComputeShader, workgroups: [u32; 3] }
+}
+
+/// Storage buffers are arrays of `vec4`, e.g. `buffer particles { vec4 data[]; };`.
+type StorageBuffer = UniformBuffer<[[f32; 4]]>;
+
+struct Pass {
+    name: String,
+    stage: Stage,
+    run_if: Option<Expr>,
+    on_reload: Option<OnReload>,
+    /// Set when the output holds nothing useful, i.e. the pass must run
+    /// regardless of its `run_if` condition.
+    stale: bool,
+    enabled: bool,
+    /// GPU timer of the last draw that hasn't been read back yet.
+    query: Option<TimeElapsedQuery>,
+    /// Seconds the last timed draw took on the GPU.
+    gpu_time: Option<f64>
+}
+
+/// Pass outputs and buffers read back before a reload, to carry on from.
+pub struct SavedState {
+    outputs: Vec<(String, (u32, u32), Vec<u8>)>,
+    buffers: Vec<(String, Vec<[f32; 4]>)>
+}
+
+pub struct Pipeline {
+    passes: Vec<Pass>,
+    uniforms: BTreeMap<String, f32>,
+    ranges: BTreeMap<String, (f32, f32)>,
+    /// Constants from `--uniform`.
+    constants: BTreeMap<String, Constant>,
+    buffers: Vec<(String, StorageBuffer)>,
+    vertex_buffer: VertexBuffer<Vertex>,
+    indices: NoIndices,
+    blitter: Blitter,
+    size: (u32, u32),
+    /// Render at this size instead of following the window, e.g. offline.
+    fixed_size: Option<(u32, u32)>,
+    /// Whether to time fragment passes on the GPU.
+    timed: bool,
+    /// Tagged literals compiled as uniforms, with `--scrub`.
+    scrubs: Vec<Scrub>,
+    generators: Vec<Generator>
+}
+
+/// Two triangles covering `[left, bottom, right, top]` in clip space, with
+/// texture coordinates running from 0 to 1 across them.
+pub fn rect_vertices(rect: [f32; 4]) -> Vec<Vertex> {
+    let [left, bottom, right, top] = rect;
+    vec![
+        Vertex { position: [left, bottom], tex_coords: [0.0, 0.0] },
+        Vertex { position: [left, top], tex_coords: [0.0, 1.0] },
+        Vertex { position: [right, top], tex_coords: [1.0, 1.0] },
+
+        Vertex { position: [left, bottom], tex_coords: [0.0, 0.0] },
+        Vertex { position: [right, top], tex_coords: [1.0, 1.0] },
+        Vertex { position: [right, bottom], tex_coords: [1.0, 0.0] }
+    ]
+}
+
+fn fullscreen() -> Vec<Vertex> {
+    rect_vertices([-1.0, -1.0, 1.0, 1.0])
+}
+
+fn pass_target(display: &Display, size: (u32, u32)) -> Result<Texture2d, ShadeyError> {
+    Texture2d::empty_with_format(
+        display,
+        UncompressedFloatFormat::U8U8U8U8,
+        MipmapsOption::NoMipmap,
+        size.0,
+        size.1
+    ).map_err(|_| ShadeyError::Gl("Could not create pass render target."))
+}
+
+pub fn quad(display: &Display) -> Result<VertexBuffer<Vertex>, ShadeyError> {
+    VertexBuffer::new(display, &fullscreen()).
+        map_err(|_| ShadeyError::Gl("Could not create vertex buffer."))
+}
+
+/// Copies a texture onto the whole of a surface, scaled by a brightness.
+pub struct Blitter {
+    vertex_buffer: VertexBuffer<Vertex>,
+    program: Program,
+    gain: f32,
+    vision: ColorVision
+}
+
+impl Blitter {
+    pub fn new(display: &Display) -> Result<Blitter, ShadeyError> {
+        Ok(Blitter {
+            vertex_buffer: quad(display)?,
+            program: Program::from_source(display, VERTEX_SHADER, OUTPUT_SHADER, None).
+                map_err(|e| ShadeyError::compile("the output shader", e))?,
+            gain: 1.0,
+            vision: ColorVision::Normal
+        })
+    }
+
+    /// Multiply every colour drawn from now on by `gain`.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    /// Show everything drawn from now on as seen with `vision`.
+    pub fn set_vision(&mut self, vision: ColorVision) {
+        self.vision = vision;
+    }
+
+    pub fn draw<S: Surface>(&self, surface: &mut S, texture: &Texture2d) -> Result<(), ShadeyError> {
+        self.draw_in(surface, texture, None)
+    }
+
+    /// Like `draw`, but only onto `viewport` of the surface if given.
+    pub fn draw_in<S: Surface>(&self, surface: &mut S, texture: &Texture2d, viewport: Option<Rect>) ->
+        Result<(), ShadeyError>
+    {
+        let matrix = self.vision.matrix();
+        surface.draw(
+            &self.vertex_buffer,
+            NoIndices(PrimitiveType::TrianglesList),
+            &self.program,
+            &uniform! {
+                tex: texture.sampled().minify_filter(MinifySamplerFilter::Linear),
+                gain: self.gain,
+                simulate: matrix.is_some(),
+                vision: matrix.unwrap_or([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+            },
+            &DrawParameters { viewport, ..Default::default() }
+        ).map_err(|_| ShadeyError::Gl("Could not draw shader."))
+    }
+}
+
+/// What the window shows beneath its overlays: the final output with the
+/// brightness, colour vision and any grid applied. Recordings and the clean
+/// feed copy it, so they hold exactly what was on screen.
+pub struct PresentedFrame {
+    texture: Texture2d,
+    /// Copies the frame to the window as it is.
+    blitter: Blitter
+}
+
+impl PresentedFrame {
+    pub fn new(display: &Display) -> Result<PresentedFrame, ShadeyError> {
+        Ok(PresentedFrame { texture: pass_target(display, (1, 1))?, blitter: Blitter::new(display)? })
+    }
+
+    /// Match the size of the window the frame is drawn for.
+    pub fn resize(&mut self, display: &Display, size: (u32, u32)) -> Result<(), ShadeyError> {
+        if self.texture.dimensions() != size {
+            self.texture = pass_target(display, size)?;
+        }
+        Ok(())
+    }
+
+    pub fn texture(&self) -> &Texture2d {
+        &self.texture
+    }
+
+    pub fn draw<S: Surface>(&self, target: &mut S) -> Result<(), ShadeyError> {
+        self.blitter.draw(target, &self.texture)
+    }
+}
+
+/// Pass outputs have no mipmaps, so they can't use the default sampler.
+fn pass_sampler() -> SamplerBehavior {
+    SamplerBehavior {
+        wrap_function: (
+            SamplerWrapFunction::Clamp,
+            SamplerWrapFunction::Clamp,
+            SamplerWrapFunction::Clamp
+        ),
+        minify_filter: MinifySamplerFilter::Linear,
+        ..Default::default()
+    }
+}
+
+fn storage_buffer(display: &Display, length: usize) -> Result<StorageBuffer, ShadeyError> {
+    let buffer = StorageBuffer::empty_unsized(display, length * 16).
+        map_err(|_| ShadeyError::Gl("Could not create storage buffer."))?;
+    buffer.write(&vec![[0.0; 4]; length][..]);
+    Ok(buffer)
+}
+
+/// Checks that a shader's declaration of a storage block matches the buffer.
+fn storage_layout(block: &::glium::program::UniformBlock) -> Result<(), LayoutMismatchError> {
+    <[[f32; 4]] as UniformBlock>::matches(&block.layout, 0)
+}
+
+impl Pass {
+    fn output(&self) -> Option<&Texture2d> {
+        match self.stage {
+            Stage::Fragment { ref output, .. } => Some(output),
+            Stage::Compute { .. } => None
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new(display: &Display, project: &Project) -> Result<Pipeline, ShadeyError> {
+        let size = display.get_framebuffer_dimensions();
+
+        let mut passes = Vec::new();
+        let mut scrubs: Vec<Scrub> = Vec::new();
+        for spec in &project.passes {
+            // Until stdin delivers a shader, just show the image.
+            let source = if spec.shader == Path::new(stdin::PATH) {
+                BLIT_SHADER.to_string()
+            } else {
+                read_shader(&spec.shader)?
+            };
+            let name = spec.shader.display().to_string();
+            let stage = match spec.kind {
+                PassKind::Fragment => {
+                    let mut compiled = header::inject(&source, &name)?;
+                    if project.scrub && spec.shader != Path::new(stdin::PATH) {
+                        // Passes sharing a file share its values too.
+                        let first = match scrubs.iter().position(|scrub| scrub.path == spec.shader) {
+                            Some(first) => first,
+                            None => {
+                                let first = scrubs.len();
+                                scrubs.extend(scrub::find(&source, &spec.shader, first));
+                                first
+                            }
+                        };
+                        compiled = scrub::rewrite(&compiled, first);
+                    }
+                    Stage::Fragment {
+                        program: Program::from_source(display, VERTEX_SHADER, &compiled, None).
+                            map_err(|e| ShadeyError::compile(&name, e))?,
+                        output: pass_target(display, size)?
+                    }
+                },
+                PassKind::Compute { workgroups } => {
+                    if !ComputeShader::is_supported(display) {
+                        return Err(ShadeyError::Gl("Compute passes need OpenGL 4.3 or ARB_compute_shader."));
+                    }
+                    Stage::Compute {
+                        shader: ComputeShader::from_source(display, &source).
+                            map_err(|e| ShadeyError::compile(&name, e))?,
+                        workgroups
+                    }
+                }
+            };
+
+            passes.push(Pass {
+                name: spec.name.clone(),
+                stage,
+                run_if: spec.run_if.clone(),
+                on_reload: spec.on_reload,
+                stale: true,
+                enabled: true,
+                query: None,
+                gpu_time: None
+            });
+        }
+
+        let mut buffers = Vec::new();
+        for (name, &length) in &project.buffers {
+            buffers.push((name.clone(), storage_buffer(display, length)?));
+        }
+
+        let mut generators = Vec::new();
+        for (name, spec) in &project.generators {
+            generators.push(Generator::new(display, name, spec)?);
+        }
+
+        Ok(Pipeline {
+            passes,
+            uniforms: project.uniforms.clone(),
+            ranges: project.ranges.clone(),
+            constants: BTreeMap::new(),
+            buffers,
+            vertex_buffer: quad(display)?,
+            indices: NoIndices(PrimitiveType::TrianglesList),
+            blitter: Blitter::new(display)?,
+            size,
+            fixed_size: None,
+            timed: false,
+            scrubs,
+            generators
+        })
+    }
+
+    /// Recompile the final pass from `source`. The old program is kept if the
+    /// new one doesn't compile.
+    pub fn set_final_shader(&mut self, display: &Display, source: &str) -> Result<(), ShadeyError> {
+        let name = "the shader from stdin";
+        let compiled = Program::from_source(display, VERTEX_SHADER, &header::inject(source, name)?, None).
+            map_err(|e| ShadeyError::compile(name, e))?;
+
+        let last = self.passes.len() - 1;
+        if let Stage::Fragment { ref mut program, .. } = self.passes[last].stage {
+            *program = compiled;
+        }
+        self.passes[last].stale = true;
+
+        Ok(())
+    }
+
+    /// Bind `constants` in every pass from now on. They can't share a name
+    /// with a project uniform or pass.
+    pub fn set_constants(&mut self, constants: BTreeMap<String, Constant>) -> Result<(), ShadeyError> {
+        for name in constants.keys() {
+            if self.uniforms.contains_key(name) || self.passes.iter().any(|pass| &pass.name == name) {
+                return Err(ShadeyError::invalid(format!(
+                    "`{}` is already defined by the project and can't be set with --uniform.",
+                    name
+                )));
+            }
+        }
+        self.constants = constants;
+        self.invalidate();
+
+        Ok(())
+    }
+
+    /// Recreate pass outputs when the window has changed size.
+    fn resize(&mut self, display: &Display) -> Result<(), ShadeyError> {
+        let size = self.fixed_size.unwrap_or_else(|| display.get_framebuffer_dimensions());
+        if size == self.size {
+            return Ok(());
+        }
+
+        for pass in &mut self.passes {
+            if let Stage::Fragment { ref mut output, .. } = pass.stage {
+                *output = pass_target(display, size)?;
+            }
+            pass.stale = true;
+        }
+        self.size = size;
+
+        Ok(())
+    }
+
+    /// Render at `size` from now on, whatever the window's size.
+    pub fn fix_size(&mut self, size: (u32, u32)) {
+        self.fixed_size = Some(size);
+    }
+
+    /// Read back the outputs of passes that keep their state under `default`,
+    /// and the storage buffers if it keeps state.
+    pub fn save_state(&self, default: OnReload) -> SavedState {
+        let mut outputs = Vec::new();
+        for pass in &self.passes {
+            if pass.on_reload.unwrap_or(default) == OnReload::Reset || pass.stale {
+                continue;
+            }
+            if let Some(output) = pass.output() {
+                let raw: RawImage2d<u8> = output.read();
+                outputs.push((pass.name.clone(), (raw.width, raw.height), raw.data.into_owned()));
+            }
+        }
+
+        let mut buffers = Vec::new();
+        if default == OnReload::KeepState {
+            for (name, buffer) in &self.buffers {
+                if let Ok(contents) = buffer.read() {
+                    buffers.push((name.clone(), contents));
+                }
+            }
+        }
+
+        SavedState { outputs, buffers }
+    }
+
+    /// Put back what `save_state` read, where passes and buffers of the same
+    /// name and size are still there.
+    pub fn restore_state(&mut self, display: &Display, state: SavedState) -> Result<(), ShadeyError> {
+        self.resize(display)?;
+        for (name, size, data) in state.outputs {
+            let pass = match self.passes.iter_mut().find(|pass| pass.name == name) {
+                Some(pass) => pass,
+                None => continue
+            };
+            if let Stage::Fragment { ref output, .. } = pass.stage {
+                if output.dimensions() == size {
+                    let rect = Rect { left: 0, bottom: 0, width: size.0, height: size.1 };
+                    output.write(rect, RawImage2d::from_raw_rgba(data, size));
+                    pass.stale = false;
+                }
+            }
+        }
+        for (name, contents) in state.buffers {
+            let buffer = self.buffers.iter().find(|(other, _)| *other == name);
+            if let Some((_, buffer)) = buffer.filter(|(_, buffer)| buffer.len() == contents.len()) {
+                buffer.write(&contents);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Force every pass to run on the next frame, e.g. after an input changed.
+    pub fn invalidate(&mut self) {
+        for pass in &mut self.passes {
+            pass.stale = true;
+        }
+    }
+
+    /// The final pass's output as of the last call to `render`.
+    pub fn output(&self) -> &Texture2d {
+        self.passes[self.passes.len() - 1].output().
+            expect("Projects always end with a fragment pass.")
+    }
+
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name.as_str()).collect()
+    }
+
+    pub fn uniform_names(&self) -> Vec<&str> {
+        self.uniforms.keys().map(|name| name.as_str()).collect()
+    }
+
+    /// Current values of the project uniforms, including overrides.
+    pub fn uniforms(&self) -> &BTreeMap<String, f32> {
+        &self.uniforms
+    }
+
+    /// The declared `(min, max)` ranges of project uniforms.
+    pub fn uniform_ranges(&self) -> &BTreeMap<String, (f32, f32)> {
+        &self.ranges
+    }
+
+    /// Override a project uniform. Returns false if there's no such uniform.
+    pub fn set_uniform(&mut self, name: &str, value: f32) -> bool {
+        match self.uniforms.get_mut(name) {
+            Some(uniform) => {
+                *uniform = value;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Tagged literals that can be scrubbed, see `scrub`.
+    pub fn scrubs(&self) -> &[Scrub] {
+        &self.scrubs
+    }
+
+    pub fn set_scrub(&mut self, index: usize, value: f32) {
+        if let Some(scrub) = self.scrubs.get_mut(index) {
+            scrub.value = value;
+        }
+    }
+
+    /// Enable or disable a pass. Disabled passes keep their last output.
+    /// Returns the pass's new state, or `None` if there's no such pass.
+    pub fn toggle_pass(&mut self, name: &str) -> Option<bool> {
+        let pass = self.passes.iter_mut().find(|pass| pass.name == name)?;
+        pass.enabled = !pass.enabled;
+        Some(pass.enabled)
+    }
+
+    /// Measure how long each fragment pass takes on the GPU, see `gpu_times`.
+    pub fn time_passes(&mut self) {
+        self.timed = true;
+    }
+
+    /// The latest GPU time of every fragment pass that has been timed, in
+    /// seconds. Timers are read back once they're ready, a frame or so late,
+    /// so timing doesn't stall rendering.
+    pub fn gpu_times(&self) -> Vec<(&str, f64)> {
+        self.passes.iter().
+            filter_map(|pass| pass.gpu_time.map(|time| (pass.name.as_str(), time))).
+            collect()
+    }
+
+    /// Run every enabled pass whose condition holds.
+    pub fn render(&mut self, display: &Display, image: &Texture2d, inputs: &FrameInputs) ->
+        Result<(), ShadeyError>
+    {
+        self.resize(display)?;
+        for pass in &mut self.passes {
+            if pass.query.as_ref().is_some_and(TimeElapsedQuery::is_ready) {
+                let nanoseconds = pass.query.take().map_or(0, TimeElapsedQuery::get);
+                pass.gpu_time = Some(f64::from(nanoseconds) / 1e9);
+            }
+        }
+        for generator in &mut self.generators {
+            generator.update(inputs, self.size, &self.uniforms);
+        }
+
+        let resolution = [self.size.0 as f32, self.size.1 as f32, 1.0];
+        let lookup = |name: &str| match name {
+            "iTime" => Some(f64::from(inputs.time)),
+            "iTimeDelta" => Some(f64::from(inputs.time_delta)),
+            "iFrame" => Some(f64::from(inputs.frame)),
+            "iFrameRate" => Some(f64::from(inputs.frame_rate)),
+            "iIdleTime" => Some(f64::from(inputs.idle_time)),
+            _ => self.uniforms.get(name).map(|&v| f64::from(v))
+        };
+        let should_run: Vec<bool> = self.passes.iter().
+            map(|pass| pass.enabled &&
+                (pass.stale || pass.run_if.as_ref().is_none_or(|expr| expr.is_true(&lookup)))).
+            collect();
+
+        let mut queries = Vec::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            if !should_run[i] {
+                continue;
+            }
+
+            let mut uniforms = UniformSet::new();
+            uniforms.add("tex", UniformValue::Texture2d(image, None));
+            uniforms.add("iResolution", UniformValue::Vec3(resolution));
+            uniforms.add("iTime", UniformValue::Float(inputs.time));
+            uniforms.add("iTimeHigh", UniformValue::Float(inputs.time_high));
+            uniforms.add("iTimeLow", UniformValue::Float(inputs.time_low));
+            uniforms.add("iTimeDelta", UniformValue::Float(inputs.time_delta));
+            uniforms.add("iFrame", UniformValue::SignedInt(inputs.frame));
+            uniforms.add("iFrameRate", UniformValue::Float(inputs.frame_rate));
+            uniforms.add("iDate", UniformValue::Vec4(inputs.date));
+            uniforms.add("iMouse", UniformValue::Vec4(inputs.mouse));
+            uniforms.add("iIdleTime", UniformValue::Float(inputs.idle_time));
+            if let Some(camera) = inputs.camera {
+                uniforms.add("uCameraPos", UniformValue::Vec3(camera.position));
+                uniforms.add("uCameraDir", UniformValue::Vec3(camera.direction));
+                uniforms.add("uViewMatrix", UniformValue::Mat4(camera.view));
+            }
+            for (name, &value) in &self.uniforms {
+                uniforms.add(name, UniformValue::Float(value));
+            }
+            for (name, constant) in &self.constants {
+                uniforms.add(name, constant.uniform());
+            }
+            for scrub in &self.scrubs {
+                uniforms.add(&scrub.name, UniformValue::Float(scrub.value));
+            }
+            for generator in &self.generators {
+                uniforms.add(&generator.name, generator.uniform());
+            }
+            // Earlier passes provide this frame's output, later ones the
+            // previous frame's. A pass can't sample its own render target.
+            for other in self.passes.iter().filter(|other| other.name != pass.name) {
+                if let Some(output) = other.output() {
+                    uniforms.add(&other.name, UniformValue::Texture2d(output, Some(pass_sampler())));
+                }
+            }
+            for (name, buffer) in &self.buffers {
+                uniforms.add(name, UniformValue::Block(buffer.as_slice_any(), storage_layout));
+            }
+
+            match pass.stage {
+                Stage::Fragment { ref program, ref output } => {
+                    // A pass still waiting on its last timer isn't timed again.
+                    let query = if self.timed && pass.query.is_none() {
+                        TimeElapsedQuery::new(display).ok()
+                    } else {
+                        None
+                    };
+                    let params = DrawParameters {
+                        time_elapsed_query: query.as_ref(),
+                        ..Default::default()
+                    };
+                    output.as_surface().
+                        draw(&self.vertex_buffer, self.indices, program, &uniforms, &params).
+                        map_err(|_| ShadeyError::Gl("Could not draw shader."))?;
+                    if let Some(query) = query {
+                        queries.push((i, query));
+                    }
+                },
+                Stage::Compute { ref shader, workgroups: [x, y, z] } => {
+                    shader.execute(uniforms, x, y, z);
+                }
+            }
+        }
+
+        for (pass, ran) in self.passes.iter_mut().zip(should_run) {
+            pass.stale &= !ran;
+        }
+        for (i, query) in queries {
+            self.passes[i].query = Some(query);
+        }
+
+        Ok(())
+    }
+
+    /// Scale the brightness of everything presented, from 0 (black) to 1.
+    pub fn set_brightness(&mut self, brightness: f32) {
+        self.blitter.set_gain(brightness);
+    }
+
+    /// Simulate a colour vision deficiency in what's presented.
+    pub fn set_vision(&mut self, vision: ColorVision) {
+        self.blitter.set_vision(vision);
+    }
+
+    /// Draw the final pass's output onto `target`.
+    pub fn present<S: Surface>(&self, target: &mut S) -> Result<(), ShadeyError> {
+        target.clear_color(1.0, 1.0, 1.0, 1.0);
+        self.blitter.draw(target, self.output())
+    }
+
+    /// Draw the final pass's output onto `viewport` of `target`, leaving the
+    /// rest of it alone.
+    pub fn present_in<S: Surface>(&self, target: &mut S, viewport: Rect) -> Result<(), ShadeyError> {
+        self.blitter.draw_in(target, self.output(), Some(viewport))
+    }
+}
@@ -0,0 +1,149 @@
+//! Presentation timing from `GLX_OML_sync_control`, for investigating
+//! stutter with what the display actually showed rather than CPU timings.
+//!
+//! After every swap the counters of vertical blanks (MSC) and completed
+//! swaps (SBC) are read. With vsync each blank should bring a new frame, so
+//! blanks without one mean a frame arrived late and the previous one was
+//! shown again.
+
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_int, c_ulong, c_void};
+
+use glium::Display;
+use glium::glutin::GlContext;
+use glium::glutin::os::unix::WindowExt;
+use x11_dl::glx::Glx;
+use x11_dl::xlib::Xlib;
+
+type GetSyncValues = unsafe extern "C" fn(*mut c_void, c_ulong, *mut i64, *mut i64, *mut i64) -> c_int;
+
+#[derive(Clone, Copy)]
+struct SyncValues {
+    /// System time in microseconds.
+    ust: i64,
+    msc: i64,
+    sbc: i64
+}
+
+fn read(get_sync_values: GetSyncValues, display: *mut c_void, window: c_ulong) -> Option<SyncValues> {
+    let (mut ust, mut msc, mut sbc) = (0, 0, 0);
+    if unsafe { get_sync_values(display, window, &mut ust, &mut msc, &mut sbc) } == 0 {
+        return None;
+    }
+
+    Some(SyncValues { ust, msc, sbc })
+}
+
+/// Whether the display's GLX lists `GLX_OML_sync_control`. glvnd and Mesa
+/// hand out an address for any `glX` name, so that alone proves nothing.
+fn supported(display: *mut c_void) -> bool {
+    let (glx, xlib) = match (Glx::open(), Xlib::open()) {
+        (Ok(glx), Ok(xlib)) => (glx, xlib),
+        _ => return false
+    };
+    unsafe {
+        let display = display as *mut _;
+        let extensions = (glx.glXQueryExtensionsString)(display, (xlib.XDefaultScreen)(display));
+        !extensions.is_null() &&
+            CStr::from_ptr(extensions).to_string_lossy().split_whitespace().any(|name| name == "GLX_OML_sync_control")
+    }
+}
+
+pub struct PresentStats {
+    get_sync_values: GetSyncValues,
+    display: *mut c_void,
+    window: c_ulong,
+    first: Option<SyncValues>,
+    last: Option<SyncValues>,
+    /// Blanks that showed a frame for the second time or more.
+    repeated: i64,
+    /// Times one or more frames in a row were late.
+    stutters: u64,
+    /// Longest any one frame stayed on screen, in microseconds.
+    longest: i64
+}
+
+impl PresentStats {
+    /// `None` if the window isn't on X11 or the driver lacks the extension.
+    pub fn new(display: &Display) -> Option<PresentStats> {
+        let window = display.gl_window();
+        let (x_display, x_window) = (window.get_xlib_display()?, window.get_xlib_window()? as c_ulong);
+        if !supported(x_display) {
+            return None;
+        }
+        let address = window.get_proc_address("glXGetSyncValuesOML");
+        if address.is_null() {
+            return None;
+        }
+
+        let get_sync_values = unsafe { mem::transmute::<*const (), GetSyncValues>(address) };
+        read(get_sync_values, x_display, x_window)?;
+
+        Some(PresentStats {
+            get_sync_values,
+            display: x_display,
+            window: x_window,
+            first: None,
+            last: None,
+            repeated: 0,
+            stutters: 0,
+            longest: 0
+        })
+    }
+
+    /// Call after every swap.
+    pub fn update(&mut self) {
+        let now = match read(self.get_sync_values, self.display, self.window) {
+            Some(now) => now,
+            None => return
+        };
+
+        if let Some(last) = self.last {
+            let (blanks, swaps) = (now.msc - last.msc, now.sbc - last.sbc);
+            // The swap may not have completed yet; count it on a later call.
+            if swaps <= 0 {
+                return;
+            }
+            if blanks > swaps {
+                self.repeated += blanks - swaps;
+                self.stutters += 1;
+            }
+            // Frames that were on time had one blank each, so the late one
+            // had the rest. Averaging over the swaps would hide it.
+            let shown = if blanks > swaps {
+                (now.ust - last.ust) * (blanks - swaps + 1) / blanks
+            } else {
+                (now.ust - last.ust) / swaps
+            };
+            self.longest = self.longest.max(shown);
+        }
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    fn report(&self) -> String {
+        let (first, last) = match (self.first, self.last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return "Present stats: nothing was presented.".to_string()
+        };
+
+        format!(
+            "Present stats: {} frames over {} vertical blanks in {:.1} s. {} blanks repeated a late \
+             frame, over {} stutters. Longest a frame was shown: {:.1} ms.",
+            last.sbc - first.sbc,
+            last.msc - first.msc,
+            (last.ust - first.ust) as f64 * 1e-6,
+            self.repeated,
+            self.stutters,
+            self.longest as f64 * 1e-3
+        )
+    }
+}
+
+/// Reported whenever the window closes, including on reloads.
+impl Drop for PresentStats {
+    fn drop(&mut self) {
+        eprintln!("{}", self.report());
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use error::ShadeyError;
+use expr::Expr;
+use generators::GeneratorSpec;
+use numbers::Decimal;
+use schedule::{self, ScheduleEntry};
+use snapshot::{self, Snapshot};
+use stdin;
+
+/// Built-in values that `run_if` expressions may refer to, alongside any
+/// uniforms declared in the project file.
+pub const BUILTIN_VARIABLES: [&str; 5] = ["iTime", "iTimeDelta", "iFrame", "iFrameRate", "iIdleTime"];
+
+/// On-disk layout of a `shadey.toml` project file.
+#[derive(Debug, Deserialize)]
+struct ProjectFile {
+    image: String,
+    #[serde(rename = "pass")]
+    passes: Vec<PassFile>,
+    #[serde(default)]
+    uniforms: BTreeMap<String, UniformFile>,
+    #[serde(default)]
+    buffers: BTreeMap<String, usize>,
+    #[serde(default)]
+    snapshots: BTreeMap<String, Snapshot>,
+    #[serde(default)]
+    schedule: Vec<ScheduleFile>,
+    #[serde(default)]
+    generators: BTreeMap<String, GeneratorSpec>,
+    /// How numbers in `run_if` expressions are written.
+    #[serde(default)]
+    decimal: Decimal
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleFile {
+    at: String,
+    load: Option<String>,
+    #[serde(default)]
+    uniforms: BTreeMap<String, f32>,
+    brightness: Option<f32>
+}
+
+/// A uniform is either just its initial value, or a table that also gives
+/// the range it's randomized within.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum UniformFile {
+    Value(f32),
+    Range { value: f32, min: f32, max: f32 }
+}
+
+#[derive(Debug, Deserialize)]
+struct PassFile {
+    name: Option<String>,
+    shader: Option<String>,
+    compute: Option<String>,
+    workgroups: Option<[u32; 3]>,
+    run_if: Option<String>,
+    on_reload: Option<OnReload>
+}
+
+/// Whether state survives the shaders being reloaded after an edit.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnReload {
+    /// Start again from blank, with iTime and iFrame back at 0.
+    Reset,
+    /// Carry on from the last frame before the reload.
+    KeepState
+}
+
+impl OnReload {
+    pub fn from_name(name: &str) -> Result<OnReload, ShadeyError> {
+        match name {
+            "reset" => Ok(OnReload::Reset),
+            "keep-state" => Ok(OnReload::KeepState),
+            _ => Err(ShadeyError::invalid(format!("Unknown --on-reload `{}`. Expected keep-state or reset.", name)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PassKind {
+    /// Renders a full screen quad into the pass's output texture.
+    Fragment,
+    /// Dispatches a compute shader, which communicates with other passes
+    /// through the project's storage buffers.
+    Compute { workgroups: [u32; 3] }
+}
+
+pub struct PassSpec {
+    /// Name of the sampler other passes use to read this pass's output.
+    pub name: String,
+    pub shader: PathBuf,
+    pub kind: PassKind,
+    /// When present the pass is only rendered on frames where this holds.
+    /// Skipped passes keep their previous output.
+    pub run_if: Option<Expr>,
+    /// Overrides `--on-reload` for this pass's output.
+    pub on_reload: Option<OnReload>
+}
+
+pub struct Project {
+    pub image: PathBuf,
+    pub passes: Vec<PassSpec>,
+    pub uniforms: BTreeMap<String, f32>,
+    /// The `(min, max)` range of uniforms that declare one.
+    pub ranges: BTreeMap<String, (f32, f32)>,
+    /// Shader storage buffers shared by every pass, with their length in
+    /// `vec4` elements.
+    pub buffers: BTreeMap<String, usize>,
+    /// Saved snapshots by slot number.
+    pub snapshots: BTreeMap<u32, Snapshot>,
+    /// Changes to make at times of day, sorted by time.
+    pub schedule: Vec<ScheduleEntry>,
+    /// Values computed on the CPU each frame, see `generators`.
+    pub generators: BTreeMap<String, GeneratorSpec>,
+    /// Every file that should trigger a reload when modified.
+    pub watched: Vec<PathBuf>,
+    /// Whether `/*@scrub*/` literals become live values, see `scrub`.
+    pub scrub: bool
+}
+
+impl Project {
+    /// A project consisting of a single pass over one image.
+    pub fn single(image: &str, shader: &str) -> Project {
+        Project {
+            image: PathBuf::from(image),
+            passes: vec![PassSpec {
+                name: "pass0".to_string(),
+                shader: PathBuf::from(shader),
+                kind: PassKind::Fragment,
+                run_if: None,
+                on_reload: None
+            }],
+            uniforms: BTreeMap::new(),
+            ranges: BTreeMap::new(),
+            buffers: BTreeMap::new(),
+            snapshots: BTreeMap::new(),
+            schedule: Vec::new(),
+            generators: BTreeMap::new(),
+            watched: vec![PathBuf::from(image), PathBuf::from(shader)].
+                into_iter().
+                filter(|path| path != Path::new(stdin::PATH)).
+                collect(),
+            scrub: false
+        }
+    }
+
+    /// Swap the final pass's shader for another file.
+    pub fn replace_shader(&mut self, shader: &Path) {
+        let last = self.passes.len() - 1;
+        let old = ::std::mem::replace(&mut self.passes[last].shader, shader.to_path_buf());
+        // Other passes may share the old file, so only add the new one.
+        if !self.passes.iter().any(|pass| pass.shader == old) {
+            self.watched.retain(|path| *path != old);
+        }
+        self.watched.push(shader.to_path_buf());
+    }
+
+    pub fn load(project_path: &str) -> Result<Project, ShadeyError> {
+        let path = Path::new(project_path);
+        let mut file = File::open(path).map_err(|e| ShadeyError::io("Could not open project", path, e))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).map_err(|e| ShadeyError::io("Could not read project", path, e))?;
+        let parsed: ProjectFile = toml::from_str(&contents).
+            map_err(|e| ShadeyError::Parse { path: path.to_path_buf(), message: e.to_string() })?;
+
+        let ProjectFile {
+            image,
+            passes: pass_files,
+            uniforms: uniform_tables,
+            buffers,
+            snapshots: snapshot_tables,
+            schedule: schedule_tables,
+            generators,
+            decimal
+        } = parsed;
+        if pass_files.is_empty() {
+            return Err(ShadeyError::invalid("Project file must declare at least one pass."));
+        }
+        if pass_files[pass_files.len() - 1].shader.is_none() {
+            return Err(ShadeyError::invalid("The final pass must be a fragment shader pass."));
+        }
+        if buffers.values().any(|&length| length == 0) {
+            return Err(ShadeyError::invalid("Storage buffers must hold at least one element."));
+        }
+
+        let mut uniforms = BTreeMap::new();
+        let mut ranges = BTreeMap::new();
+        for (name, uniform) in uniform_tables {
+            let value = match uniform {
+                UniformFile::Value(value) => value,
+                UniformFile::Range { value, min, max } => {
+                    if min > max {
+                        return Err(ShadeyError::invalid(format!(
+                            "Uniform `{}` has a min greater than its max.",
+                            name
+                        )));
+                    }
+                    ranges.insert(name.clone(), (min, max));
+                    value
+                }
+            };
+            uniforms.insert(name, value);
+        }
+
+        for (name, generator) in &generators {
+            if name == "tex" || uniforms.contains_key(name) || buffers.contains_key(name) {
+                return Err(ShadeyError::invalid(format!(
+                    "Generator name `{}` is taken by a uniform, a buffer or `tex`.",
+                    name
+                )));
+            }
+            generator.validate(name, &uniforms)?;
+        }
+
+        let mut snapshots = BTreeMap::new();
+        for (slot, snapshot) in snapshot_tables {
+            match slot.parse() {
+                Ok(slot) if slot < snapshot::SLOTS => snapshots.insert(slot, snapshot),
+                _ => return Err(ShadeyError::invalid(format!(
+                    "Snapshot slot `{}` isn't a number from 0 to 9.",
+                    slot
+                )))
+            };
+        }
+
+        // Paths in the project file are relative to the project file itself.
+        let base = Path::new(project_path).parent().unwrap_or_else(|| Path::new(""));
+        let image = base.join(&image);
+        let mut watched = vec![PathBuf::from(project_path), image.clone()];
+
+        let mut schedule = Vec::new();
+        for entry in schedule_tables {
+            if entry.load.is_none() && entry.uniforms.is_empty() && entry.brightness.is_none() {
+                return Err(ShadeyError::invalid(format!(
+                    "Schedule entry at {} needs a shader to `load`, `uniforms` or a `brightness` to set.",
+                    entry.at
+                )));
+            }
+            if entry.brightness.is_some_and(|b| !(0.0..=1.0).contains(&b)) {
+                return Err(ShadeyError::invalid(format!(
+                    "Schedule entry at {} has a brightness outside of 0 to 1.",
+                    entry.at
+                )));
+            }
+            if let Some(unknown) = entry.uniforms.keys().find(|name| !uniforms.contains_key(*name)) {
+                return Err(ShadeyError::invalid(format!(
+                    "Schedule entry at {} sets `{}`, which isn't a project uniform.",
+                    entry.at,
+                    unknown
+                )));
+            }
+            schedule.push(ScheduleEntry {
+                at: schedule::parse_time(&entry.at)?,
+                shader: entry.load.map(|shader| base.join(shader)),
+                uniforms: entry.uniforms,
+                brightness: entry.brightness
+            });
+        }
+        schedule.sort_by_key(|entry| entry.at);
+
+        let mut passes: Vec<PassSpec> = Vec::new();
+        for (i, pass) in pass_files.into_iter().enumerate() {
+            let name = pass.name.unwrap_or_else(|| format!("pass{}", i));
+            let taken = uniforms.contains_key(&name) || buffers.contains_key(&name) || generators.contains_key(&name);
+            if name == "tex" || passes.iter().any(|p| p.name == name) || taken {
+                return Err(ShadeyError::invalid(format!(
+                    "Pass name `{}` is taken. Names must be unique and may not be `tex` or a uniform's, buffer's or \
+                     generator's name.",
+                    name
+                )));
+            }
+
+            let (shader, kind) = match (pass.shader, pass.compute) {
+                (Some(shader), None) => (shader, PassKind::Fragment),
+                (None, Some(compute)) => {
+                    let workgroups = pass.workgroups.unwrap_or([1, 1, 1]);
+                    (compute, PassKind::Compute { workgroups })
+                },
+                _ => return Err(ShadeyError::invalid(format!(
+                    "Pass `{}` needs exactly one of `shader` or `compute`.",
+                    name
+                )))
+            };
+
+            let run_if = match pass.run_if {
+                Some(ref src) => Some(Expr::parse(src, decimal)?),
+                None => None
+            };
+            if let Some(ref expr) = run_if {
+                let known = |var: &str| {
+                    BUILTIN_VARIABLES.contains(&var) || uniforms.contains_key(var)
+                };
+                if let Some(unknown) = expr.variables().into_iter().find(|&var| !known(var)) {
+                    return Err(ShadeyError::invalid(format!(
+                        "Unknown variable `{}` in run_if of pass `{}`.",
+                        unknown,
+                        name
+                    )));
+                }
+            }
+
+            let shader = base.join(&shader);
+            watched.push(shader.clone());
+            passes.push(PassSpec { name, shader, kind, run_if, on_reload: pass.on_reload });
+        }
+
+        Ok(Project {
+            image,
+            passes,
+            uniforms,
+            ranges,
+            buffers,
+            snapshots,
+            schedule,
+            generators,
+            watched,
+            scrub: false
+        })
+    }
+}
@@ -0,0 +1,135 @@
+//! `--metrics=<address>`: serves frame timing, GPU time per pass and reload
+//! and error counts in the Prometheus text format, so installations and
+//! render boxes can be monitored like any other service.
+//!
+//! Requests are answered on a background thread from the latest figures,
+//! which the render loop updates every frame. They're answered one at a
+//! time, so a connection that sends nothing is dropped after `TIMEOUT`
+//! rather than stalling scrapes.
+
+use std::fmt::Write;
+use std::io::{BufRead, BufReader, Write as IoWrite};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use error::ShadeyError;
+use pipeline::{FrameInputs, Pipeline};
+
+/// How long a connection gets to send its request or take the response.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Stats {
+    frame_time: f64,
+    frame_rate: f64,
+    frames: u64,
+    reloads: u64,
+    errors: u64,
+    pass_times: Vec<(String, f64)>
+}
+
+pub struct MetricsServer {
+    stats: Arc<Mutex<Stats>>
+}
+
+/// Label values escape backslashes, quotes and newlines.
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn render(stats: &Stats, uptime: f64) -> String {
+    let mut body = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+        let _ = writeln!(body, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+    };
+    metric("shadey_uptime_seconds", "gauge", "Seconds since shadey started.", uptime);
+    metric("shadey_frame_time_seconds", "gauge", "Time between the last two frames.", stats.frame_time);
+    metric("shadey_frames_per_second", "gauge", "Frame rate, smoothed over the last few frames.", stats.frame_rate);
+    metric("shadey_frames_total", "counter", "Frames rendered.", stats.frames as f64);
+    metric("shadey_reloads_total", "counter", "Times the shaders or project were reloaded.", stats.reloads as f64);
+    metric("shadey_errors_total", "counter", "Errors, including shaders that failed to compile.", stats.errors as f64);
+
+    if !stats.pass_times.is_empty() {
+        body.push_str("# HELP shadey_pass_gpu_time_seconds GPU time of the latest timed draw of each pass.\n");
+        body.push_str("# TYPE shadey_pass_gpu_time_seconds gauge\n");
+        for (pass, time) in &stats.pass_times {
+            let _ = writeln!(body, "shadey_pass_gpu_time_seconds{{pass=\"{}\"}} {}", label(pass), time);
+        }
+    }
+
+    body
+}
+
+fn respond(mut stream: TcpStream, stats: &Mutex<Stats>, start: Instant) {
+    if stream.set_read_timeout(Some(TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(TIMEOUT))).is_err() {
+        return;
+    }
+    let mut request = String::new();
+    if BufReader::new(&stream).read_line(&mut request).is_err() {
+        return;
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let response = if path == "/metrics" || path == "/" {
+        let body = match stats.lock() {
+            Ok(stats) => render(&stats, start.elapsed().as_secs_f64()),
+            Err(_) => return
+        };
+        format!(
+            "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+impl MetricsServer {
+    /// Listen on `address`, e.g. `:9090` for this machine only or
+    /// `0.0.0.0:9090` for every interface.
+    pub fn start(address: &str) -> Result<MetricsServer, ShadeyError> {
+        let address = if address.starts_with(':') { format!("127.0.0.1{}", address) } else { address.to_string() };
+        let listener = TcpListener::bind(&address).
+            map_err(|e| ShadeyError::invalid(format!("Could not serve metrics on {}: {}", address, e)))?;
+        println!("Serving metrics on http://{}/metrics", address);
+
+        let stats = Arc::new(Mutex::new(Stats::default()));
+        let shared = stats.clone();
+        let start = Instant::now();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &shared, start);
+            }
+        });
+
+        Ok(MetricsServer { stats })
+    }
+
+    fn update<F: FnOnce(&mut Stats)>(&self, f: F) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+
+    /// Call after every frame.
+    pub fn frame(&self, inputs: &FrameInputs, pipeline: &Pipeline) {
+        self.update(|stats| {
+            stats.frame_time = f64::from(inputs.time_delta);
+            stats.frame_rate = f64::from(inputs.frame_rate);
+            stats.frames += 1;
+            stats.pass_times = pipeline.gpu_times().into_iter().map(|(name, time)| (name.to_string(), time)).collect();
+        });
+    }
+
+    pub fn reloaded(&self) {
+        self.update(|stats| stats.reloads += 1);
+    }
+
+    pub fn error(&self) {
+        self.update(|stats| stats.errors += 1);
+    }
+}
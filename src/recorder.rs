@@ -0,0 +1,165 @@
+//! Records exactly the frames that were presented, together with the time at
+//! which each one was shown.
+//!
+//! Frames are written as a PNG sequence alongside `timestamps.csv` and an
+//! ffmpeg concat script (`frames.ffconcat`) carrying each frame's real
+//! duration, so the footage can be muxed into a variable frame rate video
+//! without being resampled:
+//!
+//!     ffmpeg -f concat -i frames.ffconcat -vsync vfr out.mkv
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use glium::texture::{RawImage2d, Texture2d};
+use image::{self, RgbaImage};
+
+use error::ShadeyError;
+
+/// How many frames may be waiting on the encoder before rendering blocks.
+/// Blocking is preferable to dropping frames, which would make the recording
+/// disagree with what was on screen.
+const QUEUE_LENGTH: usize = 64;
+
+struct CapturedFrame {
+    index: u32,
+    seconds: f64,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+pub struct Recorder {
+    sender: Option<SyncSender<CapturedFrame>>,
+    worker: Option<JoinHandle<Result<(), ShadeyError>>>,
+    start: Option<Instant>,
+    frames: u32
+}
+
+fn seconds_since(start: Instant) -> f64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9
+}
+
+fn frame_name(index: u32) -> String {
+    format!("frame_{:06}.png", index)
+}
+
+fn write_frame(dir: &Path, index: u32, width: u32, height: u32, pixels: Vec<u8>) ->
+    Result<(), ShadeyError>
+{
+    // OpenGL rows run bottom to top.
+    let img = RgbaImage::from_raw(width, height, pixels).
+        ok_or(ShadeyError::Gl("Captured frame has the wrong size."))?;
+    let path = dir.join(frame_name(index));
+    image::imageops::flip_vertical(&img).save(&path).
+        map_err(|e| ShadeyError::io("Could not write recorded frame", &path, e))
+}
+
+/// Read a texture back as an image with its rows top to bottom.
+pub fn texture_image(texture: &Texture2d) -> Result<RgbaImage, ShadeyError> {
+    let raw: RawImage2d<u8> = texture.read();
+    let img = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).
+        ok_or(ShadeyError::Gl("Captured frame has the wrong size."))?;
+
+    Ok(image::imageops::flip_vertical(&img))
+}
+
+/// Save a single texture as a PNG, e.g. for a screenshot.
+pub fn save_texture(texture: &Texture2d, path: &Path) -> Result<(), ShadeyError> {
+    texture_image(texture)?.save(path).
+        map_err(|e| ShadeyError::io("Could not write screenshot", path, e))
+}
+
+fn encode(dir: PathBuf, frames: Receiver<CapturedFrame>) -> Result<(), ShadeyError> {
+    let timestamps_path = dir.join("timestamps.csv");
+    let concat_path = dir.join("frames.ffconcat");
+    let create = |path: &Path| File::create(path).
+        map(BufWriter::new).
+        map_err(|e| ShadeyError::io("Could not create", path, e));
+    let mut timestamps = create(&timestamps_path)?;
+    let mut concat = create(&concat_path)?;
+    let timestamps_error = |e| ShadeyError::io("Could not write", &timestamps_path, e);
+    let concat_error = |e| ShadeyError::io("Could not write", &concat_path, e);
+
+    writeln!(timestamps, "frame,seconds").map_err(timestamps_error)?;
+    writeln!(concat, "ffconcat version 1.0").map_err(concat_error)?;
+
+    // A frame's duration is only known once the next one has been presented.
+    let mut previous: Option<(u32, f64)> = None;
+    let mut last_duration = 0.0;
+    for frame in frames {
+        write_frame(&dir, frame.index, frame.width, frame.height, frame.pixels)?;
+        writeln!(timestamps, "{},{:.6}", frame.index, frame.seconds).map_err(timestamps_error)?;
+
+        if let Some((index, seconds)) = previous {
+            last_duration = frame.seconds - seconds;
+            writeln!(concat, "file '{}'\nduration {:.6}", frame_name(index), last_duration).
+                map_err(concat_error)?;
+        }
+        previous = Some((frame.index, frame.seconds));
+    }
+
+    // The concat demuxer ignores the duration of the final entry unless the
+    // file is listed twice.
+    if let Some((index, _)) = previous {
+        writeln!(concat, "file '{0}'\nduration {1:.6}\nfile '{0}'", frame_name(index), last_duration).
+            map_err(concat_error)?;
+    }
+
+    timestamps.flush().map_err(timestamps_error)?;
+    concat.flush().map_err(concat_error)
+}
+
+impl Recorder {
+    pub fn new(dir: &str) -> Result<Recorder, ShadeyError> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir).map_err(|e| ShadeyError::io("Could not create recording directory", &dir, e))?;
+
+        let (sender, receiver) = sync_channel(QUEUE_LENGTH);
+        let worker = thread::spawn(move || encode(dir, receiver));
+
+        Ok(Recorder {
+            sender: Some(sender),
+            worker: Some(worker),
+            start: None,
+            frames: 0
+        })
+    }
+
+    /// Queue the frame that was just presented. `texture` must hold exactly
+    /// what was drawn to the window.
+    pub fn capture(&mut self, texture: &Texture2d) -> Result<(), ShadeyError> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let seconds = seconds_since(start);
+        let raw: RawImage2d<u8> = texture.read();
+
+        let frame = CapturedFrame {
+            index: self.frames,
+            seconds,
+            width: raw.width,
+            height: raw.height,
+            pixels: raw.data.into_owned()
+        };
+        self.frames += 1;
+
+        match self.sender {
+            Some(ref sender) => sender.send(frame).map_err(|_| ShadeyError::Worker("The recording encoder stopped.")),
+            None => Err(ShadeyError::Worker("The recording has already finished."))
+        }
+    }
+
+    /// Wait for every queued frame to be written.
+    pub fn finish(&mut self) -> Result<(), ShadeyError> {
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => worker.join().map_err(|_| ShadeyError::Worker("The recording encoder crashed."))?,
+            None => Ok(())
+        }
+    }
+}
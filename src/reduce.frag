@@ -0,0 +1,24 @@
+#version 140
+
+out vec4 color;
+
+uniform sampler2D source;
+
+// Each output texel is the sum of a BLOCK x BLOCK square of the source.
+const int BLOCK = 8;
+
+void main() {
+    ivec2 size = textureSize(source, 0);
+    ivec2 origin = ivec2(gl_FragCoord.xy) * BLOCK;
+
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < BLOCK; y++) {
+        for (int x = 0; x < BLOCK; x++) {
+            ivec2 q = origin + ivec2(x, y);
+            if (all(lessThan(q, size))) {
+                sum += texelFetch(source, q, 0);
+            }
+        }
+    }
+    color = sum;
+}
@@ -0,0 +1,297 @@
+//! `shadey render`: renders frames of a shader or project offline, at a
+//! fixed size and frame rate, optionally saving them and printing hashes.
+//!
+//! `--capture-speed` makes `iTime` advance slower or faster than the frames
+//! would play back at `--fps`, for slow motion or time-lapses.
+//!
+//! With `--bench` it also times every frame and reports how the GPU's
+//! temperature and clock changed over the run, and with `--flicker-metric`
+//! how much the frames shimmer, see `flicker`.
+//!
+//! `--start-frame` and `--end-frame` render a slice of a longer animation,
+//! numbered and timed as in the whole, so it can be split between machines.
+//!
+//! `--find-loop=max=10s` first renders up to ten seconds looking for the frame
+//! that best matches the first, then renders up to just before it, so the
+//! export loops seamlessly.
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use glium::{glutin, Display, Surface};
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::MagnifySamplerFilter;
+
+use animation::{Format, GifOptions, GifWriter, VideoWriter};
+
+use clock;
+use constants;
+use control::ControlServer;
+use error::ShadeyError;
+use flicker::Flicker;
+use golden::{Golden, Tolerance};
+use gpu;
+use hash;
+use metrics::Measure;
+use numbers::Decimal;
+use offline;
+use pipeline::{FrameInputs, Pipeline};
+use project::Project;
+use recorder::texture_image;
+use {input_image, Args};
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e3 + f64::from(duration.subsec_nanos()) * 1e-6
+}
+
+fn report(timings: &[Duration], sensors: &gpu::Samples) {
+    if let (Some(fastest), Some(slowest)) = (timings.iter().min(), timings.iter().max()) {
+        let total: f64 = timings.iter().map(|&t| milliseconds(t)).sum();
+        println!(
+            "Frames: {}, mean {:.3} ms, fastest {:.3} ms, slowest {:.3} ms",
+            timings.len(),
+            total / timings.len() as f64,
+            milliseconds(*fastest),
+            milliseconds(*slowest)
+        );
+    }
+    for line in sensors.report() {
+        println!("{}", line);
+    }
+}
+
+/// Shortest loop `--find-loop` considers, so frames that barely differ from
+/// the first because little has moved yet don't count.
+const MIN_LOOP_SECONDS: f32 = 0.5;
+
+/// The longest loop to look for, from `max=10s`.
+fn parse_find_loop(spec: &str, decimal: Decimal) -> Result<f32, ShadeyError> {
+    let seconds = spec.trim_start_matches("max=");
+    let seconds = seconds.strip_suffix('s').unwrap_or(seconds);
+    decimal.parse(seconds).ok().filter(|&max: &f32| max > MIN_LOOP_SECONDS).ok_or_else(|| ShadeyError::invalid(format!(
+        "Invalid --find-loop `{}`: give the longest loop to look for, e.g. max=10s, over {}s.",
+        spec, MIN_LOOP_SECONDS
+    )))
+}
+
+fn new_pipeline(display: &Display, project: &Project, args: &Args, size: (u32, u32)) ->
+    Result<Pipeline, ShadeyError>
+{
+    let mut pipeline = Pipeline::new(display, project)?;
+    pipeline.fix_size(size);
+    pipeline.set_constants(constants::parse_all(&args.flag_uniform, args.decimal())?)?;
+    Ok(pipeline)
+}
+
+fn frame_inputs(args: &Args, frame: u32, fps: f32, speed: f32) -> FrameInputs {
+    // Time advances by exactly one frame, however long rendering takes.
+    let step = speed / fps;
+    let time = f64::from(args.time) + f64::from(frame) * f64::from(step);
+    let (time_high, time_low) = clock::split(time, args.time_wrap);
+    FrameInputs {
+        time: time as f32,
+        time_high,
+        time_low,
+        time_delta: if frame == 0 { 0.0 } else { step },
+        frame: frame as i32,
+        frame_rate: fps,
+        ..offline::still_inputs(args)
+    }
+}
+
+/// Render the frames of `search` and return how many come before the one that
+/// best matches the first.
+fn find_loop(display: &Display, texture: &Texture2d, mut pipeline: Pipeline, search: RangeInclusive<u32>, fps: f32,
+    speed: f32, args: &Args) -> Result<u32, ShadeyError>
+{
+    let measure = Measure::new(display)?;
+    let start = *search.start();
+    pipeline.render(display, texture, &frame_inputs(args, start, fps, speed))?;
+    let (width, height) = pipeline.output().dimensions();
+    let first = Texture2d::empty_with_format(display, UncompressedFloatFormat::F32F32F32F32, MipmapsOption::NoMipmap,
+        width, height).map_err(|_| ShadeyError::Gl("Could not keep the first frame."))?;
+    pipeline.output().as_surface().fill(&first.as_surface(), MagnifySamplerFilter::Nearest);
+
+    let min_frames = ((MIN_LOOP_SECONDS * fps / speed).ceil() as u32).max(2);
+    let mut best: Option<(u32, f32)> = None;
+    for frame in start + 1..=*search.end() {
+        pipeline.render(display, texture, &frame_inputs(args, frame, fps, speed))?;
+        if frame - start < min_frames {
+            continue;
+        }
+        let rmse = measure.compare(display, &first, pipeline.output())?.rmse;
+        if best.is_none_or(|(_, best_rmse)| rmse < best_rmse) {
+            best = Some((frame - start, rmse));
+        }
+    }
+
+    let (frame, rmse) = best.ok_or_else(|| ShadeyError::invalid("--find-loop has no frames to search at this --fps."))?;
+    eprintln!(
+        "Loop found after {:.3}s ({} frames), differing from the first frame by an RMSE of {:.4}.",
+        frame as f32 * speed / fps,
+        frame,
+        rmse
+    );
+    Ok(frame)
+}
+
+pub fn run(args: &Args) -> Result<(), ShadeyError> {
+    let size = offline::parse_size(&args.flag_size)?;
+    let mut frames = offline::frame_range(args)?;
+    let fps: f32 = args.decimal().parse(&args.flag_fps).ok().filter(|&fps: &f32| fps > 0.0).
+        ok_or_else(|| ShadeyError::invalid(format!("Invalid frame rate `{}`.", args.flag_fps)))?;
+    let speed: f32 = args.decimal().parse(&args.flag_capture_speed).ok().filter(|&speed: &f32| speed > 0.0).
+        ok_or_else(|| ShadeyError::invalid(format!("Invalid capture speed `{}`.", args.flag_capture_speed)))?;
+    // Checked before a display is opened, though each pipeline parses them.
+    constants::parse_all(&args.flag_uniform, args.decimal())?;
+    let gif_options = GifOptions::parse(&args.flag_gif_colors, &args.flag_gif_palette, &args.flag_gif_dither)?;
+    let mut tolerance = Tolerance::parse(&args.flag_tolerance, args.decimal())?;
+    if let Some(ref dir) = args.flag_golden {
+        tolerance = Tolerance::for_dir(dir, tolerance, args.decimal())?;
+    }
+    let find_loop_max = match args.flag_find_loop {
+        Some(ref spec) => Some(parse_find_loop(spec, args.decimal())?),
+        None => None
+    };
+    let mut flicker = match args.flag_flicker_metric {
+        Some(ref window) => Some(Flicker::new(window)?),
+        None => None
+    };
+    let animated = [&args.flag_gif, &args.flag_webp, &args.flag_avif, &args.flag_archival].iter().
+        any(|path| path.is_some());
+    let nothing = args.flag_output.is_none() && !animated && !args.flag_hash && !args.flag_bench;
+    if nothing && flicker.is_none() && args.flag_golden.is_none() {
+        return Err(ShadeyError::invalid(
+            "Nothing to do: give --output, --gif, --webp, --avif, --archival, --golden, --hash, --bench or \
+             --flicker-metric."
+        ));
+    }
+    let output = args.flag_output.as_ref().map(PathBuf::from);
+    if let Some(ref dir) = output {
+        fs::create_dir_all(dir).map_err(|e| ShadeyError::io("Could not create", dir, e))?;
+    }
+
+    let project = match args.flag_project {
+        Some(ref path) => Project::load(path)?,
+        None => Project::single(args.flag_image.as_deref().unwrap_or(""), &args.arg_shader)
+    };
+
+    let events_loop = glutin::EventsLoop::new();
+    let display = offline::hidden_display(&events_loop, args)?;
+    let texture = match args.flag_project {
+        Some(_) => input_image(&display, &project.image, args)?,
+        None => offline::input_texture(&display, args)?
+    };
+    let mut pipeline = new_pipeline(&display, &project, args, size)?;
+    if let Some(max) = find_loop_max {
+        let search = frames.start..=frames.start + (max * fps / speed).floor() as u32;
+        frames.end = frames.start + find_loop(&display, &texture, pipeline, search, fps, speed, args)?;
+        // From the start again, for passes that feed back into themselves.
+        pipeline = new_pipeline(&display, &project, args, size)?;
+    }
+    let mut gif = match args.flag_gif {
+        Some(ref path) => Some(GifWriter::new(Path::new(path), size, fps, gif_options)?),
+        None => None
+    };
+    let mut golden = match args.flag_golden {
+        Some(ref dir) => Some(Golden::new(&display, dir, tolerance)?),
+        None => None
+    };
+    let mut encoders = Vec::new();
+    if let Some(ref path) = args.flag_webp {
+        encoders.push(VideoWriter::new(Path::new(path), size, fps, Format::WebP)?);
+    }
+    if let Some(ref path) = args.flag_avif {
+        encoders.push(VideoWriter::new(Path::new(path), size, fps, Format::Avif)?);
+    }
+    if let Some(ref path) = args.flag_archival {
+        encoders.push(VideoWriter::new(Path::new(path), size, fps, Format::Ffv1)?);
+    }
+
+    // Only with --socket, so farm jobs don't all contend for the default.
+    let control = match args.flag_socket {
+        Some(ref socket) => ControlServer::start(Some(socket)),
+        None => None
+    };
+    if let Some(ref control) = control {
+        control.shader(Path::new(args.flag_project.as_deref().unwrap_or(&args.arg_shader)));
+    }
+
+    let mut timings = Vec::new();
+    let mut sensors = gpu::Samples::new();
+    let mut last_sample: Option<Instant> = None;
+    for frame in frames.clone() {
+        // Everything written so far is finished below, as if this were the end.
+        if control.as_ref().is_some_and(ControlServer::quit_requested) {
+            eprintln!("Quitting as asked, before frame {}.", frame);
+            frames.end = frame;
+            break;
+        }
+        let inputs = frame_inputs(args, frame, fps, speed);
+        if args.flag_bench && last_sample.is_none_or(|at| at.elapsed() >= Duration::from_secs(1)) {
+            sensors.sample();
+            last_sample = Some(Instant::now());
+        }
+        let start = Instant::now();
+        pipeline.render(&display, &texture, &inputs)?;
+        if args.flag_bench {
+            // Wait for the GPU so the time covers the work, not just queueing it.
+            display.finish();
+            timings.push(start.elapsed());
+        }
+
+        let img = texture_image(pipeline.output())?;
+        let name = format!("frame-{:05}", frame);
+        if let Some(ref dir) = output {
+            let path = dir.join(format!("{}.png", name));
+            img.save(&path).map_err(|e| ShadeyError::io("Could not write", &path, e))?;
+        }
+        if let Some(ref mut gif) = gif {
+            gif.add(&img)?;
+        }
+        for encoder in &mut encoders {
+            encoder.add(&img)?;
+        }
+        if args.flag_hash {
+            println!("{} {}", name, hash::describe(&img));
+        }
+        if let Some(ref mut flicker) = flicker {
+            flicker.add(&img, frame);
+        }
+        if let Some(ref mut golden) = golden {
+            golden.check(&display, &name, &img, pipeline.output())?;
+        }
+        if let Some(ref control) = control {
+            control.frame(&inputs, &pipeline);
+        }
+    }
+
+    if args.flag_bench {
+        sensors.sample();
+        report(&timings, &sensors);
+    }
+    if let Some(ref flicker) = flicker {
+        flicker.report();
+    }
+    if let Some(gif) = gif {
+        gif.finish()?;
+    }
+    for encoder in encoders {
+        encoder.finish()?;
+    }
+    if let Some(ref golden) = golden {
+        golden.finish()?;
+    }
+    if let Some(ref dir) = output {
+        eprintln!("Wrote {} frames to {}. To encode them:", frames.len(), dir.display());
+        let start = match frames.start {
+            0 => String::new(),
+            start => format!("-start_number {} ", start)
+        };
+        eprintln!("    ffmpeg -framerate {} {}-i {}/frame-%05d.png out.mp4", fps, start, dir.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! `shadey new`: writes a starter shader that already declares the entry
+//! point and uniforms shadey provides.
+
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use image::{Rgb, RgbImage};
+
+use error::ShadeyError;
+
+/// Starter shaders by name, built into the binary so it runs from anywhere.
+pub const TEMPLATES: &[(&str, &str)] = &[
+    ("shadertoy", include_str!("templates/shadertoy.frag")),
+    ("raymarch", include_str!("templates/raymarch.frag")),
+    ("image-filter", include_str!("templates/image-filter.frag"))
+];
+
+fn template_source(template: &str) -> Result<&'static str, ShadeyError> {
+    TEMPLATES.iter().find(|&&(name, _)| name == template).map(|&(_, source)| source).ok_or_else(|| {
+        ShadeyError::invalid(format!("Unknown template `{}`. Expected shadertoy, raymarch or image-filter.", template))
+    })
+}
+
+/// A colourful checkerboard to run shaders over until there's a real image.
+pub fn starter_image() -> RgbImage {
+    let size = 512;
+    RgbImage::from_fn(size, size, |x, y| {
+        let (u, v) = (x as f32 / size as f32, y as f32 / size as f32);
+        let shade = if (x / 64 + y / 64) % 2 == 0 { 1.0 } else { 0.75 };
+        let channel = |value: f32| (value * shade * 255.0) as u8;
+        Rgb([channel(u), channel(v), channel(1.0 - u * v)])
+    })
+}
+
+fn project_source(shader: &Path) -> String {
+    format!(
+        "# Run with: shadey --project=shadey.toml\n\
+         image = \"image.png\"\n\
+         \n\
+         [[pass]]\n\
+         shader = \"{}\"\n",
+        shader.display()
+    )
+}
+
+/// Write `contents` to `path`, refusing to clobber an existing file.
+fn write_new(path: &Path, contents: &str) -> Result<(), ShadeyError> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path).
+        map_err(|e| ShadeyError::io("Could not create", path, e))?;
+    file.write_all(contents.as_bytes()).map_err(|e| ShadeyError::io("Could not write", path, e))
+}
+
+pub fn create(name: &str, template: &str, with_project: bool) -> Result<(), ShadeyError> {
+    let source = template_source(template)?;
+    let shader = if name.ends_with(".frag") {
+        PathBuf::from(name)
+    } else {
+        PathBuf::from(format!("{}.frag", name))
+    };
+    let dir = shader.parent().unwrap_or_else(|| Path::new(""));
+    let project = dir.join("shadey.toml");
+    let image = dir.join("image.png");
+
+    // Check everything first, so nothing is left half written.
+    let mut targets = vec![&shader];
+    if with_project {
+        targets.push(&project);
+    }
+    if let Some(existing) = targets.into_iter().find(|path| path.exists()) {
+        return Err(ShadeyError::invalid(format!("{} already exists.", existing.display())));
+    }
+    let file_name = Path::new(shader.file_name().
+        ok_or_else(|| ShadeyError::invalid(format!("Invalid shader name `{}`.", name)))?);
+
+    write_new(&shader, source)?;
+    println!("Wrote {}", shader.display());
+
+    if with_project {
+        write_new(&project, &project_source(file_name))?;
+        println!("Wrote {}", project.display());
+        // An image that's already there is used as it is.
+        if !image.exists() {
+            starter_image().save(&image).map_err(|e| ShadeyError::io("Could not write", &image, e))?;
+            println!("Wrote {}, a placeholder to replace with your own", image.display());
+        }
+        println!("Run it with: shadey --project={}", project.display());
+    } else {
+        println!("Run it with: shadey <image> {}", shader.display());
+    }
+
+    Ok(())
+}
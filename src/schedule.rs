@@ -0,0 +1,57 @@
+//! Time-of-day schedules, so installations can switch shaders or settings
+//! without anyone at the keyboard:
+//!
+//! ```toml
+//! [[schedule]]
+//! at = "18:00"
+//! load = "evening.frag"
+//! uniforms = { warmth = 0.8 }
+//! brightness = 0.5
+//! ```
+//!
+//! The latest entry whose time has passed applies, wrapping around to the
+//! previous day's last entry before the first one of the day.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use error::ShadeyError;
+
+pub struct ScheduleEntry {
+    /// Local time of day, in seconds since midnight.
+    pub at: u32,
+    pub shader: Option<PathBuf>,
+    pub uniforms: BTreeMap<String, f32>,
+    /// Master brightness from 0 to 1, see `--brightness`.
+    pub brightness: Option<f32>
+}
+
+/// Parse a time of day given as `HH:MM` or `HH:MM:SS`.
+pub fn parse_time(text: &str) -> Result<u32, ShadeyError> {
+    let invalid = || ShadeyError::invalid(format!("Invalid schedule time `{}`. Expected e.g. 18:00.", text));
+    let parts = text.trim().split(':').
+        map(|part| part.parse::<u32>().map_err(|_| invalid())).
+        collect::<Result<Vec<_>, _>>()?;
+
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes] => (hours, minutes, 0),
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        _ => return Err(invalid())
+    };
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return Err(invalid());
+    }
+
+    Ok(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// The entry in effect at `now` seconds since midnight. `entries` must be
+/// sorted by time.
+pub fn active(entries: &[ScheduleEntry], now: f32) -> Option<usize> {
+    if entries.is_empty() {
+        return None;
+    }
+
+    let started = entries.iter().take_while(|entry| entry.at as f32 <= now).count();
+    Some(if started == 0 { entries.len() - 1 } else { started - 1 })
+}
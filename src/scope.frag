@@ -0,0 +1,9 @@
+#version 140
+
+in vec3 shade;
+out vec4 color;
+
+void main() {
+    // Dark colours are lifted a little so they still show up.
+    color = vec4(max(shade, vec3(0.15)), 0.25);
+}
@@ -0,0 +1,127 @@
+//! F4 shows a vectorscope in the bottom left of the window: a sampling of the
+//! output plotted by its chroma, BT.709 Cb across and Cr up, each point in its
+//! own colour. Neutral colours gather in the middle and saturated ones towards
+//! the edge, with letters marking where pure primaries and secondaries land,
+//! which helps match the palette of visuals to stage lighting.
+//!
+//! The points are placed by a vertex shader reading the output directly, so
+//! nothing is read back from the GPU.
+
+use glium::{Blend, BlendingFunction, Display, DrawParameters, LinearBlendingFactor, Program, Surface, VertexBuffer};
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::Texture2d;
+use glium::uniforms::MinifySamplerFilter;
+
+use error::ShadeyError;
+use overlay::{Overlay, CHAR_SIZE};
+
+const SCOPE_VERTEX: &str = include_str!("scope.vert");
+const SCOPE_FRAGMENT: &str = include_str!("scope.frag");
+
+/// Samples taken across and down the output.
+const SAMPLES: u32 = 128;
+/// Side of the scope in pixels.
+const SIZE: f32 = 256.0;
+/// Share of the scope's half width that a chroma of 0.5 reaches, leaving
+/// room for the labels.
+const REACH: f32 = 0.85;
+
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+#[derive(Copy, Clone)]
+struct Sample {
+    texel: [f32; 2]
+}
+
+implement_vertex!(Sample, texel);
+
+pub struct Vectorscope {
+    program: Program,
+    samples: VertexBuffer<Sample>
+}
+
+/// Cb and Cr of `rgb`, as the vertex shader works them out.
+fn chroma(rgb: [f32; 3]) -> (f32, f32) {
+    let y = rgb[0] * LUMA[0] + rgb[1] * LUMA[1] + rgb[2] * LUMA[2];
+    ((rgb[2] - y) / 1.8556, (rgb[0] - y) / 1.5748)
+}
+
+impl Vectorscope {
+    pub fn new(display: &Display) -> Result<Vectorscope, ShadeyError> {
+        let samples: Vec<Sample> = (0..SAMPLES * SAMPLES).map(|i| Sample {
+            texel: [
+                ((i % SAMPLES) as f32 + 0.5) / SAMPLES as f32,
+                ((i / SAMPLES) as f32 + 0.5) / SAMPLES as f32
+            ]
+        }).collect();
+
+        Ok(Vectorscope {
+            program: Program::from_source(display, SCOPE_VERTEX, SCOPE_FRAGMENT, None).
+                map_err(|e| ShadeyError::compile("the vectorscope shader", e))?,
+            samples: VertexBuffer::new(display, &samples).
+                map_err(|_| ShadeyError::Gl("Could not create the vectorscope's samples."))?
+        })
+    }
+
+    /// Top left of the scope in overlay pixels.
+    fn origin(height: f32) -> (f32, f32) {
+        (CHAR_SIZE, height - CHAR_SIZE - SIZE)
+    }
+
+    /// Queue the backdrop and labels on `overlay`, for a window `height`
+    /// pixels high.
+    pub fn draw_backdrop(&self, overlay: &mut Overlay, height: f32) {
+        let (left, top) = Vectorscope::origin(height);
+        overlay.rect(left, top, SIZE, SIZE, [0.05, 0.05, 0.05, 0.9]);
+        overlay.rect(left + SIZE * 0.5, top, 1.0, SIZE, [0.3, 0.3, 0.3, 1.0]);
+        overlay.rect(left, top + SIZE * 0.5, SIZE, 1.0, [0.3, 0.3, 0.3, 1.0]);
+
+        let targets = [
+            ("R", [1.0, 0.0, 0.0]),
+            ("G", [0.0, 1.0, 0.0]),
+            ("B", [0.0, 0.0, 1.0]),
+            ("C", [0.0, 1.0, 1.0]),
+            ("M", [1.0, 0.0, 1.0]),
+            ("Y", [1.0, 1.0, 0.0])
+        ];
+        for &(label, rgb) in &targets {
+            let (cb, cr) = chroma(rgb);
+            let x = left + SIZE * (0.5 + cb * REACH);
+            let y = top + SIZE * (0.5 - cr * REACH);
+            overlay.text(x - CHAR_SIZE * 0.5, y - CHAR_SIZE * 0.5, label, [rgb[0], rgb[1], rgb[2], 0.8]);
+        }
+    }
+
+    /// Plot `texture` over the backdrop. Call after the overlay is drawn.
+    pub fn draw<S: Surface>(&self, target: &mut S, texture: &Texture2d) -> Result<(), ShadeyError> {
+        let (width, height) = target.get_dimensions();
+        let (width, height) = (width as f32, height as f32);
+        // Clip space, from the bottom left.
+        let centre = (CHAR_SIZE + SIZE * 0.5, CHAR_SIZE + SIZE * 0.5);
+        let rect = [
+            centre.0 / width * 2.0 - 1.0,
+            centre.1 / height * 2.0 - 1.0,
+            SIZE * 0.5 * REACH / width * 2.0,
+            SIZE * 0.5 * REACH / height * 2.0
+        ];
+
+        let params = DrawParameters {
+            // Added up, so the hues that cover most of the output glow.
+            blend: Blend {
+                color: BlendingFunction::Addition {
+                    source: LinearBlendingFactor::SourceAlpha,
+                    destination: LinearBlendingFactor::One
+                },
+                alpha: BlendingFunction::AlwaysReplace,
+                constant_value: (0.0, 0.0, 0.0, 0.0)
+            },
+            ..Default::default()
+        };
+        let uniforms = uniform! {
+            tex: texture.sampled().minify_filter(MinifySamplerFilter::Nearest),
+            rect: rect
+        };
+        target.draw(&self.samples, NoIndices(PrimitiveType::Points), &self.program, &uniforms, &params).
+            map_err(|_| ShadeyError::Gl("Could not draw the vectorscope."))
+    }
+}
@@ -0,0 +1,19 @@
+#version 140
+
+in vec2 texel;
+out vec3 shade;
+
+uniform sampler2D tex;
+// Centre and half size of the scope in clip space.
+uniform vec4 rect;
+
+const vec3 LUMA = vec3(0.2126, 0.7152, 0.0722);
+
+void main() {
+    vec3 c = clamp(textureLod(tex, texel, 0.0).rgb, 0.0, 1.0);
+    float y = dot(c, LUMA);
+    // BT.709 Cb and Cr, from -0.5 to 0.5.
+    vec2 chroma = vec2((c.b - y) / 1.8556, (c.r - y) / 1.5748);
+    shade = c;
+    gl_Position = vec4(rect.xy + chroma * 2.0 * rect.zw, 0.0, 1.0);
+}
@@ -0,0 +1,289 @@
+//! `--scrub`: float literals tagged `/*@scrub*/`, e.g. `0.35/*@scrub*/`,
+//! become values listed in the top right of the window. Drag one sideways to
+//! change it while the shader runs, or pick one with the up and down keys and
+//! nudge it with left and right, holding shift for finer steps either way.
+//! Ctrl+S writes every value back into the shader files, so the source stays
+//! the only record of them.
+//!
+//! Each literal is compiled as a uniform of its own in place of the number.
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use error::ShadeyError;
+use overlay::{Overlay, CHAR_SIZE};
+
+const TAG: &str = "/*@scrub*/";
+
+/// How much a value changes per pixel dragged, and with shift held.
+const STEP: f32 = 0.01;
+const FINE_STEP: f32 = 0.001;
+/// How many pixels of dragging a press of left or right is worth.
+const KEY_PIXELS: f32 = 10.0;
+
+/// A tagged literal in a shader.
+pub struct Scrub {
+    /// The uniform it's compiled as.
+    pub name: String,
+    pub path: PathBuf,
+    /// Line in the file, counting from 1.
+    pub line: usize,
+    /// Which tagged literal of the file it is.
+    index: usize,
+    pub value: f32
+}
+
+/// Byte range and value of a tagged literal in some source.
+struct Literal {
+    start: usize,
+    end: usize,
+    value: f32
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Every literal followed by the tag. A leading minus counts as part of the
+/// literal where it can't be a subtraction.
+fn literals(source: &str) -> Vec<Literal> {
+    let mut found = Vec::new();
+    for (tag, _) in source.match_indices(TAG) {
+        let end = source[..tag].trim_end().len();
+        let bytes = source.as_bytes();
+        let mut start = end;
+        while start > 0 {
+            let c = bytes[start - 1] as char;
+            let exponent_sign = (c == '-' || c == '+') && start >= 2 &&
+                (bytes[start - 2] == b'e' || bytes[start - 2] == b'E');
+            if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                start -= 1;
+            } else {
+                break;
+            }
+        }
+        if start == end || source[..start].ends_with(is_identifier) {
+            continue;
+        }
+        if source[..start].ends_with('-') {
+            let before = source[..start - 1].trim_end();
+            if !before.ends_with(|c: char| is_identifier(c) || c == ')' || c == ']') {
+                start -= 1;
+            }
+        }
+
+        if let Ok(value) = source[start..end].parse() {
+            found.push(Literal { start, end, value });
+        }
+    }
+
+    found
+}
+
+/// The tagged literals of `source`, read from `path`. `first` numbers the
+/// uniforms on from those of earlier passes.
+pub fn find(source: &str, path: &Path, first: usize) -> Vec<Scrub> {
+    literals(source).into_iter().enumerate().map(|(index, literal)| Scrub {
+        name: format!("shadey_scrub{}", first + index),
+        path: path.to_path_buf(),
+        line: source[..literal.start].matches('\n').count() + 1,
+        index,
+        value: literal.value
+    }).collect()
+}
+
+/// Compile the tagged literals of `source` as uniforms instead, numbered from
+/// `first` as by `find`.
+pub fn rewrite(source: &str, first: usize) -> String {
+    let literals = literals(source);
+    if literals.is_empty() {
+        return source.to_string();
+    }
+
+    let mut body = String::new();
+    let mut copied = 0;
+    for (i, literal) in literals.iter().enumerate() {
+        body.push_str(&source[copied..literal.start]);
+        body.push_str(&format!("shadey_scrub{}", first + i));
+        copied = literal.end;
+    }
+    body.push_str(&source[copied..]);
+
+    // Declared straight after #version, which has to come first.
+    let declarations: String = (0..literals.len()).
+        map(|i| format!("uniform float shadey_scrub{};", first + i)).
+        collect::<Vec<_>>().
+        join(" ");
+    let version = body.lines().position(|line| line.trim_start().starts_with("#version"));
+    let mut output = String::new();
+    for (number, line) in body.lines().enumerate() {
+        if version.is_none() && number == 0 {
+            output.push_str(&format!("{}\n#line 1\n", declarations));
+        }
+        output.push_str(line);
+        output.push('\n');
+        if version == Some(number) {
+            output.push_str(&format!("{}\n#line {}\n", declarations, number + 2));
+        }
+    }
+
+    output
+}
+
+/// A GLSL float literal for `value`, to four decimal places at most.
+fn format_literal(value: f32) -> String {
+    let mut text = format!("{:.4}", value);
+    while text.ends_with('0') && !text.ends_with(".0") {
+        text.pop();
+    }
+    if text == "-0.0" { "0.0".to_string() } else { text }
+}
+
+/// Write the values of `scrubs` back over their literals.
+pub fn write_back(scrubs: &[Scrub]) -> Result<(), ShadeyError> {
+    let mut paths: Vec<&Path> = scrubs.iter().map(|scrub| scrub.path.as_path()).collect();
+    paths.sort();
+    paths.dedup();
+
+    for path in paths {
+        let mut source = String::new();
+        File::open(path).
+            and_then(|mut file| file.read_to_string(&mut source)).
+            map_err(|e| ShadeyError::io("Could not read shader", path, e))?;
+
+        let found = literals(&source);
+        let mut output = source.clone();
+        // From the end, so earlier byte ranges stay put.
+        for scrub in scrubs.iter().rev().filter(|scrub| scrub.path == path) {
+            let literal = found.get(scrub.index).ok_or_else(|| ShadeyError::invalid(format!(
+                "{} has changed since it was loaded. Reload it before saving scrubbed values.",
+                path.display()
+            )))?;
+            let mut text = format_literal(scrub.value);
+            // `x-0.5` mustn't become `x--0.5`.
+            if text.starts_with('-') && source[..literal.start].ends_with(['-', '+']) {
+                text.insert(0, ' ');
+            }
+            output.replace_range(literal.start..literal.end, &text);
+        }
+
+        File::create(path).
+            and_then(|mut file| file.write_all(output.as_bytes())).
+            map_err(|e| ShadeyError::io("Could not write shader", path, e))?;
+        println!("Saved scrubbed values to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// The listing of scrubbable values, and dragging them.
+#[derive(Default)]
+pub struct ScrubPanel {
+    /// Row being dragged.
+    dragging: Option<usize>,
+    /// Row picked with the up and down keys.
+    selected: Option<usize>,
+    /// Pixel rows of the listing as last drawn, `(top, height)`.
+    rows: (f32, f32),
+    left: f32
+}
+
+impl ScrubPanel {
+    pub fn new() -> ScrubPanel {
+        Default::default()
+    }
+
+    pub fn draw(&mut self, overlay: &mut Overlay, scrubs: &[Scrub], width: f32) {
+        if scrubs.is_empty() {
+            return;
+        }
+
+        let labels: Vec<String> = scrubs.iter().map(|scrub| format!(
+            "{}:{} {:>9.4}",
+            scrub.path.file_name().map_or_else(|| scrub.path.display().to_string(), |n| n.to_string_lossy().into_owned()),
+            scrub.line,
+            scrub.value
+        )).collect();
+        let columns = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+
+        let margin = CHAR_SIZE;
+        let line = CHAR_SIZE * 1.5;
+        let panel_width = (columns as f32 + 1.0) * CHAR_SIZE;
+        let left = (width - margin - panel_width).max(0.0);
+        self.rows = (margin + CHAR_SIZE * 0.25, line);
+        self.left = left;
+
+        overlay.rect(left, margin, panel_width, line * labels.len() as f32 + CHAR_SIZE * 0.5, [0.1, 0.1, 0.1, 0.8]);
+        for (i, label) in labels.iter().enumerate() {
+            let y = self.rows.0 + line * i as f32;
+            if self.dragging == Some(i) || self.selected == Some(i) {
+                overlay.rect(left, y - CHAR_SIZE * 0.25, panel_width, line, [0.25, 0.35, 0.6, 0.9]);
+            }
+            overlay.text(left + CHAR_SIZE * 0.5, y, label, [1.0, 1.0, 1.0, 1.0]);
+        }
+    }
+
+    /// Start dragging the row under `position`, in pixels from the top left.
+    /// Returns whether there was one.
+    pub fn press(&mut self, position: (f32, f32), scrubs: &[Scrub]) -> bool {
+        let (top, height) = self.rows;
+        if scrubs.is_empty() || height == 0.0 || position.0 < self.left || position.1 < top - CHAR_SIZE * 0.25 {
+            return false;
+        }
+
+        let row = ((position.1 - top + CHAR_SIZE * 0.25) / height) as usize;
+        if row >= scrubs.len() {
+            return false;
+        }
+        self.dragging = Some(row);
+        true
+    }
+
+    /// The value being dragged and its new value after moving `dx` pixels.
+    pub fn drag(&self, dx: f32, fine: bool, scrubs: &[Scrub]) -> Option<(usize, f32)> {
+        let row = self.dragging?;
+        let step = if fine { FINE_STEP } else { STEP };
+        scrubs.get(row).map(|scrub| (row, scrub.value + dx * step))
+    }
+
+    /// Pick the row `step` rows from the picked one, from the top if none is.
+    pub fn select(&mut self, step: isize, scrubs: &[Scrub]) {
+        if scrubs.is_empty() {
+            return;
+        }
+        let row = match self.selected {
+            Some(row) => (row as isize + step).rem_euclid(scrubs.len() as isize) as usize,
+            None => 0
+        };
+        self.selected = Some(row);
+    }
+
+    /// The picked value and its new value after nudging it in `direction`,
+    /// as if dragged.
+    pub fn nudge(&self, direction: f32, fine: bool, scrubs: &[Scrub]) -> Option<(usize, f32)> {
+        let row = self.selected?;
+        let step = if fine { FINE_STEP } else { STEP };
+        scrubs.get(row).map(|scrub| (row, scrub.value + direction * KEY_PIXELS * step))
+    }
+
+    /// The picked value, in words, for `--announce`.
+    pub fn describe(&self, scrubs: &[Scrub]) -> String {
+        match self.selected.and_then(|row| scrubs.get(row).map(|scrub| (row, scrub))) {
+            Some((row, scrub)) => format!(
+                "{}:{} = {:.4}, {} of {}",
+                scrub.path.display(),
+                scrub.line,
+                scrub.value,
+                row + 1,
+                scrubs.len()
+            ),
+            None => "none picked".to_string()
+        }
+    }
+
+    /// Stop dragging. Returns whether a row was being dragged.
+    pub fn release(&mut self) -> bool {
+        self.dragging.take().is_some()
+    }
+}
@@ -0,0 +1,180 @@
+//! Saved sessions, so a crash or reboot doesn't lose the working context.
+//!
+//! Every run keeps its tabs or project, window geometry, uniform values and
+//! playback time in `~/.local/state/shadey/sessions/last.toml`, or under
+//! another name with `--session=<name>`. `shadey --session=last` with no
+//! shader reopens it.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use error::ShadeyError;
+use stdin;
+use tabs::TabState;
+use {Args, Session};
+
+/// Name sessions are saved under without `--session`.
+pub const LAST: &str = "last";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedTab {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    shader: String,
+    /// Shader loaded from the command palette instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loaded: Option<String>,
+    #[serde(default)]
+    time: f64,
+    #[serde(default)]
+    uniforms: BTreeMap<String, f32>
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionFile {
+    #[serde(default)]
+    current: usize,
+    /// `[x, y, width, height]` of a window that isn't fullscreen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window: Option<[i32; 4]>,
+    #[serde(rename = "tab")]
+    tabs: Vec<SavedTab>
+}
+
+fn directory() -> Option<PathBuf> {
+    match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| Path::new(&home).join(".local/state"))
+    }.map(|dir| dir.join("shadey/sessions"))
+}
+
+fn path(name: &str) -> Result<PathBuf, ShadeyError> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(ShadeyError::invalid(format!("Invalid session name `{}`.", name)));
+    }
+    let dir = directory().ok_or_else(|| ShadeyError::invalid("Can't find a home directory to save sessions in."))?;
+
+    Ok(dir.join(format!("{}.toml", name)))
+}
+
+/// Paths are saved absolute, so sessions can be reopened from anywhere.
+fn absolute(path: &str) -> String {
+    fs::canonicalize(path).map(|path| path.display().to_string()).unwrap_or_else(|_| path.to_string())
+}
+
+fn to_state(tab: &SavedTab) -> TabState {
+    TabState {
+        uniforms: tab.uniforms.clone(),
+        shader: tab.loaded.as_ref().map(PathBuf::from),
+        time: tab.time
+    }
+}
+
+fn saved_tab(image: &str, shader: &str, state: Option<&TabState>) -> SavedTab {
+    SavedTab {
+        project: None,
+        image: absolute(image),
+        shader: absolute(shader),
+        loaded: state.and_then(|state| state.shader.as_ref()).map(|path| absolute(&path.to_string_lossy())),
+        time: state.map_or(0.0, |state| state.time),
+        uniforms: state.map(|state| state.uniforms.clone()).unwrap_or_default()
+    }
+}
+
+impl SessionFile {
+    pub fn load(name: &str) -> Result<SessionFile, ShadeyError> {
+        let path = path(name)?;
+        let mut contents = String::new();
+        File::open(&path).
+            and_then(|mut file| file.read_to_string(&mut contents)).
+            map_err(|e| ShadeyError::io("Could not open saved session", &path, e))?;
+
+        let file: SessionFile = toml::from_str(&contents).
+            map_err(|e| ShadeyError::Parse { path: path.clone(), message: e.to_string() })?;
+        if file.tabs.is_empty() {
+            return Err(ShadeyError::Parse { path, message: "the session has no tabs".to_string() });
+        }
+
+        Ok(file)
+    }
+
+    /// Point `args` at what the session had open.
+    pub fn apply(&self, args: &mut Args) {
+        match self.tabs[..] {
+            [SavedTab { project: Some(ref project), .. }] => args.flag_project = Some(project.clone()),
+            [ref tab] => {
+                args.arg_image = tab.image.clone();
+                args.arg_shader = tab.shader.clone();
+            },
+            ref tabs => {
+                args.flag_tab = tabs.len();
+                args.arg_tab_image = tabs.iter().map(|tab| tab.image.clone()).collect();
+                args.arg_tab_shader = tabs.iter().map(|tab| tab.shader.clone()).collect();
+            }
+        }
+    }
+
+    /// Carry the saved uniforms, time and window geometry over to `session`.
+    pub fn restore(&self, session: &mut Session) {
+        match session.tabs {
+            Some(ref mut tabs) => tabs.restore(self.current, self.tabs.iter().map(to_state).collect()),
+            None => session.resume = Some(to_state(&self.tabs[0]))
+        }
+        session.window = self.window;
+    }
+
+    /// What `session` has open right now, or none if it can't be reopened.
+    pub fn capture(args: &Args, session: &Session) -> Option<SessionFile> {
+        let live = TabState {
+            uniforms: session.uniforms.clone(),
+            shader: session.shader.clone(),
+            time: session.time
+        };
+
+        let (current, tabs) = match session.tabs {
+            Some(ref tabs) => {
+                let current = tabs.number() - 1;
+                let saved = tabs.saved().into_iter().enumerate().
+                    map(|(i, (image, shader, state))| {
+                        saved_tab(image, shader, if i == current { state.or(Some(&live)) } else { state })
+                    }).
+                    collect();
+                (current, saved)
+            },
+            None => match args.flag_project {
+                Some(ref project) => {
+                    let tab = SavedTab { project: Some(absolute(project)), ..saved_tab("", "", Some(&live)) };
+                    (0, vec![tab])
+                },
+                None if args.arg_shader == stdin::PATH => return None,
+                None => (0, vec![saved_tab(&args.arg_image, &args.arg_shader, Some(&live))])
+            }
+        };
+
+        Some(SessionFile { current, window: session.window, tabs })
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), ShadeyError> {
+        let path = path(name)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ShadeyError::io("Could not create", dir, e))?;
+        }
+        let contents = toml::to_string(self).
+            map_err(|e| ShadeyError::invalid(format!("Could not serialize the session: {}", e)))?;
+
+        // Written aside and renamed over, so a crash mid-write keeps the last one.
+        let partial = path.with_extension("toml.partial");
+        File::create(&partial).
+            and_then(|mut file| file.write_all(contents.as_bytes())).
+            and_then(|_| fs::rename(&partial, &path)).
+            map_err(|e| ShadeyError::io("Could not save the session to", &path, e))
+    }
+}
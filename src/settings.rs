@@ -0,0 +1,58 @@
+//! Per-shader settings, kept in a sidecar file next to the shader so that
+//! tweaks survive restarts. `blur.frag` stores its settings in
+//! `blur.frag.shadey`. A project keeps its own the same way, in
+//! `shadey.toml.shadey`, whatever shaders it runs.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use camera::Camera;
+use error::ShadeyError;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// Project uniforms set from the command palette.
+    #[serde(default)]
+    pub uniforms: BTreeMap<String, f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Camera>,
+    /// Output to open fullscreen on, see `shadey monitors`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>
+}
+
+fn sidecar_path(shader: &Path) -> PathBuf {
+    let mut name = shader.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".shadey");
+    shader.with_file_name(name)
+}
+
+impl Settings {
+    /// Settings saved for `shader`, or the defaults if there are none.
+    pub fn load(shader: &Path) -> Result<Settings, ShadeyError> {
+        let path = sidecar_path(shader);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(Default::default())
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).map_err(|e| ShadeyError::io("Could not read", &path, e))?;
+
+        toml::from_str(&contents).map_err(|e| ShadeyError::Parse { path, message: e.to_string() })
+    }
+
+    pub fn save(&self, shader: &Path) -> Result<(), ShadeyError> {
+        let path = sidecar_path(shader);
+        let contents = toml::to_string(self).
+            map_err(|e| ShadeyError::invalid(format!("Could not serialize shader settings: {}", e)))?;
+
+        File::create(&path).
+            and_then(|mut file| file.write_all(contents.as_bytes())).
+            map_err(|e| ShadeyError::io("Could not write", &path, e))
+    }
+}
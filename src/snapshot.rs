@@ -0,0 +1,88 @@
+//! Snapshots of the tweakable state, saved with Ctrl+0-9 and recalled with
+//! 0-9.
+//!
+//! In project mode they're stored as `[snapshots.N]` sections at the end of
+//! the project file. Those sections are rewritten on every save; the rest of
+//! the file is left untouched.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use glium::glutin::VirtualKeyCode;
+use toml;
+
+use camera::Camera;
+use error::ShadeyError;
+
+/// Number of snapshot slots, one per digit key.
+pub const SLOTS: u32 = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub uniforms: BTreeMap<String, f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Camera>
+}
+
+#[derive(Serialize)]
+struct SnapshotSections<'a> {
+    snapshots: BTreeMap<String, &'a Snapshot>
+}
+
+/// The slot a digit key saves to or recalls from.
+pub fn slot(key: VirtualKeyCode) -> Option<u32> {
+    let slot = match key {
+        VirtualKeyCode::Key0 => 0,
+        VirtualKeyCode::Key1 => 1,
+        VirtualKeyCode::Key2 => 2,
+        VirtualKeyCode::Key3 => 3,
+        VirtualKeyCode::Key4 => 4,
+        VirtualKeyCode::Key5 => 5,
+        VirtualKeyCode::Key6 => 6,
+        VirtualKeyCode::Key7 => 7,
+        VirtualKeyCode::Key8 => 8,
+        VirtualKeyCode::Key9 => 9,
+        _ => return None
+    };
+
+    Some(slot)
+}
+
+fn is_snapshot_header(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("[snapshots]") || line.starts_with("[snapshots.")
+}
+
+/// Replace the snapshot sections of the TOML file at `path` with `snapshots`.
+pub fn write(path: &Path, snapshots: &BTreeMap<u32, Snapshot>) -> Result<(), ShadeyError> {
+    let mut contents = String::new();
+    File::open(path).
+        and_then(|mut file| file.read_to_string(&mut contents)).
+        map_err(|e| ShadeyError::io("Could not read project", path, e))?;
+
+    let mut kept = String::new();
+    let mut in_snapshot = false;
+    for line in contents.lines() {
+        if line.trim_start().starts_with('[') {
+            in_snapshot = is_snapshot_header(line);
+        }
+        if !in_snapshot {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+
+    let sections = SnapshotSections {
+        snapshots: snapshots.iter().map(|(slot, snapshot)| (slot.to_string(), snapshot)).collect()
+    };
+    let serialized = toml::to_string(&sections).
+        map_err(|e| ShadeyError::invalid(format!("Could not serialize snapshots: {}", e)))?;
+    let output = format!("{}\n{}", kept.trim_end(), serialized);
+
+    File::create(path).
+        and_then(|mut file| file.write_all(output.as_bytes())).
+        map_err(|e| ShadeyError::io("Could not write snapshots to", path, e))
+}
@@ -0,0 +1,80 @@
+//! Reads fragment shader source from stdin, for editors that push their
+//! buffer on every change instead of saving it.
+//!
+//! Each shader ends at a NUL byte or at a line holding only `// shadey-end`.
+
+use std::io::{self, BufRead};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+/// Shader path that means "read the shader from stdin".
+pub const PATH: &str = "-";
+const MARKER: &str = "// shadey-end";
+
+pub struct ShaderStream {
+    receiver: Receiver<String>,
+    latest: Option<String>
+}
+
+/// Send the shader collected so far, returning false once nobody's listening.
+fn send(sender: &Sender<String>, current: &mut Vec<u8>) -> bool {
+    let source = String::from_utf8_lossy(current).into_owned();
+    current.clear();
+    source.trim().is_empty() || sender.send(source).is_ok()
+}
+
+fn read_shaders(sender: Sender<String>) {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut current = Vec::new();
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        match input.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => ()
+        }
+
+        let mut segments: Vec<&[u8]> = line.split(|&b| b == 0).collect();
+        let last = segments.pop().unwrap_or(&[]);
+        for segment in segments {
+            current.extend_from_slice(segment);
+            if !send(&sender, &mut current) {
+                return;
+            }
+        }
+
+        if String::from_utf8_lossy(last).trim() == MARKER {
+            if !send(&sender, &mut current) {
+                return;
+            }
+        } else {
+            current.extend_from_slice(last);
+        }
+    }
+
+    send(&sender, &mut current);
+}
+
+impl ShaderStream {
+    pub fn spawn() -> ShaderStream {
+        let (sender, receiver) = channel();
+        thread::spawn(move || read_shaders(sender));
+
+        ShaderStream { receiver, latest: None }
+    }
+
+    /// The newest shader received since the last call, if any. Older ones
+    /// that were never picked up are skipped.
+    pub fn poll(&mut self) -> Option<&str> {
+        let newest = self.receiver.try_iter().last()?;
+        self.latest = Some(newest);
+        self.latest.as_deref()
+    }
+
+    /// The newest shader received so far.
+    pub fn latest(&self) -> Option<&str> {
+        self.latest.as_deref()
+    }
+}
@@ -0,0 +1,141 @@
+//! `shadey sweep`: renders a shader across a range of values for one or two
+//! uniforms, saving each render and a labelled contact sheet of them all.
+//!
+//! The first `--param` varies along the columns and the second, if any,
+//! along the rows.
+
+use std::fs;
+use std::path::PathBuf;
+
+use font8x8::legacy::BASIC_LEGACY;
+use glium::glutin;
+use image::{imageops, Rgba, RgbaImage};
+
+use constants;
+use error::ShadeyError;
+use hash;
+use numbers::Decimal;
+use offline;
+use pipeline::Pipeline;
+use project::Project;
+use recorder::texture_image;
+use Args;
+
+struct Param {
+    name: String,
+    values: Vec<f32>
+}
+
+/// Parse `name=start..end:steps`.
+fn parse_param(spec: &str, decimal: Decimal) -> Result<Param, ShadeyError> {
+    let invalid = || ShadeyError::invalid(format!(
+        "Invalid sweep parameter `{}`. Expected e.g. radius=0..1:11.",
+        spec
+    ));
+    let (name, range) = spec.split_once('=').ok_or_else(invalid)?;
+    let (bounds, steps) = range.split_once(':').ok_or_else(invalid)?;
+    let (start, end) = bounds.split_once("..").ok_or_else(invalid)?;
+    let start: f32 = decimal.parse(start).map_err(|_| invalid())?;
+    let end: f32 = decimal.parse(end).map_err(|_| invalid())?;
+    let steps: usize = steps.trim().parse().map_err(|_| invalid())?;
+    if name.trim().is_empty() || steps == 0 {
+        return Err(invalid());
+    }
+
+    let values = (0..steps).
+        map(|i| if steps == 1 { start } else { start + (end - start) * i as f32 / (steps - 1) as f32 }).
+        collect();
+
+    Ok(Param { name: name.trim().to_string(), values })
+}
+
+/// Draw `text` onto `img` on a dark strip, two texels per font pixel.
+fn label(img: &mut RgbaImage, x: u32, y: u32, text: &str) {
+    const SCALE: u32 = 2;
+    let width = (text.len() as u32 * 8 + 4) * SCALE;
+    let height = 12 * SCALE;
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            img.put_pixel(px, py, Rgba([0, 0, 0, 200]));
+        }
+    }
+
+    for (i, c) in text.chars().enumerate() {
+        let glyph = BASIC_LEGACY[if (c as usize) < BASIC_LEGACY.len() { c as usize } else { '?' as usize }];
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..8 {
+                if bits & (1 << column) == 0 {
+                    continue;
+                }
+                let gx = x + (2 + i as u32 * 8 + column) * SCALE;
+                let gy = y + (2 + row as u32) * SCALE;
+                for (dx, dy) in (0..SCALE).flat_map(|dx| (0..SCALE).map(move |dy| (dx, dy))) {
+                    if gx + dx < img.width() && gy + dy < img.height() {
+                        img.put_pixel(gx + dx, gy + dy, Rgba([255, 255, 255, 255]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), ShadeyError> {
+    let params = args.flag_param.iter().map(|spec| parse_param(spec, args.decimal())).collect::<Result<Vec<_>, _>>()?;
+    if params.is_empty() || params.len() > 2 {
+        return Err(ShadeyError::invalid("A sweep takes one or two --param options."));
+    }
+    let size = offline::parse_size(&args.flag_size)?;
+    let constants = constants::parse_all(&args.flag_uniform, args.decimal())?;
+    let output = PathBuf::from(args.flag_output.as_ref().map_or("sweep", String::as_str));
+    fs::create_dir_all(&output).map_err(|e| ShadeyError::io("Could not create", &output, e))?;
+
+    let mut project = Project::single(args.flag_image.as_ref().map_or("", String::as_str), &args.arg_shader);
+    for param in &params {
+        project.uniforms.insert(param.name.clone(), param.values[0]);
+    }
+
+    let events_loop = glutin::EventsLoop::new();
+    let display = offline::hidden_display(&events_loop, args)?;
+    let texture = offline::input_texture(&display, args)?;
+    let mut pipeline = Pipeline::new(&display, &project)?;
+    pipeline.fix_size(size);
+    pipeline.set_constants(constants)?;
+    let inputs = offline::still_inputs(args);
+
+    let columns = &params[0];
+    let rows: Vec<Option<(&str, f32)>> = match params.get(1) {
+        Some(param) => param.values.iter().map(|&v| Some((param.name.as_str(), v))).collect(),
+        None => vec![None]
+    };
+    let mut sheet = RgbaImage::new(size.0 * columns.values.len() as u32, size.1 * rows.len() as u32);
+
+    for (row, row_param) in rows.iter().enumerate() {
+        for (column, &value) in columns.values.iter().enumerate() {
+            let mut settings = vec![(columns.name.as_str(), value)];
+            settings.extend(row_param.iter().cloned());
+            for &(name, value) in &settings {
+                pipeline.set_uniform(name, value);
+            }
+            pipeline.invalidate();
+            pipeline.render(&display, &texture, &inputs)?;
+
+            let cell = texture_image(pipeline.output())?;
+            let name: Vec<String> = settings.iter().map(|&(name, value)| format!("{}={:.3}", name, value)).collect();
+            let path = output.join(format!("{}.png", name.join("_")));
+            cell.save(&path).map_err(|e| ShadeyError::io("Could not write", &path, e))?;
+            if args.flag_hash {
+                println!("{} {}", name.join("_"), hash::describe(&cell));
+            }
+
+            let (x, y) = (column as u32 * size.0, row as u32 * size.1);
+            imageops::replace(&mut sheet, &cell, x, y);
+            label(&mut sheet, x, y, &name.join(" "));
+        }
+    }
+
+    let path = output.join("sheet.png");
+    sheet.save(&path).map_err(|e| ShadeyError::io("Could not write", &path, e))?;
+    println!("Wrote {} renders and {}", columns.values.len() * rows.len(), path.display());
+
+    Ok(())
+}
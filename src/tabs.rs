@@ -0,0 +1,93 @@
+//! `--tab <image> <shader>`, given more than once, runs several shaders in
+//! one window, switched between with Ctrl+Tab and Ctrl+Shift+Tab. Each tab
+//! keeps its own input image, uniform values and time while it's hidden.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use error::ShadeyError;
+use project::Project;
+use stdin;
+use Args;
+
+/// What a tab was doing when it was switched away from.
+#[derive(Default)]
+pub struct TabState {
+    pub uniforms: BTreeMap<String, f32>,
+    /// Shader picked from the command palette.
+    pub shader: Option<PathBuf>,
+    pub time: f64
+}
+
+struct Tab {
+    image: String,
+    shader: String,
+    state: Option<TabState>
+}
+
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    current: usize
+}
+
+impl Tabs {
+    pub fn from_args(args: &Args) -> Result<Option<Tabs>, ShadeyError> {
+        if args.flag_tab == 0 {
+            return Ok(None);
+        }
+        if args.flag_project.is_some() {
+            return Err(ShadeyError::invalid("--tab can't be combined with --project."));
+        }
+
+        // A `-` shader makes docopt read `--tab` as a bare flag instead.
+        if args.arg_tab_shader.len() != args.flag_tab || args.arg_tab_shader.iter().any(|s| s == stdin::PATH) {
+            return Err(ShadeyError::invalid("Each --tab needs an image and a shader file; tabs can't use stdin."));
+        }
+
+        let tabs = args.arg_tab_image.iter().zip(&args.arg_tab_shader).
+            map(|(image, shader)| Tab { image: image.clone(), shader: shader.clone(), state: None }).
+            collect();
+
+        Ok(Some(Tabs { tabs, current: 0 }))
+    }
+
+    pub fn project(&self) -> Project {
+        let tab = &self.tabs[self.current];
+        Project::single(&tab.image, &tab.shader)
+    }
+
+    /// Take the state the current tab was left in, if it's been shown before.
+    pub fn resume(&mut self) -> Option<TabState> {
+        self.tabs[self.current].state.take()
+    }
+
+    /// Put away the current tab's state and move `step` tabs along, wrapping
+    /// around at either end.
+    pub fn switch(&mut self, step: isize, state: TabState) {
+        self.tabs[self.current].state = Some(state);
+        let count = self.tabs.len() as isize;
+        self.current = (self.current as isize + step).rem_euclid(count) as usize;
+
+        let tab = &self.tabs[self.current];
+        println!("Tab {}/{}: {} {}", self.current + 1, count, tab.image, tab.shader);
+    }
+
+    /// Pick up from a saved session: `states` are in tab order.
+    pub fn restore(&mut self, current: usize, states: Vec<TabState>) {
+        for (tab, state) in self.tabs.iter_mut().zip(states) {
+            tab.state = Some(state);
+        }
+        self.current = current.min(self.tabs.len() - 1);
+    }
+
+    /// The image, shader and put-away state of every tab. The current tab
+    /// has none once it's been resumed, as its state is live.
+    pub fn saved(&self) -> Vec<(&str, &str, Option<&TabState>)> {
+        self.tabs.iter().map(|tab| (tab.image.as_str(), tab.shader.as_str(), tab.state.as_ref())).collect()
+    }
+
+    /// The current tab, counting from 1.
+    pub fn number(&self) -> usize {
+        self.current + 1
+    }
+}
@@ -0,0 +1,18 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+// The input image, and the output size in pixels.
+uniform sampler2D tex;
+uniform vec3 iResolution;
+// Seconds since the shader was loaded, and the number of frames rendered.
+uniform float iTime;
+uniform int iFrame;
+
+void main() {
+    vec4 source = texture(tex, pos);
+    float luma = dot(source.rgb, vec3(0.299, 0.587, 0.114));
+
+    color = vec4(vec3(luma), source.a);
+}
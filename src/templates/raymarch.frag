@@ -0,0 +1,59 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec3 iResolution;
+uniform float iTime;
+uniform int iFrame;
+
+const int MAX_STEPS = 128;
+const float MAX_DIST = 100.0;
+const float SURFACE_DIST = 0.001;
+
+float scene(vec3 p) {
+    float sphere = length(p - vec3(0.0, 1.0, 6.0)) - 1.0;
+    float plane = p.y;
+
+    return min(sphere, plane);
+}
+
+float march(vec3 origin, vec3 dir) {
+    float dist = 0.0;
+    for (int i = 0; i < MAX_STEPS; i++) {
+        float d = scene(origin + dir * dist);
+        dist += d;
+        if (d < SURFACE_DIST || dist > MAX_DIST) {
+            break;
+        }
+    }
+
+    return dist;
+}
+
+vec3 normal(vec3 p) {
+    vec2 e = vec2(0.001, 0.0);
+
+    return normalize(vec3(
+        scene(p + e.xyy) - scene(p - e.xyy),
+        scene(p + e.yxy) - scene(p - e.yxy),
+        scene(p + e.yyx) - scene(p - e.yyx)
+    ));
+}
+
+void main() {
+    vec2 uv = (gl_FragCoord.xy - 0.5 * iResolution.xy) / iResolution.y;
+    vec3 origin = vec3(0.0, 1.0, 0.0);
+    vec3 dir = normalize(vec3(uv, 1.0));
+
+    float dist = march(origin, dir);
+    vec3 col = vec3(0.0);
+    if (dist < MAX_DIST) {
+        vec3 p = origin + dir * dist;
+        vec3 light = normalize(vec3(sin(iTime), 2.0, cos(iTime) + 3.0) - p);
+        col = vec3(clamp(dot(normal(p), light), 0.0, 1.0));
+    }
+
+    color = vec4(col, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 140
+
+in vec2 pos;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec3 iResolution;
+uniform float iTime;
+uniform int iFrame;
+
+// Write your shader as you would on Shadertoy, with `tex` in place of
+// iChannel0.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 col = 0.5 + 0.5 * cos(iTime + uv.xyx + vec3(0.0, 2.0, 4.0));
+
+    fragColor = vec4(col, 1.0);
+}
+
+void main() {
+    mainImage(color, gl_FragCoord.xy);
+}
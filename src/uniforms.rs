@@ -0,0 +1,26 @@
+use glium::uniforms::{Uniforms, UniformValue};
+
+/// A uniform set built at runtime, for when the names of the uniforms aren't
+/// known at compile time (e.g. the outputs of project passes).
+#[derive(Default)]
+pub struct UniformSet<'a> {
+    values: Vec<(String, UniformValue<'a>)>
+}
+
+impl<'a> UniformSet<'a> {
+    pub fn new() -> UniformSet<'a> {
+        UniformSet { values: Vec::new() }
+    }
+
+    pub fn add(&mut self, name: &str, value: UniformValue<'a>) {
+        self.values.push((name.to_string(), value));
+    }
+}
+
+impl<'a> Uniforms for UniformSet<'a> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        for &(ref name, value) in &self.values {
+            output(name, value);
+        }
+    }
+}
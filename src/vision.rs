@@ -0,0 +1,72 @@
+//! Simulated colour vision deficiencies, for checking that visualizations
+//! stay legible to everyone. F3 cycles through them. They're applied as the
+//! frame is presented, so recordings and the clean feed show them as well,
+//! but shaders and screenshots never see them.
+//!
+//! The matrices are Machado, Oliveira and Fernandes' (2009) at full severity,
+//! applied to linear RGB.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorVision {
+    Normal,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia
+}
+
+pub const ALL: [ColorVision; 4] = [
+    ColorVision::Normal,
+    ColorVision::Protanopia,
+    ColorVision::Deuteranopia,
+    ColorVision::Tritanopia
+];
+
+impl ColorVision {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorVision::Normal => "normal",
+            ColorVision::Protanopia => "protanopia",
+            ColorVision::Deuteranopia => "deuteranopia",
+            ColorVision::Tritanopia => "tritanopia"
+        }
+    }
+
+    /// The one F3 switches to next.
+    pub fn next(self) -> ColorVision {
+        let index = ALL.iter().position(|&vision| vision == self).unwrap_or(0);
+        ALL[(index + 1) % ALL.len()]
+    }
+
+    /// Row-major matrix taking linear RGB to what's seen, or none for normal
+    /// vision.
+    fn rows(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            ColorVision::Normal => None,
+            ColorVision::Protanopia => Some([
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998]
+            ]),
+            ColorVision::Deuteranopia => Some([
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881]
+            ]),
+            ColorVision::Tritanopia => Some([
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900]
+            ])
+        }
+    }
+
+    /// The matrix as GLSL wants it, column by column, or none for normal
+    /// vision.
+    pub fn matrix(self) -> Option<[[f32; 3]; 3]> {
+        self.rows().map(|m| [
+            [m[0][0], m[1][0], m[2][0]],
+            [m[0][1], m[1][1], m[2][1]],
+            [m[0][2], m[1][2], m[2][2]]
+        ])
+    }
+}
@@ -0,0 +1,231 @@
+//! Watches a project's input files for changes.
+//!
+//! The parent directories are watched rather than the files themselves, so
+//! that editors which save by writing a temporary file and renaming it over
+//! the original are noticed as well.
+//!
+//! Shaders and the project file only count as changed when their contents
+//! do, so saving an untouched file doesn't throw away feedback buffers and
+//! the rest of a running simulation. A shader is compared as the pipeline
+//! compiles it, after `#pragma shadey` lines are expanded, and its comments
+//! and whitespace are ignored, except in scrub mode, where values are
+//! written back by position.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use inotify::{event_mask, watch_mask, Inotify, WatchDescriptor};
+
+use error::ShadeyError;
+use header;
+use project::Project;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Change {
+    /// Only the input image changed; it can be swapped in place.
+    Image,
+    /// A shader or the project file changed; everything must be rebuilt.
+    Project
+}
+
+struct Target {
+    path: PathBuf,
+    dir: WatchDescriptor,
+    name: OsString,
+    change: Change,
+    /// Whether only the tokens the file compiles to matter, not how they're
+    /// spaced or commented.
+    spacing_free: bool,
+    /// Hash of the contents last seen, if the file could be read.
+    digest: Option<u64>
+}
+
+pub struct Watcher {
+    inotify: Inotify,
+    targets: Vec<Target>
+}
+
+fn split(path: &Path) -> Result<(PathBuf, OsString), ShadeyError> {
+    let name = path.file_name().
+        ok_or_else(|| ShadeyError::invalid(format!("Can't watch {}, it has no file name.", path.display())))?;
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => PathBuf::from(".")
+    };
+
+    Ok((dir, name.to_os_string()))
+}
+
+/// `source` with its comments blanked out. Newlines in block comments are
+/// kept, as preprocessor directives end at them.
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some(&'/')) => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            },
+            ('/', Some(&'*')) => {
+                chars.next();
+                // Tokens either side stay apart, as they do when compiled.
+                output.push(' ');
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    if c == '\n' {
+                        output.push('\n');
+                    }
+                    last = c;
+                }
+            },
+            _ => output.push(c)
+        }
+    }
+    output
+}
+
+fn digest_source(contents: &str, path: &Path, spacing_free: bool) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    if spacing_free {
+        let compiled = strip_comments(&header::inject(contents, &path.display().to_string()).ok()?);
+        // Lines are kept apart, as preprocessor directives end at them.
+        // The `#line`s after expanded pragmas only say where errors are.
+        for line in compiled.lines().filter(|line| !line.trim_start().starts_with("#line")) {
+            let mut words = line.split_whitespace().peekable();
+            if words.peek().is_some() {
+                words.for_each(|word| word.hash(&mut hasher));
+                '\n'.hash(&mut hasher);
+            }
+        }
+    } else {
+        contents.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+fn digest(path: &Path, spacing_free: bool) -> Option<u64> {
+    digest_source(&fs::read_to_string(path).ok()?, path, spacing_free)
+}
+
+impl Watcher {
+    pub fn new(project: &Project) -> Result<Watcher, ShadeyError> {
+        let mut inotify = Inotify::init().
+            map_err(|error| ShadeyError::Io { action: "Could not initialize inotify", path: None, error })?;
+        let mut dirs: HashMap<PathBuf, WatchDescriptor> = HashMap::new();
+        let mut targets = Vec::new();
+
+        for path in &project.watched {
+            let (dir, name) = split(path)?;
+            let wd = match dirs.get(&dir) {
+                Some(&wd) => wd,
+                None => {
+                    let mask = watch_mask::CLOSE_WRITE | watch_mask::MODIFY |
+                        watch_mask::CREATE | watch_mask::MOVED_TO;
+                    inotify.add_watch(&dir, mask).
+                        map_err(|e| ShadeyError::io("Could not watch", &dir, e))?
+                }
+            };
+            dirs.insert(dir, wd);
+
+            let change = if *path == project.image { Change::Image } else { Change::Project };
+            let spacing_free = !project.scrub && project.passes.iter().any(|pass| pass.shader == *path);
+            let digest = match change {
+                Change::Project => digest(path, spacing_free),
+                Change::Image => None
+            };
+            targets.push(Target { path: path.clone(), dir: wd, name, change, spacing_free, digest });
+        }
+
+        Ok(Watcher { inotify, targets })
+    }
+
+    /// The most significant change since the last poll, if any.
+    pub fn poll(&mut self) -> Result<Option<Change>, ShadeyError> {
+        self.read(None)
+    }
+
+    /// Like `poll`, but ignores changes to `path`. For use straight after
+    /// shadey has written `path` itself.
+    pub fn poll_except(&mut self, path: &Path) -> Result<Option<Change>, ShadeyError> {
+        self.read(Some(path))
+    }
+
+    fn read(&mut self, ignored: Option<&Path>) -> Result<Option<Change>, ShadeyError> {
+        let mut event_buffer = [0; 4096];
+        let events = self.inotify.read_events(&mut event_buffer).
+            map_err(|error| ShadeyError::Io { action: "Could not read inotify events", path: None, error })?;
+
+        let mut change = None;
+        for event in events {
+            let relevant = event_mask::CLOSE_WRITE | event_mask::MODIFY |
+                event_mask::CREATE | event_mask::MOVED_TO;
+            if !event.mask.intersects(relevant) {
+                continue;
+            }
+
+            let targets = self.targets.iter_mut().
+                filter(|target| target.dir == event.wd && target.name == event.name).
+                filter(|target| ignored != Some(target.path.as_path()));
+            for target in targets {
+                if target.change == Change::Project {
+                    let digest = digest(&target.path, target.spacing_free);
+                    if digest.is_some() && digest == target.digest {
+                        continue;
+                    }
+                    target.digest = digest;
+                }
+                if change.is_none_or(|c| target.change > c) {
+                    change = Some(target.change);
+                }
+            }
+        }
+
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::digest_source;
+
+    const SOURCE: &str = "#version 140\n\
+        uniform float speed;\n\
+        out vec4 color;\n\
+        void main() {\n\
+            color = vec4(speed);\n\
+        }\n";
+
+    fn digest(source: &str) -> Option<u64> {
+        digest_source(source, Path::new("test.frag"), true)
+    }
+
+    #[test]
+    fn comment_edits_keep_the_digest() {
+        let commented = SOURCE.
+            replace("uniform float speed;", "// How fast it goes.\nuniform float speed; // units per second").
+            replace("void main() {", "/* The entry\n   point. */ void main() {");
+        assert_eq!(digest(SOURCE), digest(&commented));
+    }
+
+    #[test]
+    fn code_edits_change_the_digest() {
+        assert_ne!(digest(SOURCE), digest(&SOURCE.replace("vec4(speed)", "vec4(speed * 2.0)")));
+    }
+
+    #[test]
+    fn block_comments_separate_tokens() {
+        assert_eq!(digest(SOURCE), digest(&SOURCE.replace("vec4 color", "vec4/**/color")));
+        assert_ne!(digest(SOURCE), digest(&SOURCE.replace("out vec4", "ou/**/t vec4")));
+    }
+}
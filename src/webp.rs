@@ -0,0 +1,142 @@
+//! `render --webp=<file>` encoded in process with libwebp, when shadey is
+//! built with the `webp` feature. libwebp encodes each frame as a still and
+//! the stills are put together into an animation here, so only its simple
+//! API is needed and not libwebpmux.
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::os::raw::{c_int, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+
+use image::RgbaImage;
+
+use error::ShadeyError;
+
+/// From 0 to 100, as ffmpeg's default for libwebp.
+const QUALITY: f32 = 80.0;
+/// Largest width or height a WebP can have.
+const MAX_SIZE: u32 = 16383;
+
+#[link(name = "webp")]
+extern "C" {
+    fn WebPEncodeRGB(
+        rgb: *const u8,
+        width: c_int,
+        height: c_int,
+        stride: c_int,
+        quality: f32,
+        output: *mut *mut u8
+    ) -> usize;
+    fn WebPFree(ptr: *mut c_void);
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8]
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16 | u32::from(bytes[3]) << 24
+}
+
+/// Append a RIFF chunk, padded to an even length.
+fn chunk(out: &mut Vec<u8>, id: &[u8; 4], payload: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// The chunks of a still WebP that hold its image, as they are.
+fn image_chunks(still: &[u8]) -> Vec<u8> {
+    // After `RIFF <size> WEBP`.
+    let mut rest = still.get(12..).unwrap_or(&[]);
+    let mut chunks = Vec::new();
+    while rest.len() >= 8 {
+        let length = le32(&rest[4..8]) as usize;
+        let end = (8 + length + length % 2).min(rest.len());
+        // The animation has its own header.
+        if &rest[..4] != b"VP8X" {
+            chunks.extend_from_slice(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+pub struct WebPWriter {
+    file: File,
+    path: PathBuf,
+    size: (u32, u32),
+    fps: f32,
+    /// The frames' chunks so far. They're written at the end, when the size
+    /// that heads the file is known.
+    frames: Vec<u8>,
+    count: u32
+}
+
+impl WebPWriter {
+    pub fn new(path: &Path, size: (u32, u32), fps: f32) -> Result<WebPWriter, ShadeyError> {
+        if size.0 > MAX_SIZE || size.1 > MAX_SIZE {
+            return Err(ShadeyError::invalid(format!("WebPs can be at most {} pixels across.", MAX_SIZE)));
+        }
+        let file = File::create(path).map_err(|e| ShadeyError::io("Could not create", path, e))?;
+
+        Ok(WebPWriter { file, path: path.to_path_buf(), size, fps, frames: Vec::new(), count: 0 })
+    }
+
+    /// When frame `index` starts, in milliseconds. Rounding each start rather
+    /// than each duration keeps the animation from drifting.
+    fn start(&self, index: u32) -> u32 {
+        (f64::from(index) * 1000.0 / f64::from(self.fps)).round() as u32
+    }
+
+    pub fn add(&mut self, img: &RgbaImage) -> Result<(), ShadeyError> {
+        let (width, height) = self.size;
+        // All without alpha, as with GIFs.
+        let rgb: Vec<u8> = img.chunks(4).flat_map(|pixel| pixel[..3].iter().cloned()).collect();
+        let mut output = ptr::null_mut();
+        let length = unsafe {
+            WebPEncodeRGB(rgb.as_ptr(), width as c_int, height as c_int, (width * 3) as c_int, QUALITY, &mut output)
+        };
+        if length == 0 {
+            return Err(ShadeyError::invalid(format!("libwebp could not encode a frame of {}.", self.path.display())));
+        }
+        let data = image_chunks(unsafe { slice::from_raw_parts(output, length) });
+        unsafe { WebPFree(output as *mut c_void) };
+
+        let duration = (self.start(self.count + 1) - self.start(self.count)).clamp(1, 0xff_ffff);
+        let mut frame = Vec::with_capacity(16 + data.len());
+        // At the top left, covering the canvas, and replacing what was there.
+        frame.extend_from_slice(&u24(0));
+        frame.extend_from_slice(&u24(0));
+        frame.extend_from_slice(&u24(width - 1));
+        frame.extend_from_slice(&u24(height - 1));
+        frame.extend_from_slice(&u24(duration));
+        frame.push(0b10);
+        frame.extend_from_slice(&data);
+        chunk(&mut self.frames, b"ANMF", &frame);
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), ShadeyError> {
+        let (width, height) = self.size;
+        let mut body = b"WEBP".to_vec();
+        let mut header = vec![0b10, 0, 0, 0];
+        header.extend_from_slice(&u24(width - 1));
+        header.extend_from_slice(&u24(height - 1));
+        chunk(&mut body, b"VP8X", &header);
+        // An opaque black background, and looping forever.
+        chunk(&mut body, b"ANIM", &[0, 0, 0, 255, 0, 0]);
+        body.extend_from_slice(&self.frames);
+
+        let mut riff = Vec::with_capacity(body.len() + 8);
+        chunk(&mut riff, b"RIFF", &body);
+        let path = &self.path;
+        self.file.write_all(&riff).map_err(|e| ShadeyError::io("Could not write", path, e))
+    }
+}